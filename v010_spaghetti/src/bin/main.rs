@@ -1,9 +1,105 @@
-use std::{self, collections::HashMap, io};
+use std::{self, collections::HashMap, env, io};
+
+#[derive(Clone, Copy)]
+enum Language {
+    Fr,
+    En,
+}
+
+impl Language {
+    fn from_locale(locale: &str) -> Option<Language> {
+        match locale.get(..2).map(|code| code.to_lowercase()).as_deref() {
+            Some("fr") => Some(Language::Fr),
+            Some("en") => Some(Language::En),
+            _ => None,
+        }
+    }
 
+    fn detect() -> Language {
+        let args: Vec<String> = env::args().collect();
+        if let Some(position) = args.iter().position(|arg| arg == "--lang") {
+            if let Some(language) = args.get(position + 1).and_then(|code| Language::from_locale(code)) {
+                return language;
+            }
+        }
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|variable| env::var(variable).ok())
+            .find_map(|locale| Language::from_locale(&locale))
+            .unwrap_or(Language::Fr)
+    }
+}
+
+enum Keyword {
+    Vote,
+    Voters,
+    Scores,
+}
+
+impl Keyword {
+    fn word(&self, language: Language) -> &'static str {
+        match (self, language) {
+            (Keyword::Vote, Language::Fr) => "voter",
+            (Keyword::Vote, Language::En) => "vote",
+            (Keyword::Voters, Language::Fr) => "votants",
+            (Keyword::Voters, Language::En) => "voters",
+            (Keyword::Scores, _) => "scores",
+        }
+    }
+}
+
+enum Message<'a> {
+    Help,
+    MissingVoter,
+    HasAlreadyVoted(&'a str),
+    HasVoted(&'a str),
+    VoteAdded(&'a str),
+    UnknownCandidate(&'a str),
+    BlankVoteAdded,
+    VotersTitle,
+    ScoresTitle,
+    InvalidCommand,
+    Candidate(&'a str),
+}
+
+impl Message<'_> {
+    fn text(&self, language: Language) -> String {
+        match (self, language) {
+            (Message::Help, Language::Fr) => format!(
+                "\n -{vote} <votant> <candidat> : voter pour un candidat\n\n -{vote} <votant> : vote nul\n\n -{voters} : voir les votants\n\n -{scores} : voir les scores",
+                vote = Keyword::Vote.word(language), voters = Keyword::Voters.word(language), scores = Keyword::Scores.word(language)),
+            (Message::Help, Language::En) => format!(
+                "\n -{vote} <voter> <candidate> : vote for a candidate\n\n -{vote} <voter> : blank vote\n\n -{voters} : list the voters\n\n -{scores} : show the scores",
+                vote = Keyword::Vote.word(language), voters = Keyword::Voters.word(language), scores = Keyword::Scores.word(language)),
+            (Message::MissingVoter, Language::Fr) => String::from("Veuillez entrer au moins le nom du votant"),
+            (Message::MissingVoter, Language::En) => String::from("Please enter at least the name of the voter"),
+            (Message::HasAlreadyVoted(votant), Language::Fr) => format!("{} à déjà voté. Il ne peut pas voter 2 fois !", votant),
+            (Message::HasAlreadyVoted(votant), Language::En) => format!("{} has already voted and cannot vote twice!", votant),
+            (Message::HasVoted(votant), Language::Fr) => format!("{} à voté", votant),
+            (Message::HasVoted(votant), Language::En) => format!("{} has voted", votant),
+            (Message::VoteAdded(candidat), Language::Fr) => format!("un vote à été ajouté pour {}", candidat),
+            (Message::VoteAdded(candidat), Language::En) => format!("a vote was added for {}", candidat),
+            (Message::UnknownCandidate(candidat), Language::Fr) => format!("Le candidat : {} n'existe pas !", candidat),
+            (Message::UnknownCandidate(candidat), Language::En) => format!("Candidate {} does not exist!", candidat),
+            (Message::BlankVoteAdded, Language::Fr) => String::from("un vote blanc à été ajouté"),
+            (Message::BlankVoteAdded, Language::En) => String::from("a blank vote was added"),
+            (Message::VotersTitle, Language::Fr) => String::from("Votants :"),
+            (Message::VotersTitle, Language::En) => String::from("Voters:"),
+            (Message::ScoresTitle, Language::Fr) => String::from("Scores :"),
+            (Message::ScoresTitle, Language::En) => String::from("Scores:"),
+            (Message::InvalidCommand, Language::Fr) => String::from("Commande invalide ..."),
+            (Message::InvalidCommand, Language::En) => String::from("Invalid command ..."),
+            (Message::Candidate(candidat), Language::En) if *candidat == "Nul" => String::from("Invalid"),
+            (Message::Candidate(candidat), Language::En) if *candidat == "Blanc" => String::from("Blank"),
+            (Message::Candidate(candidat), _) => candidat.to_string(),
+        }
+    }
+}
 
 fn main() -> anyhow::Result<()> {
    
     let stdin = io::stdin();
+    let language = Language::detect();
 
     let mut votants: Vec<String> = vec![];
     let mut candidatesVotes: HashMap<String, i32> = HashMap::new();
@@ -22,61 +118,58 @@ fn main() -> anyhow::Result<()> {
 
         if user_input.eq("") {
 
-            println!("\n -voter <votant> <candidat> : voter pour un candidat");
-            println!("\n -voter <votant> : vote nul");
-            println!("\n -votants : voir les votants");
-            println!("\n -scores : voir les scores");
+            println!("{}", Message::Help.text(language));
 
-        } else if args[0].eq("voter") {
+        } else if args[0].eq(Keyword::Vote.word(language)) {
 
             if args.len() == 1 {
 
-                println!("Veuillez entrer au moins le nom du votant");
+                println!("{}", Message::MissingVoter.text(language));
 
             }
             else {
 
                 if votants.contains(&args[1]) {
 
-                    println!("{} à déjà voté. Il ne peut pas voter 2 fois !", args[1]);
+                    println!("{}", Message::HasAlreadyVoted(&args[1]).text(language));
 
                 } else {
 
                     votants.push(args[1].clone());
-                    println!("{} à voté", args[1]);
+                    println!("{}", Message::HasVoted(&args[1]).text(language));
 
                     if args.len() == 3 {
                         match candidatesVotes.get(&args[2]) {
 
                             Some(candidateScore) => {
                                 candidatesVotes.insert(args[2].clone(), candidateScore + 1);
-                                println!("un vote à été ajouté pour {}", args[2]);
+                                println!("{}", Message::VoteAdded(&args[2]).text(language));
                             }
                             None => {
                                 let candidateScore = candidatesVotes.get(&String::from("Nul")).expect("candidat nul n'existe pas");
                                 candidatesVotes.insert(String::from("Nul"), candidateScore + 1);
-                                println!("Le candidat : {} n'existe pas !", args[2]);
+                                println!("{}", Message::UnknownCandidate(&args[2]).text(language));
                             }
                         }
                     } else {
                         let candidateScore = candidatesVotes.get(&String::from("Blanc")).expect("candidat blanc n'existe pas");
                         candidatesVotes.insert(String::from("Blanc"), candidateScore + 1);
-                        println!("un vote blanc à été ajouté");
+                        println!("{}", Message::BlankVoteAdded.text(language));
                     }
                 }
             }
-        } else if args[0].eq("votants") {
-            println!("Votants :");
+        } else if args[0].eq(Keyword::Voters.word(language)) {
+            println!("{}", Message::VotersTitle.text(language));
             for votant in &votants {
                 println!(" - {}", votant);
             }
-        } else if args[0].eq("scores") {
-            println!("Scores :");
+        } else if args[0].eq(Keyword::Scores.word(language)) {
+            println!("{}", Message::ScoresTitle.text(language));
             for (key, value) in &candidatesVotes {
-                println!(" - {} : {}", key, value);
+                println!(" - {} : {}", Message::Candidate(key).text(language), value);
             }
         } else {
-            println!("Commande invalide ...");
+            println!("{}", Message::InvalidCommand.text(language));
         }
     }
    
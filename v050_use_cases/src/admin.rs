@@ -0,0 +1,82 @@
+use sha2::{Digest, Sha256};
+
+use crate::domain::{Candidate, Voter, WithdrawalPolicy};
+
+#[derive(Clone, Debug)]
+pub struct AdminCredential(Vec<u8>);
+
+impl AdminCredential {
+    pub fn new(passphrase: &str) -> Self {
+        AdminCredential(Sha256::digest(passphrase.as_bytes()).to_vec())
+    }
+
+    pub fn verify(&self, passphrase: &str) -> bool {
+        let digest = Sha256::digest(passphrase.as_bytes());
+        digest.len() == self.0.len()
+            && digest.iter().zip(self.0.iter()).fold(0u8, |difference, (left, right)| difference | (left ^ right)) == 0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdminAction {
+    Login,
+    Open,
+    Close,
+    AddVoter(Voter),
+    Withdraw(Candidate, WithdrawalPolicy),
+    Rename(Candidate, String),
+    IssueCodes(String),
+    Initialize,
+    Export(String),
+    SubmitPartial(String),
+    Combine,
+    RegisterProxy(Voter, Voter),
+}
+
+impl AdminAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdminAction::Login => "login",
+            AdminAction::Open => "open",
+            AdminAction::Close => "close",
+            AdminAction::AddVoter(_) => "add_voter",
+            AdminAction::Withdraw(_, _) => "withdraw",
+            AdminAction::Rename(_, _) => "rename",
+            AdminAction::IssueCodes(_) => "issue_codes",
+            AdminAction::Initialize => "init",
+            AdminAction::Export(_) => "export",
+            AdminAction::SubmitPartial(_) => "submit_partial",
+            AdminAction::Combine => "combine",
+            AdminAction::RegisterProxy(_, _) => "register_proxy",
+        }
+    }
+
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            AdminAction::Login | AdminAction::Open | AdminAction::Close | AdminAction::Initialize | AdminAction::Combine => None,
+            AdminAction::AddVoter(voter) => Some(voter.0.clone()),
+            AdminAction::Withdraw(candidate, policy) => Some(format!("{} ({})", candidate.0, policy.code())),
+            AdminAction::Rename(candidate, name) => Some(format!("{} -> {}", candidate.0, name)),
+            AdminAction::IssueCodes(export) => Some(export.clone()),
+            AdminAction::Export(target) => Some(target.clone()),
+            AdminAction::SubmitPartial(partial) => Some(partial.clone()),
+            AdminAction::RegisterProxy(principal, holder) => Some(format!("{} -> {}", principal.0, holder.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::AdminCredential;
+
+    #[test]
+    fn credential_only_accepts_the_configured_token()
+    {
+        let credential : AdminCredential = AdminCredential::new("s3cret passphrase");
+
+        assert!(credential.verify("s3cret passphrase"));
+        assert!(!credential.verify("s3cret"));
+        assert!(!credential.verify(""));
+    }
+}
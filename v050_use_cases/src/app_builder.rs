@@ -1,128 +1,958 @@
-use std::{self, io, sync::Arc};
-use tokio::sync::RwLock;
+use std::{self, fs, io, path::Path, process::ExitCode, sync::Arc};
+use anyhow::anyhow;
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::{net::TcpListener, sync::RwLock};
 
-use crate::{configuration::{Configuration, StorageType}, domain::{Voter, VotingMachine, Candidate, BallotPaper, VoteOutcome}, storage::{memory::{MemoryStore}, Storage, file::FileStore}, use_cases::{get_voting_machine, vote}};
+use crate::{admin::{AdminAction, AdminCredential}, audit::{AuditEntry, AuditFilter, AuditLog, AuditOutcome}, bulletin::{InclusionProof, MerkleTree, PublishedBulletin, VerificationFailure}, elgamal::{Group, KeyShare, SecretKey, ThresholdKey}, server::{AppState, serve}, trustees::{PartialDecryption, published_aggregate}, tui, configuration::{Command, Configuration, OutputFormat, StorageType}, results::{Results, ballot_label, render_audit, render_bulletin, render_codes, render_voters}, messages::{Language, Message}, shell::{ParseError, ShellCommand, parse}, domain::{VotingMachine, Candidate, Delegation, Issuance, Opening, Proxy, Receipt, Revelation, Scoreboard, Submission, TrackingCode, Voter, VoteOutcome, Withdrawal}, storage::{memory::{MemoryStore}, Storage, StorageError, StorageResult, file::{FileStore, write_private}}, use_cases::{get_voting_machine, get_scoreboard, vote, VoteForm, backup, restore, migrate, initialize, open, close, reveal, submit_partial, combine, rename_candidate, withdraw_candidate, issue_codes, register_proxy}};
 
-pub async fn run_app(configuration: Configuration) -> anyhow::Result<()> {
+pub const EXIT_STORAGE_ERROR: u8 = 1;
+pub const EXIT_USAGE_ERROR: u8 = 2;
+pub const EXIT_VOTE_REFUSED: u8 = 3;
+pub const EXIT_INVALID_VOTE: u8 = 4;
+pub const EXIT_CONFLICT: u8 = 5;
+pub const EXIT_NOT_FOUND: u8 = 6;
+pub const EXIT_VERIFICATION_FAILED: u8 = 7;
+pub const EXIT_ACCESS_DENIED: u8 = 8;
 
-    let candidates_config: Vec<String> = configuration.candidates;
-    
-    let mut candidates: Vec<Candidate> = Vec::new();
+fn exit_code_of(error: &StorageError) -> ExitCode {
+    match error
+    {
+        StorageError::Conflict(_) => ExitCode::from(EXIT_CONFLICT),
+        _ => ExitCode::from(EXIT_STORAGE_ERROR),
+    }
+}
+
+fn print_voters(machine: &mut VotingMachine, format: OutputFormat, language: Language) {
+    println!("{}", render_voters(machine.get_voters(), format, language));
+}
+
+fn print_receipt(receipt: &Receipt, language: Language) {
+    println!("{}", Message::Vote(&receipt.outcome).text(language));
+    if let Some(tracking_code) = &receipt.tracking_code
+    {
+        println!("{}", Message::TrackingCode(&tracking_code.0).text(language));
+    }
+    if let Some(superseded) = &receipt.superseded
+    {
+        println!("{}", Message::BallotSuperseded(&superseded.0).text(language));
+    }
+}
+
+fn print_scores(scoreboard: &Scoreboard, format: OutputFormat, language: Language) {
+    println!("{}", Results::from(scoreboard).render(format, language));
+}
+
+fn record(audit: &AuditLog, entry: &AuditEntry, language: Language) {
+    if let Err(error) = audit.record(entry)
+    {
+        eprintln!("{}", Message::AuditFailed(&error).text(language));
+    }
+}
+
+fn command_entry(command: &Command) -> Option<AuditEntry> {
+    let action : AdminAction = match command
+    {
+        Command::Voters => return Some(AuditEntry::new("cli", "voters", AuditOutcome::Succeeded)),
+        Command::Scores => return Some(AuditEntry::new("cli", "scores", AuditOutcome::Succeeded)),
+        Command::Bulletin => return Some(AuditEntry::new("cli", "bulletin", AuditOutcome::Succeeded)),
+        Command::Track { .. } => return Some(AuditEntry::new("cli", "track", AuditOutcome::Succeeded)),
+        Command::Proof { .. } => return Some(AuditEntry::new("cli", "proof", AuditOutcome::Succeeded)),
+        Command::Init { .. } => AdminAction::Initialize,
+        Command::Close => AdminAction::Close,
+        Command::Rename { candidate, name } => AdminAction::Rename(Candidate(candidate.clone()), name.clone()),
+        Command::IssueCodes { export, .. } => AdminAction::IssueCodes(export.clone()),
+        Command::Withdraw { candidate, policy } => AdminAction::Withdraw(Candidate(candidate.clone()), *policy),
+        Command::SubmitPartial { partial } => AdminAction::SubmitPartial(partial.clone()),
+        Command::Combine => AdminAction::Combine,
+        Command::RegisterProxy { principal, holder, .. } => AdminAction::RegisterProxy(Voter(principal.clone()), Voter(holder.clone())),
+        Command::Vote { .. } | Command::Audit { .. } | Command::Verify { .. } | Command::PartialDecrypt { .. } | Command::Shell | Command::Tui => return None,
+    };
+    Some(AuditEntry::admin("cli", &action, AuditOutcome::Succeeded))
+}
+
+fn shell_entry(command: &ShellCommand) -> Option<AuditEntry> {
+    let action : AdminAction = match command
+    {
+        ShellCommand::Voters => return Some(AuditEntry::new("shell", "voters", AuditOutcome::Succeeded)),
+        ShellCommand::Scores => return Some(AuditEntry::new("shell", "scores", AuditOutcome::Succeeded)),
+        ShellCommand::Open => AdminAction::Open,
+        ShellCommand::Close => AdminAction::Close,
+        ShellCommand::AddVoter { voter } => AdminAction::AddVoter(Voter(voter.clone())),
+        ShellCommand::Withdraw { candidate, policy } => AdminAction::Withdraw(Candidate(candidate.clone()), *policy),
+        ShellCommand::Backup { filepath } => AdminAction::Export(filepath.clone()),
+        _ => return None,
+    };
+    Some(AuditEntry::admin("shell", &action, AuditOutcome::Succeeded))
+}
+
+fn storage_description(configuration: &Configuration) -> String {
+    match configuration.storage_type
+    {
+        StorageType::Memory => String::from("memory"),
+        StorageType::File => format!("file {}", configuration.file_path),
+    }
+}
+
+fn show_audit(filter: AuditFilter, configuration: &Configuration) -> ExitCode {
+    let language : Language = configuration.language();
+    let audit : AuditLog = AuditLog::new(&configuration.audit_log);
+    match audit.read(&filter).and_then(|entries| Ok((entries, audit.broken_link()?)))
+    {
+        Ok((entries, broken_link)) => 
+        {
+            println!("{}", render_audit(&entries, configuration.output, language));
+            let Some(line) = broken_link else {
+                return ExitCode::SUCCESS;
+            };
+            eprintln!("{}", Message::AuditChainBroken(line).text(language));
+            ExitCode::from(EXIT_VERIFICATION_FAILED)
+        }
+        Err(error) => 
+        {
+            eprintln!("{}", Message::AuditUnreadable(&error).text(language));
+            ExitCode::from(EXIT_STORAGE_ERROR)
+        }
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let content : String = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&content).map_err(|error| error.to_string())
+}
+
+fn check_publication(bulletin: Option<&PublishedBulletin>, proof: Option<&InclusionProof>, root: Option<&str>, language: Language) -> Result<(), VerificationFailure> {
+    if let Some(bulletin) = bulletin
+    {
+        bulletin.verify()?;
+        println!("{}", Message::BulletinVerified(bulletin.ballots.len(), &bulletin.root).text(language));
+    }
+    if let Some(proof) = proof
+    {
+        match (bulletin, root)
+        {
+            (Some(bulletin), _) => bulletin.verify_proof(proof)?,
+            (None, Some(root)) if proof.root != root => 
+            {
+                return Err(VerificationFailure::RootMismatch { published: root.to_string(), computed: proof.root.clone() });
+            }
+            (None, _) => proof.verify()?,
+        }
+        println!("{}", Message::ProofVerified(&proof.entry.tracking_code).text(language));
+    }
+    Ok(())
+}
+
+fn verify_publication(bulletin: Option<String>, proof: Option<String>, root: Option<String>, configuration: &Configuration) -> ExitCode {
+    let language : Language = configuration.language();
+    let published : Option<PublishedBulletin> = match bulletin.as_deref().map(read_json).transpose()
+    {
+        Ok(published) => published,
+        Err(error) => 
+        {
+            eprintln!("{}", Message::FileUnreadable(bulletin.as_deref().unwrap_or_default(), &error).text(language));
+            return ExitCode::from(EXIT_STORAGE_ERROR);
+        }
+    };
+    let inclusion : Option<InclusionProof> = match proof.as_deref().map(read_json).transpose()
+    {
+        Ok(inclusion) => inclusion,
+        Err(error) => 
+        {
+            eprintln!("{}", Message::FileUnreadable(proof.as_deref().unwrap_or_default(), &error).text(language));
+            return ExitCode::from(EXIT_STORAGE_ERROR);
+        }
+    };
+    match check_publication(published.as_ref(), inclusion.as_ref(), root.as_deref(), language)
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => 
+        {
+            eprintln!("{}", Message::VerificationFailed(&failure).text(language));
+            ExitCode::from(EXIT_VERIFICATION_FAILED)
+        }
+    }
+}
+
+fn decrypt_partially(share: &str, bulletin: &str, configuration: &Configuration) -> ExitCode {
+    let language : Language = configuration.language();
+    let key_share : KeyShare = match read_json(share)
+    {
+        Ok(key_share) => key_share,
+        Err(error) => 
+        {
+            eprintln!("{}", Message::FileUnreadable(share, &error).text(language));
+            return ExitCode::from(EXIT_STORAGE_ERROR);
+        }
+    };
+    let published : PublishedBulletin = match read_json(bulletin)
+    {
+        Ok(published) => published,
+        Err(error) => 
+        {
+            eprintln!("{}", Message::FileUnreadable(bulletin, &error).text(language));
+            return ExitCode::from(EXIT_STORAGE_ERROR);
+        }
+    };
+    match published_aggregate(&published, &key_share.group)
+    {
+        Ok(aggregate) => 
+        {
+            let partial : PartialDecryption = PartialDecryption::compute(&key_share, &aggregate, &mut rand::thread_rng());
+            println!("{}", serde_json::to_string_pretty(&partial).unwrap_or_default());
+            ExitCode::SUCCESS
+        }
+        Err(failure) => 
+        {
+            eprintln!("{}", Message::VerificationFailed(&failure).text(language));
+            ExitCode::from(EXIT_VERIFICATION_FAILED)
+        }
+    }
+}
+
+fn candidates_of(configuration: &Configuration) -> Vec<Candidate> {
+    configuration.candidates.iter().map(|candidate| Candidate(candidate.clone())).collect()
+}
+
+fn scoreboard_of(configuration: &Configuration) -> Scoreboard {
+    let mut scoreboard : Scoreboard = if configuration.profiles.is_empty()
+    {
+        Scoreboard::new(candidates_of(configuration))
+    }
+    else 
+    {
+        Scoreboard::with_profiles(configuration.profiles.clone())
+    };
+    scoreboard.write_in = configuration.write_in;
+    scoreboard.revote = configuration.revote;
+    scoreboard.max_proxies = configuration.max_proxies;
+    scoreboard.schedule = configuration.schedule;
+    scoreboard
+}
+
+fn init_refusal<'a>(configuration: &'a Configuration, encrypted: bool, sharing: Option<(usize, usize)>, share_files: &'a [String]) -> Option<Message<'a>> {
+    if configuration.candidates.is_empty() 
+    {
+        return Some(Message::MissingCandidates);
+    }
+    if !encrypted 
+    {
+        return None;
+    }
+    match (sharing, configuration.election_key.as_deref())
+    {
+        _ if configuration.write_in => Some(Message::EncryptedWriteIn),
+        (Some((trustees, threshold)), _) if threshold == 0 || threshold > trustees => Some(Message::InvalidThreshold(threshold, trustees)),
+        (Some((trustees, _)), _) if share_files.len() != trustees => Some(Message::ShareFilesRequired(trustees)),
+        (Some(_), _) if configuration.force => None,
+        (Some(_), _) => share_files.iter().find(|path| Path::new(path).exists()).map(|path| Message::ElectionKeyExists(path)),
+        (None, None) => Some(Message::ElectionKeyRequired),
+        (None, Some(key_path)) if Path::new(key_path).exists() && !configuration.force => Some(Message::ElectionKeyExists(key_path)),
+        (None, Some(_)) => None,
+    }
+}
+
+fn export_secret<T: Serialize>(path: &Path, secret: &T, force: bool) -> io::Result<()> {
+    if force && path.exists() 
+    {
+        fs::remove_file(path)?;
+    }
+    write_private(path, serde_json::to_string_pretty(secret)?.as_bytes())
+}
+
+async fn open_storage(configuration: &Configuration) -> StorageResult<Arc<RwLock<dyn Storage>>> {
+    let filepath : &str = &configuration.file_path;
+    let store: Arc<RwLock<dyn Storage>> = if Path::new(filepath).exists() 
+    {
+        Arc::new(RwLock::new(FileStore::open(filepath).await?))
+    }
+    else 
+    {
+        Arc::new(RwLock::new(FileStore::new(&VotingMachine::with_scoreboard(scoreboard_of(configuration)), filepath).await?))
+    };
+    Ok(store)
+}
+
+async fn init_election(configuration: &Configuration, encrypted: bool, sharing: Option<(usize, usize)>, share_files: &[String]) -> StorageResult<ExitCode> {
+    let language : Language = configuration.language();
+    let admin_token : &str = configuration.admin_token.as_deref().unwrap_or_default();
+    if !encrypted 
+    {
+        let store : Arc<RwLock<dyn Storage>> = open_storage(configuration).await?;
+        initialize(store, scoreboard_of(configuration), admin_token, configuration.force).await?;
+        println!("{}", Message::ElectionInitialized(configuration.candidates.len()).text(language));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let key : SecretKey = SecretKey::generate(Group::election(), &mut rand::thread_rng());
+    let mut scoreboard : Scoreboard = scoreboard_of(configuration);
+    scoreboard.election_key = Some(key.public_key());
+    let Some((trustees, threshold)) = sharing else {
+        let key_path : &str = configuration.election_key.as_deref().unwrap_or_default();
+        export_secret(Path::new(key_path), &key, configuration.force)?;
+        let store : Arc<RwLock<dyn Storage>> = open_storage(configuration).await?;
+        initialize(store, scoreboard, admin_token, configuration.force).await?;
+        println!("{}", Message::ElectionInitialized(configuration.candidates.len()).text(language));
+        println!("{}", Message::ElectionKeyExported(key_path).text(language));
+        return Ok(ExitCode::SUCCESS);
+    };
+
+    let (threshold_key, shares) : (ThresholdKey, Vec<KeyShare>) = key.split(threshold, trustees, &mut rand::thread_rng());
+    scoreboard.threshold_key = Some(threshold_key);
+    for (index, (share, path)) in shares.iter().zip(share_files).enumerate() 
+    {
+        if let Err(error) = export_secret(Path::new(path), share, configuration.force) 
+        {
+            share_files[..index].iter().for_each(|written| { let _ = fs::remove_file(written); });
+            return Err(error.into());
+        }
+    }
+    let store : Arc<RwLock<dyn Storage>> = open_storage(configuration).await?;
+    initialize(store, scoreboard, admin_token, configuration.force).await?;
+    println!("{}", Message::ElectionInitialized(configuration.candidates.len()).text(language));
+    for (share, path) in shares.iter().zip(share_files) 
+    {
+        println!("{}", Message::ShareExported(share.trustee, path).text(language));
+    }
+    println!("{}", Message::SharesExported(trustees, threshold).text(language));
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn execute_command(command: Command, configuration: &Configuration, audit: &AuditLog) -> StorageResult<ExitCode> {
+    if !matches!(command, Command::Init { .. }) && !Path::new(&configuration.file_path).exists() 
+    {
+        return Err(StorageError::Missing(configuration.file_path.clone()));
+    }
+
+    let language : Language = configuration.language();
+
+    if let Command::Init { encrypted, trustees, threshold, share_files } = command
+    {
+        if let Some(refusal) = init_refusal(configuration, encrypted, trustees.zip(threshold), &share_files)
+        {
+            eprintln!("{}", refusal.text(language));
+            return Ok(ExitCode::from(EXIT_USAGE_ERROR));
+        }
+        return init_election(configuration, encrypted, trustees.zip(threshold), &share_files).await;
+    }
+
+    let store : Arc<RwLock<dyn Storage>> = open_storage(configuration).await?;
+
+    match command
+    {
+        Command::Vote { voter, candidate, code, on_behalf_of } => 
+        {
+            let vote_form : VoteForm = VoteForm { voter: voter.clone(), candidate: candidate.unwrap_or_default(), code, on_behalf_of };
+            let result : StorageResult<Receipt> = vote(store, vote_form).await;
+            record(audit, &AuditEntry::vote("cli", &Voter(voter), &result), language);
+            let receipt : Receipt = result?;
+            print_receipt(&receipt, language);
+            match receipt.outcome
+            {
+                VoteOutcome::AcceptedVote(_, _) | VoteOutcome::WriteInVote(_, _) | VoteOutcome::BlankVote(_) => Ok(ExitCode::SUCCESS),
+                VoteOutcome::InvalidVote(_, _) => Ok(ExitCode::from(EXIT_INVALID_VOTE)),
+                VoteOutcome::HasAlreadyVoted(_) | VoteOutcome::NotYetOpen(_) | VoteOutcome::ElectionClosed(_) | VoteOutcome::MissingCode(_) | VoteOutcome::WrongCode(_) | VoteOutcome::InvalidProxy(_, _) => Ok(ExitCode::from(EXIT_VOTE_REFUSED)),
+            }
+        }
+        Command::Voters => 
+        {
+            print_voters(&mut get_voting_machine(store).await?, configuration.output, language);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Scores => 
+        {
+            print_scores(&get_scoreboard(store).await?, configuration.output, language);
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Close => 
+        {
+            let machine : VotingMachine = close(store.clone()).await?;
+            eprintln!("{}", Message::ElectionClosed.text(language));
+            eprintln!("{}", Message::BulletinRoot(&MerkleTree::of(&machine.ballot_box).root()).text(language));
+            if machine.scoreboard.election_key.is_none() 
+            {
+                print_scores(&machine.scoreboard, configuration.output, language);
+                return Ok(ExitCode::SUCCESS);
+            }
+            if let Some(threshold_key) = &machine.scoreboard.threshold_key 
+            {
+                eprintln!("{}", Message::ResultsAwaitTrustees(threshold_key.threshold, threshold_key.trustees).text(language));
+                print_scores(&machine.scoreboard, configuration.output, language);
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let Some(key_path) = configuration.election_key.as_deref() else {
+                eprintln!("{}", Message::ResultsSealed.text(language));
+                print_scores(&machine.scoreboard, configuration.output, language);
+                return Ok(ExitCode::SUCCESS);
+            };
+            let key : SecretKey = match read_json(key_path)
+            {
+                Ok(key) => key,
+                Err(error) => 
+                {
+                    eprintln!("{}", Message::FileUnreadable(key_path, &error).text(language));
+                    return Ok(ExitCode::from(EXIT_USAGE_ERROR));
+                }
+            };
+            let revelation : Revelation = reveal(store.clone(), &key).await?;
+            eprintln!("{}", Message::Revelation(&revelation).text(language));
+            match revelation
+            {
+                Revelation::Revealed(_) => 
+                {
+                    print_scores(&get_scoreboard(store).await?, configuration.output, language);
+                    Ok(ExitCode::SUCCESS)
+                }
+                _ => Ok(ExitCode::from(EXIT_USAGE_ERROR)),
+            }
+        }
+        Command::SubmitPartial { partial } => 
+        {
+            let partial_decryption : PartialDecryption = match read_json(&partial)
+            {
+                Ok(partial_decryption) => partial_decryption,
+                Err(error) => 
+                {
+                    eprintln!("{}", Message::FileUnreadable(&partial, &error).text(language));
+                    return Ok(ExitCode::from(EXIT_USAGE_ERROR));
+                }
+            };
+            let submission : Submission = submit_partial(store, partial_decryption).await?;
+            match submission
+            {
+                Submission::Accepted { .. } => 
+                {
+                    println!("{}", Message::Submission(&submission).text(language));
+                    Ok(ExitCode::SUCCESS)
+                }
+                Submission::InvalidProof(_) => 
+                {
+                    eprintln!("{}", Message::Submission(&submission).text(language));
+                    Ok(ExitCode::from(EXIT_VERIFICATION_FAILED))
+                }
+                _ => 
+                {
+                    eprintln!("{}", Message::Submission(&submission).text(language));
+                    Ok(ExitCode::from(EXIT_USAGE_ERROR))
+                }
+            }
+        }
+        Command::Combine => 
+        {
+            let revelation : Revelation = combine(store.clone()).await?;
+            eprintln!("{}", Message::Revelation(&revelation).text(language));
+            match revelation
+            {
+                Revelation::Revealed(_) => 
+                {
+                    print_scores(&get_scoreboard(store).await?, configuration.output, language);
+                    Ok(ExitCode::SUCCESS)
+                }
+                _ => Ok(ExitCode::from(EXIT_USAGE_ERROR)),
+            }
+        }
+        Command::Rename { candidate, name } => 
+        {
+            if !rename_candidate(store, Candidate(candidate.clone()), &name).await? 
+            {
+                eprintln!("{}", Message::UnknownCandidate(&candidate).text(language));
+                return Ok(ExitCode::from(EXIT_USAGE_ERROR));
+            }
+            println!("{}", Message::CandidateRenamed(&candidate, &name).text(language));
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::IssueCodes { voters, export } => 
+        {
+            let roll : Vec<Voter> = std::fs::read_to_string(&voters)?
+                .lines()
+                .map(str::trim)
+                .filter(|voter| !voter.is_empty())
+                .map(|voter| Voter(voter.to_string()))
+                .collect();
+            match issue_codes(store, roll, configuration.force).await?
+            {
+                Issuance::Issued(codes) => 
+                {
+                    std::fs::write(&export, render_codes(&codes, configuration.output, language))?;
+                    println!("{}", Message::CodesIssued(codes.len(), &export).text(language));
+                    Ok(ExitCode::SUCCESS)
+                }
+                Issuance::AlreadyIssued(holders) => 
+                {
+                    eprintln!("{}", Message::CodesAlreadyIssued(&holders).text(language));
+                    Ok(ExitCode::from(EXIT_USAGE_ERROR))
+                }
+            }
+        }
+        Command::Withdraw { candidate, policy } => 
+        {
+            let withdrawal : Withdrawal = withdraw_candidate(store, Candidate(candidate), policy).await?;
+            match withdrawal
+            {
+                Withdrawal::Withdrawn(_) => 
+                {
+                    println!("{}", Message::Withdrawal(&withdrawal).text(language));
+                    Ok(ExitCode::SUCCESS)
+                }
+                _ => 
+                {
+                    eprintln!("{}", Message::Withdrawal(&withdrawal).text(language));
+                    Ok(ExitCode::from(EXIT_USAGE_ERROR))
+                }
+            }
+        }
+        Command::RegisterProxy { principal, holder, valid_from, valid_until } => 
+        {
+            let proxy : Proxy = Proxy { holder: Voter(holder), valid_from: valid_from.unwrap_or_default(), valid_until };
+            let delegation : Delegation = register_proxy(store, Voter(principal), proxy).await?;
+            match delegation
+            {
+                Delegation::Registered(_, _) => 
+                {
+                    println!("{}", Message::Delegation(&delegation).text(language));
+                    Ok(ExitCode::SUCCESS)
+                }
+                _ => 
+                {
+                    eprintln!("{}", Message::Delegation(&delegation).text(language));
+                    Ok(ExitCode::from(EXIT_VOTE_REFUSED))
+                }
+            }
+        }
+        Command::Bulletin => 
+        {
+            let machine : VotingMachine = get_voting_machine(store).await?;
+            if !machine.closed 
+            {
+                eprintln!("{}", Message::BulletinNotPublished.text(language));
+                return Ok(ExitCode::from(EXIT_CONFLICT));
+            }
+            println!("{}", render_bulletin(&machine, configuration.output, language));
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Track { tracking_code } => 
+        {
+            let machine : VotingMachine = get_voting_machine(store).await?;
+            match machine.ballot_box.lookup(&tracking_code)
+            {
+                Some(ballot) => 
+                {
+                    println!("{}", Message::BallotFound(&TrackingCode::normalized(&tracking_code).0, &ballot_label(ballot, &machine.scoreboard, language)).text(language));
+                    Ok(ExitCode::SUCCESS)
+                }
+                None => 
+                {
+                    eprintln!("{}", Message::BallotNotFound(&tracking_code).text(language));
+                    Ok(ExitCode::from(EXIT_NOT_FOUND))
+                }
+            }
+        }
+        Command::Proof { tracking_code } => 
+        {
+            let machine : VotingMachine = get_voting_machine(store).await?;
+            if !machine.closed 
+            {
+                eprintln!("{}", Message::BulletinNotPublished.text(language));
+                return Ok(ExitCode::from(EXIT_CONFLICT));
+            }
+            match MerkleTree::of(&machine.ballot_box).proof(&tracking_code)
+            {
+                Some(proof) => 
+                {
+                    println!("{}", serde_json::to_string_pretty(&proof).unwrap_or_default());
+                    Ok(ExitCode::SUCCESS)
+                }
+                None => 
+                {
+                    eprintln!("{}", Message::BallotNotFound(&tracking_code).text(language));
+                    Ok(ExitCode::from(EXIT_NOT_FOUND))
+                }
+            }
+        }
+        Command::Init { .. } | Command::Audit { .. } | Command::Verify { .. } | Command::PartialDecrypt { .. } | Command::Shell | Command::Tui => Ok(ExitCode::SUCCESS),
+    }
+}
 
-    for candidate_config in &candidates_config {
-        candidates.push(Candidate(String::from(candidate_config)));
+async fn admin_refusal<'a>(command: &Command, configuration: &Configuration) -> StorageResult<Option<Message<'a>>> {
+    let Some(admin_token) = configuration.admin_token.as_deref() else {
+        return Ok(Some(Message::AdminTokenRequired));
+    };
+    if !Path::new(&configuration.file_path).exists() 
+    {
+        return Ok(None);
     }
+    let machine : VotingMachine = get_voting_machine(open_storage(configuration).await?).await?;
+    if machine.authorizes(Some(admin_token)) || (matches!(command, Command::Init { .. }) && machine.admin.is_none()) 
+    {
+        return Ok(None);
+    }
+    Ok(Some(Message::AdminTokenRejected))
+}
+
+async fn run_command(command: Command, configuration: Configuration, audit: &AuditLog) -> ExitCode {
+    let language : Language = configuration.language();
 
+    if let StorageType::Memory = configuration.storage_type 
+    {
+        eprintln!("{}", Message::PersistentStorageRequired.text(language));
+        return ExitCode::from(EXIT_USAGE_ERROR);
+    }
 
-    let machine : VotingMachine = VotingMachine::new(candidates.clone());
+    let entry : Option<AuditEntry> = command_entry(&command);
 
-    let memory: Arc<RwLock<dyn Storage>>;
-    match configuration.storage_type {
+    if command.is_privileged()
+    {
+        match admin_refusal(&command, &configuration).await
+        {
+            Ok(None) => {}
+            Ok(Some(refusal)) => 
+            {
+                if let Some(entry) = entry
+                {
+                    record(audit, &AuditEntry { outcome: AuditOutcome::Denied, ..entry }, language);
+                }
+                eprintln!("{}", refusal.text(language));
+                return ExitCode::from(EXIT_ACCESS_DENIED);
+            }
+            Err(error) => 
+            {
+                if let Some(entry) = entry
+                {
+                    record(audit, &AuditEntry { outcome: AuditOutcome::Failed, ..entry }.with_reason(error.to_string()), language);
+                }
+                eprintln!("{}", Message::Storage(&error).text(language));
+                return exit_code_of(&error);
+            }
+        }
+    }
+
+    let result : StorageResult<ExitCode> = execute_command(command, &configuration, audit).await;
+
+    if let Some(entry) = entry
+    {
+        let entry : AuditEntry = match &result
+        {
+            Ok(code) if *code != ExitCode::SUCCESS => AuditEntry { outcome: AuditOutcome::Failed, ..entry },
+            _ => entry.with_result(&result),
+        };
+        record(audit, &entry, language);
+    }
+
+    match result
+    {
+        Ok(code) => code,
+        Err(error) => 
+        {
+            eprintln!("{}", Message::Storage(&error).text(language));
+            exit_code_of(&error)
+        }
+    }
+}
+
+pub async fn run_app(configuration: Configuration) -> anyhow::Result<ExitCode> {
+    let language : Language = configuration.language();
+    let audit : AuditLog = AuditLog::new(&configuration.audit_log);
+    let source : &str = match (&configuration.command, &configuration.serve)
+    {
+        (None | Some(Command::Shell), Some(_)) => "http",
+        (None | Some(Command::Shell), None) => "shell",
+        (Some(Command::Tui), _) => "tui",
+        _ => "cli",
+    };
+    record(&audit, &AuditEntry::new(source, "startup", AuditOutcome::Succeeded).with_detail(storage_description(&configuration)), language);
+
+    let result : anyhow::Result<ExitCode> = match configuration.command.clone()
+    {
+        None | Some(Command::Shell) if configuration.migrate_to.is_some_and(|target| matches!((configuration.storage_type, target), (StorageType::Memory, _) | (_, StorageType::Memory))) => 
+        {
+            eprintln!("{}", Message::MemoryMigration.text(language));
+            Ok(ExitCode::from(EXIT_USAGE_ERROR))
+        }
+        None | Some(Command::Shell) => run_shell(configuration, &audit).await.map(|_| ExitCode::SUCCESS),
+        Some(Command::Tui) => run_tui(configuration, &audit).await.map(|_| ExitCode::SUCCESS),
+        Some(Command::Audit { action, source, outcome, detail, since, until }) => 
+        {
+            Ok(show_audit(AuditFilter { action, source, outcome, detail, since, until }, &configuration))
+        }
+        Some(Command::Verify { bulletin, proof, root }) => Ok(verify_publication(bulletin, proof, root, &configuration)),
+        Some(Command::PartialDecrypt { share, bulletin }) => Ok(decrypt_partially(&share, &bulletin, &configuration)),
+        Some(command) => Ok(run_command(command, configuration, &audit).await),
+    };
+
+    record(&audit, &AuditEntry::new(source, "shutdown", AuditOutcome::Succeeded).with_result(&result), language);
+    result
+}
+
+async fn build_storage(storage_type: StorageType, machine: VotingMachine, filepath: &str) -> StorageResult<Arc<RwLock<dyn Storage>>> {
+    let store: Arc<RwLock<dyn Storage>> = match storage_type {
         StorageType::Memory => 
         {
-            memory = Arc::new(RwLock::new(MemoryStore::new(machine)));
+            Arc::new(RwLock::new(MemoryStore::new(machine)))
         }
         StorageType::File => 
         {
-            let filepath : &str = "data.txt";
-            memory = Arc::new(RwLock::new(FileStore::new(&machine, filepath).await?));
+            Arc::new(RwLock::new(FileStore::new(&machine, filepath).await?))
         }
+    };
+    Ok(store)
+}
+
+async fn prepare_storage(configuration: &Configuration, machine: VotingMachine, audit: &AuditLog, source: &str) -> anyhow::Result<Arc<RwLock<dyn Storage>>> {
+
+    let language : Language = configuration.language();
+
+    if configuration.candidates.is_empty() && matches!(configuration.storage_type, StorageType::Memory)
+    {
+        return Err(anyhow!(Message::MissingCandidates.text(language)));
+    }
+
+    let memory: Arc<RwLock<dyn Storage>> = build_storage(configuration.storage_type, machine, &configuration.file_path).await
+        .map_err(|error| anyhow!(Message::Storage(&error).text(language)))?;
+
+    if let Some(archive_path) = &configuration.restore
+    {
+        let result : StorageResult<()> = restore(memory.clone(), archive_path, configuration.force).await;
+        record(audit, &AuditEntry::new(source, "restore", AuditOutcome::Succeeded).with_detail(archive_path.clone()).with_result(&result), language);
+        result.map_err(|error| anyhow!(Message::Storage(&error).text(language)))?;
+        println!("{}", Message::ElectionRestored(archive_path).text(language));
+    }
+
+    Ok(memory)
+}
+
+async fn run_tui(configuration: Configuration, audit: &AuditLog) -> anyhow::Result<()> {
+    let machine : VotingMachine = VotingMachine::with_scoreboard(scoreboard_of(&configuration));
+    let memory: Arc<RwLock<dyn Storage>> = prepare_storage(&configuration, machine, audit, "tui").await?;
+    tui::run(memory, audit.clone(), configuration.language()).await
+}
+
+async fn run_shell(configuration: Configuration, audit: &AuditLog) -> anyhow::Result<()> {
+
+    let language : Language = configuration.language();
+    let mut machine : VotingMachine = VotingMachine::with_scoreboard(scoreboard_of(&configuration));
+    if let Some(admin_token) = configuration.admin_token.as_deref() 
+    {
+        machine.protect(admin_token);
+    }
+    let source : &str = if configuration.serve.is_some() { "http" } else { "shell" };
+    let memory: Arc<RwLock<dyn Storage>> = prepare_storage(&configuration, machine.clone(), audit, source).await?;
+    let admin : Option<AdminCredential> = configuration.admin_token.as_deref().map(AdminCredential::new);
+    let stored : VotingMachine = get_voting_machine(memory.clone()).await
+        .map_err(|error| anyhow!(Message::Storage(&error).text(language)))?;
+    if configuration.admin_token.is_some() && !stored.authorizes(configuration.admin_token.as_deref()) 
+    {
+        return Err(anyhow!(Message::AdminTokenRejected.text(language)));
+    }
+
+    if let Some(title) = &configuration.title
+    {
+        println!("{}", title);
+    }
+
+    if let Some(target_type) = configuration.migrate_to
+    {
+        let target_path : String = configuration.migrate_file_path.unwrap_or_default();
+        let target : Arc<RwLock<dyn Storage>> = build_storage(target_type, machine, &target_path).await
+            .map_err(|error| anyhow!(Message::Storage(&error).text(language)))?;
+        let result : StorageResult<VotingMachine> = migrate(memory, target, configuration.force).await;
+        record(audit, &AuditEntry::new(source, "migrate", AuditOutcome::Succeeded).with_detail(target_path.clone()).with_result(&result), language);
+        let mut migrated_machine : VotingMachine = result.map_err(|error| anyhow!(Message::Storage(&error).text(language)))?;
+        println!("{}", Message::ElectionMigrated(migrated_machine.get_voters().attendees.len()).text(language));
+        return Ok(());
+    }
+
+    if let Some(address) = &configuration.serve
+    {
+        let listener : TcpListener = TcpListener::bind(address).await?;
+        println!("{}", Message::Listening(&listener.local_addr()?.to_string()).text(language));
+        serve(listener, AppState::new(memory).with_admin(admin, audit.clone())).await?;
+        return Ok(());
     }
 
     let stdin = io::stdin();
+    let mut is_admin : bool = false;
     
     loop {
 
         let mut user_input : String = String::new();
-        stdin.read_line(&mut user_input).expect("result");
-        let args: Vec<String> = user_input.trim().split_whitespace().map(String::from).collect();
-
-        if user_input.eq("") 
+        if stdin.read_line(&mut user_input)? == 0 
         {
-            println!("\n -voter <votant> <candidat> : voter pour un candidat");
-            println!("\n -voter <votant> : vote nul");
-            println!("\n -votants : voir les votants");
-            println!("\n -scores : voir les scores");
-        } 
-        else if args[0].eq("voter")
+            return Ok(());
+        }
+
+        let command : Result<ShellCommand, ParseError> = parse(&user_input, language);
+
+        if let Ok(command) = &command
         {
-            if args.len() == 1 
+            if command.is_privileged() && !is_admin 
             {
-                println!("Veuillez entrer au moins le nom du votant");
+                if let Some(entry) = shell_entry(command) 
+                {
+                    record(audit, &AuditEntry { outcome: AuditOutcome::Denied, ..entry }, language);
+                }
+                println!("{}", Message::AdminRequired.text(language));
+                continue;
             }
-            else 
+        }
+
+        match command
+        {
+            Ok(ShellCommand::Empty) => {}
+            Ok(ShellCommand::Help) => println!("{}", Message::Help.text(language)),
+            Ok(ShellCommand::Quit) => return Ok(()),
+            Ok(ShellCommand::Vote { voter, candidate, code, on_behalf_of }) => 
             {
-                let ballot_paper : BallotPaper;
-                if args.len() >= 3 
+                let vote_form : VoteForm = VoteForm 
                 { 
-                    ballot_paper = BallotPaper 
-                    { 
-                        voter: Voter(args[1].clone()), 
-                        candidate: Some(Candidate(args[2].clone())),
+                    voter: voter.clone(), 
+                    candidate: candidate.unwrap_or_default(),
+                    code,
+                    on_behalf_of,
+                };
+
+                let result : StorageResult<Receipt> = vote(memory.clone(), vote_form).await;
+                record(audit, &AuditEntry::vote("shell", &Voter(voter), &result), language);
+                match result
+                {
+                    Ok(receipt) => print_receipt(&receipt, language),
+                    Err(error) => println!("{}", Message::Storage(&error).text(language)),
+                }
+            }
+            Ok(ShellCommand::Voters) => 
+            {
+                let result : StorageResult<VotingMachine> = get_voting_machine(memory.clone()).await;
+                record(audit, &AuditEntry::new("shell", "voters", AuditOutcome::Succeeded).with_result(&result), language);
+                match result 
+                {
+                    Ok(mut machine) => print_voters(&mut machine, configuration.output, language),
+                    Err(error) => println!("{}", Message::Storage(&error).text(language)),
+                }
+            }
+            Ok(ShellCommand::Scores) => 
+            {
+                let result : StorageResult<Scoreboard> = get_scoreboard(memory.clone()).await;
+                record(audit, &AuditEntry::new("shell", "scores", AuditOutcome::Succeeded).with_result(&result), language);
+                match result 
+                {
+                    Ok(scoreboard) => print_scores(&scoreboard, configuration.output, language),
+                    Err(error) => println!("{}", Message::Storage(&error).text(language)),
+                }
+            }
+            Ok(ShellCommand::Backup { filepath }) => 
+            {
+                let result : StorageResult<()> = backup(memory.clone(), &filepath).await;
+                record(audit, &AuditEntry::admin("shell", &AdminAction::Export(filepath.clone()), AuditOutcome::Succeeded).with_result(&result), language);
+                match result 
+                {
+                    Ok(()) => println!("{}", Message::ElectionSaved(&filepath).text(language)),
+                    Err(error) => println!("{}", Message::BackupFailed(&error).text(language)),
+                }
+            }
+            Ok(ShellCommand::Admin { passphrase }) => 
+            {
+                let Some(credential) = &admin else {
+                    record(audit, &AuditEntry::admin("shell", &AdminAction::Login, AuditOutcome::Denied), language);
+                    println!("{}", Message::AdminTokenRequired.text(language));
+                    continue;
+                };
+                let machine : VotingMachine = match get_voting_machine(memory.clone()).await 
+                {
+                    Ok(machine) => machine,
+                    Err(error) => 
+                    {
+                        record(audit, &AuditEntry::admin("shell", &AdminAction::Login, AuditOutcome::Failed).with_reason(error.to_string()), language);
+                        println!("{}", Message::Storage(&error).text(language));
+                        continue;
                     }
-                } 
+                };
+                let granted : bool = credential.verify(&passphrase) && machine.authorizes(Some(&passphrase));
+                let outcome : AuditOutcome = if granted { AuditOutcome::Succeeded } else { AuditOutcome::Denied };
+                record(audit, &AuditEntry::admin("shell", &AdminAction::Login, outcome), language);
+                if granted 
+                {
+                    is_admin = true;
+                    println!("{}", Message::AdminGranted.text(language));
+                }
                 else 
                 {
-                    ballot_paper = BallotPaper 
-                    { 
-                        voter: Voter(args[1].clone()), 
-                        candidate: None, 
-                    }; 
+                    println!("{}", Message::AdminDenied.text(language));
                 }
-
-                let outcome : VoteOutcome = get_voting_machine(memory).await?.vote(ballot_paper);
-
-                match outcome
+            }
+            Ok(ShellCommand::Logout) => 
+            {
+                is_admin = false;
+                println!("{}", Message::AdminLoggedOut.text(language));
+            }
+            Ok(ShellCommand::Open) => 
+            {
+                let result : StorageResult<Opening> = open(memory.clone()).await;
+                let entry : AuditEntry = AuditEntry::admin("shell", &AdminAction::Open, AuditOutcome::Succeeded);
+                let entry : AuditEntry = match &result
                 {
-                    VoteOutcome::AcceptedVote(voter, candidate) => 
-                    {
-                        let mut new_voting_machine : VotingMachine = get_voting_machine(memory).await?;
-                        new_voting_machine.get_voters().0.insert(voter);
-                        new_voting_machine.get_scoreboard().scores.entry(candidate).and_modify(|score| score.0 += 1);
-                        let _ = memory.write().await.put_voting_machine(new_voting_machine.clone()).await;
-                        println!("Vote accepté !");
-                    }       
-                    VoteOutcome::BlankVote(voter) =>
+                    Ok(Opening::AlreadyOpen) => AuditEntry { outcome: AuditOutcome::Failed, ..entry }.with_reason("already_open"),
+                    Ok(Opening::Closed) => AuditEntry { outcome: AuditOutcome::Failed, ..entry }.with_reason("closed"),
+                    _ => entry.with_result(&result),
+                };
+                record(audit, &entry, language);
+                match result 
+                {
+                    Ok(Opening::Opened) => println!("{}", Message::ElectionOpened.text(language)),
+                    Ok(Opening::AlreadyOpen) => println!("{}", Message::ElectionAlreadyOpen.text(language)),
+                    Ok(Opening::Closed) => println!("{}", Message::ReopenRefused.text(language)),
+                    Err(error) => println!("{}", Message::Storage(&error).text(language)),
+                }
+            }
+            Ok(ShellCommand::Close) => 
+            {
+                let result : StorageResult<VotingMachine> = close(memory.clone()).await;
+                record(audit, &AuditEntry::admin("shell", &AdminAction::Close, AuditOutcome::Succeeded).with_result(&result), language);
+                match result 
+                {
+                    Ok(machine) => 
                     {
-                        let mut new_voting_machine : VotingMachine = get_voting_machine(memory).await?;
-                        new_voting_machine.get_voters().0.insert(voter);
-                        new_voting_machine.get_scoreboard().blank_scores.0 += 1;
-                        let _ = memory.write().await.put_voting_machine(new_voting_machine.clone()).await;
-                        println!("Vote blanc");
-                    }
-                    VoteOutcome::InvalidVote(voter) => 
-                    {   
-                        let mut new_voting_machine : VotingMachine = get_voting_machine(memory).await?;
-                        new_voting_machine.get_voters().0.insert(voter);
-                        new_voting_machine.get_scoreboard().invalid_scores.0 += 1;
-                        let _ = memory.write().await.put_voting_machine(new_voting_machine.clone()).await;
-                        println!("Vote invalide");
+                        println!("{}", Message::ElectionClosed.text(language));
+                        println!("{}", Message::BulletinRoot(&MerkleTree::of(&machine.ballot_box).root()).text(language));
+                        match &machine.scoreboard.threshold_key
+                        {
+                            Some(threshold_key) => println!("{}", Message::ResultsAwaitTrustees(threshold_key.threshold, threshold_key.trustees).text(language)),
+                            None if machine.scoreboard.sealed_scores.0 > 0 => println!("{}", Message::ResultsSealed.text(language)),
+                            None => {}
+                        }
+                        print_scores(&machine.scoreboard, configuration.output, language);
                     }
-                    VoteOutcome::HasAlreadyVoted(voter) => println!("{} à déjà voté. Il ne peut pas voter 2 fois !", voter.0),
+                    Err(error) => println!("{}", Message::Storage(&error).text(language)),
                 }
             }
-        } 
-        else if args[0].eq("votants") 
-        {
-            println!("Votants :");
-            for votant in get_voting_machine(memory).await?.get_voters().0 
+            Ok(ShellCommand::AddVoter { voter }) => 
             {
-                println!(" - {}", votant.0);
+                let voter : Voter = Voter(voter);
+                let result : StorageResult<Issuance> = issue_codes(memory.clone(), vec![voter.clone()], configuration.force).await;
+                record(audit, &AuditEntry::admin("shell", &AdminAction::AddVoter(voter.clone()), AuditOutcome::Succeeded).with_result(&result), language);
+                match result 
+                {
+                    Ok(Issuance::Issued(codes)) => match codes.into_iter().next()
+                    {
+                        Some((voter, code)) => println!("{}", Message::VoterAdded(&voter.0, &code).text(language)),
+                        None => println!("{}", Message::Vote(&VoteOutcome::HasAlreadyVoted(voter)).text(language)),
+                    },
+                    Ok(Issuance::AlreadyIssued(holders)) => println!("{}", Message::CodesAlreadyIssued(&holders).text(language)),
+                    Err(error) => println!("{}", Message::Storage(&error).text(language)),
+                }
             }
-        } 
-        else if args[0].eq("scores") 
-        {
-            println!("Scores :");
-            for (key, value) in get_voting_machine(memory).await?.get_scoreboard().scores 
+            Ok(ShellCommand::Withdraw { candidate, policy }) => 
             {
-                println!(" - {} : {}", key.0, value.0);
+                let candidate : Candidate = Candidate(candidate);
+                let result : StorageResult<Withdrawal> = withdraw_candidate(memory.clone(), candidate.clone(), policy).await;
+                record(audit, &AuditEntry::admin("shell", &AdminAction::Withdraw(candidate, policy), AuditOutcome::Succeeded).with_result(&result), language);
+                match result 
+                {
+                    Ok(withdrawal) => println!("{}", Message::Withdrawal(&withdrawal).text(language)),
+                    Err(error) => println!("{}", Message::Storage(&error).text(language)),
+                }
             }
-            println!(" - votes blancs : {}", get_voting_machine(memory).await?.get_scoreboard().blank_scores.0);
-            println!(" - votes invalides : {}", get_voting_machine(memory).await?.get_scoreboard().invalid_scores.0);
-        } 
-        else 
-        {
-            println!("Commande invalide ...");
+            Err(ParseError::Usage(keyword)) => println!("{}", Message::Usage(keyword).text(language)),
+            Err(ParseError::UnknownCommand(word)) => println!("{}", Message::UnknownCommand(&word).text(language)),
+            Err(ParseError::UnterminatedQuote) => println!("{}", Message::UnterminatedQuote.text(language)),
         }
     }
-}
\ No newline at end of file
+}
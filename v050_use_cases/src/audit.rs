@@ -0,0 +1,309 @@
+use std::{fmt::Display, fs::{self, File, OpenOptions}, io::{self, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}, sync::{Arc, Mutex, PoisonError}, time::{SystemTime, UNIX_EPOCH}};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{admin::AdminAction, domain::{Receipt, Voter}, storage::StorageResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Succeeded,
+    Denied,
+    Failed,
+}
+
+impl AuditOutcome {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuditOutcome::Succeeded => "succeeded",
+            AuditOutcome::Denied => "denied",
+            AuditOutcome::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub source: String,
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub outcome: AuditOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(source: &str, action: &str, outcome: AuditOutcome) -> Self {
+        AuditEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            source: source.to_string(),
+            action: action.to_string(),
+            detail: None,
+            outcome,
+            reason: None,
+            previous: None,
+        }
+    }
+
+    pub fn with_detail(self, detail: impl Into<String>) -> Self {
+        AuditEntry { detail: Some(detail.into()), ..self }
+    }
+
+    pub fn with_reason(self, reason: impl Into<String>) -> Self {
+        AuditEntry { reason: Some(reason.into()), ..self }
+    }
+
+    pub fn with_result<T, E: Display>(self, result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => self,
+            Err(error) => AuditEntry { outcome: AuditOutcome::Failed, ..self }.with_reason(error.to_string()),
+        }
+    }
+
+    pub fn admin(source: &str, action: &AdminAction, outcome: AuditOutcome) -> Self {
+        AuditEntry { detail: action.detail(), ..AuditEntry::new(source, action.name(), outcome) }
+    }
+
+    pub fn vote(source: &str, voter: &Voter, result: &StorageResult<Receipt>) -> Self {
+        let entry : AuditEntry = AuditEntry::new(source, "vote", AuditOutcome::Succeeded).with_detail(voter.0.clone());
+        match result {
+            Ok(receipt) if receipt.outcome.is_recorded() => entry,
+            Ok(receipt) => AuditEntry { outcome: AuditOutcome::Denied, ..entry }.with_reason(receipt.outcome.code()),
+            Err(_) => entry.with_result(result),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AuditFilter {
+    pub action: Option<String>,
+    pub source: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    pub detail: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.action.as_ref().is_none_or(|action| &entry.action == action)
+            && self.source.as_ref().is_none_or(|source| &entry.source == source)
+            && self.outcome.is_none_or(|outcome| entry.outcome == outcome)
+            && self.detail.as_ref().is_none_or(|detail| entry.detail.as_ref().is_some_and(|entry_detail| entry_detail.contains(detail.as_str())))
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+    }
+}
+
+fn chain_hash(line: &str) -> String {
+    hex::encode(Sha256::digest(line.as_bytes()))
+}
+
+const TAIL_CHUNK: u64 = 4_096;
+
+fn last_line(path: &Path) -> io::Result<Option<String>> {
+    let mut file : File = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let mut end : u64 = file.seek(SeekFrom::End(0))?;
+    let mut tail : Vec<u8> = Vec::new();
+    while end > 0 {
+        let start : u64 = end.saturating_sub(TAIL_CHUNK);
+        let mut chunk : Vec<u8> = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        end = start;
+
+        let Some(last) = tail.iter().rposition(|byte| !byte.is_ascii_whitespace()) else {
+            continue;
+        };
+        let line_start : usize = match tail[..last].iter().rposition(|byte| *byte == b'\n') {
+            Some(newline) => newline + 1,
+            None if end > 0 => continue,
+            None => 0,
+        };
+        let line_end : usize = tail[last..].iter().position(|byte| *byte == b'\n').map_or(tail.len(), |newline| last + newline);
+        let line : &[u8] = tail[line_start..line_end].strip_suffix(b"\r").unwrap_or(&tail[line_start..line_end]);
+        return Ok(Some(String::from_utf8_lossy(line).into_owned()));
+    }
+    Ok(None)
+}
+
+fn parse_line(index: usize, line: &str) -> io::Result<AuditEntry> {
+    serde_json::from_str::<AuditEntry>(line)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("audit log line {}: {}", index + 1, error)))
+}
+
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days : i64 = (timestamp / 86_400) as i64;
+    let seconds : u64 = timestamp % 86_400;
+    let shifted : i64 = days + 719_468;
+    let era : i64 = shifted.div_euclid(146_097);
+    let day_of_era : i64 = shifted - era * 146_097;
+    let year_of_era : i64 = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year : i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index : i64 = (5 * day_of_year + 2) / 153;
+    let day : i64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month : i64 = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year : i64 = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, seconds / 3_600, seconds % 3_600 / 60, seconds % 60)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    writer: Arc<Mutex<()>>,
+}
+
+impl AuditLog {
+    pub fn new(path: &str) -> Self {
+        AuditLog { path: Some(PathBuf::from(path)), writer: Arc::default() }
+    }
+
+    pub fn disabled() -> Self {
+        AuditLog::default()
+    }
+
+    fn content(&self) -> io::Result<String> {
+        let Some(path) = &self.path else {
+            return Ok(String::new());
+        };
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(content),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn record(&self, entry: &AuditEntry) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous : Option<String> = last_line(path)?.as_deref().map(chain_hash);
+        let mut line : String = serde_json::to_string(&AuditEntry { previous, ..entry.clone() })?;
+        line.push('\n');
+        OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())
+    }
+
+    pub fn read(&self, filter: &AuditFilter) -> io::Result<Vec<AuditEntry>> {
+        self.content()?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| parse_line(index, line))
+            .filter(|entry| entry.as_ref().map_or(true, |entry| filter.matches(entry)))
+            .collect()
+    }
+
+    pub fn broken_link(&self) -> io::Result<Option<usize>> {
+        let mut previous : Option<String> = None;
+        for (index, line) in self.content()?.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            if parse_line(index, line)?.previous != previous {
+                return Ok(Some(index + 1));
+            }
+            previous = Some(chain_hash(line));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+
+    use crate::{admin::AdminAction, domain::{Candidate, Receipt, TrackingCode, Voter, VoteOutcome}, storage::StorageError};
+    use super::{AuditEntry, AuditFilter, AuditLog, AuditOutcome};
+
+    #[test]
+    fn entries_are_appended_as_json_lines() -> anyhow::Result<()>
+    {
+        let path = std::env::temp_dir().join(format!("v050_audit_append_{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let audit : AuditLog = AuditLog::new(path.to_str().unwrap_or_default());
+
+        audit.record(&AuditEntry::admin("shell", &AdminAction::Login, AuditOutcome::Denied))?;
+        audit.record(&AuditEntry::admin("shell", &AdminAction::AddVoter(Voter("Jean".to_string())), AuditOutcome::Succeeded))?;
+
+        let entries : Vec<AuditEntry> = audit.read(&AuditFilter::default())?;
+        let denied : Vec<AuditEntry> = audit.read(&AuditFilter { outcome: Some(AuditOutcome::Denied), ..AuditFilter::default() })?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].action.as_str(), entries[0].outcome), ("login", AuditOutcome::Denied));
+        assert_eq!((entries[1].detail.as_deref(), entries[1].outcome), (Some("Jean"), AuditOutcome::Succeeded));
+        assert_eq!(denied, entries[..1].to_vec());
+        Ok(())
+    }
+
+    #[test]
+    fn vote_entries_do_not_reveal_the_ballot()
+    {
+        let voter : Voter = Voter("Jean".to_string());
+
+        let tracking_code : Option<TrackingCode> = Some(TrackingCode("ABCD-2345-WXYZ".to_string()));
+        let accepted : AuditEntry = AuditEntry::vote("cli", &voter, &Ok(Receipt { outcome: VoteOutcome::AcceptedVote(voter.clone(), Candidate("M.Lepen".to_string())), tracking_code: tracking_code.clone(), superseded: None }));
+        let blank : AuditEntry = AuditEntry::vote("cli", &voter, &Ok(Receipt { outcome: VoteOutcome::BlankVote(voter.clone()), tracking_code, superseded: None }));
+        let duplicate : AuditEntry = AuditEntry::vote("cli", &voter, &Ok(Receipt::refused(VoteOutcome::HasAlreadyVoted(voter.clone()))));
+        let failed : AuditEntry = AuditEntry::vote("cli", &voter, &Err(StorageError::LockPoisoned));
+
+        assert_eq!((accepted.outcome, accepted.reason.clone(), accepted.detail.as_deref()), (AuditOutcome::Succeeded, None, Some("Jean")));
+        assert_eq!((blank.outcome, blank.reason), (accepted.outcome, accepted.reason.clone()));
+        assert_eq!((duplicate.outcome, duplicate.reason.as_deref()), (AuditOutcome::Denied, Some("already_voted")));
+        assert_eq!(failed.outcome, AuditOutcome::Failed);
+        assert!(!serde_json::to_string(&accepted).unwrap_or_default().contains("ABCD"));
+    }
+
+    #[test]
+    fn tampering_breaks_the_chain() -> anyhow::Result<()>
+    {
+        let path = std::env::temp_dir().join(format!("v050_audit_chain_{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let audit : AuditLog = AuditLog::new(path.to_str().unwrap_or_default());
+
+        for voter in ["Jean", "Paul", "Marie"]
+        {
+            audit.record(&AuditEntry::admin("shell", &AdminAction::AddVoter(Voter(voter.to_string())), AuditOutcome::Succeeded))?;
+        }
+        let intact : Option<usize> = audit.broken_link()?;
+        fs::write(&path, fs::read_to_string(&path)?.replace("Paul", "Pierre"))?;
+        let tampered : Option<usize> = audit.broken_link()?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(intact, None);
+        assert_eq!(tampered, Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn chain_follows_entries_longer_than_a_read_chunk() -> anyhow::Result<()>
+    {
+        let path = std::env::temp_dir().join(format!("v050_audit_long_{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let audit : AuditLog = AuditLog::new(path.to_str().unwrap_or_default());
+
+        audit.record(&AuditEntry::admin("shell", &AdminAction::Login, AuditOutcome::Succeeded))?;
+        audit.record(&AuditEntry::admin("shell", &AdminAction::AddVoter(Voter("J".repeat(10_000))), AuditOutcome::Succeeded))?;
+        audit.record(&AuditEntry::admin("shell", &AdminAction::Close, AuditOutcome::Succeeded))?;
+        fs::write(&path, fs::read_to_string(&path)? + "\n\n")?;
+        audit.record(&AuditEntry::admin("shell", &AdminAction::Open, AuditOutcome::Succeeded))?;
+        let broken : Option<usize> = audit.broken_link()?;
+        let entries : Vec<AuditEntry> = audit.read(&AuditFilter::default())?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(broken, None);
+        assert_eq!(entries.len(), 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{domain::{Ballot, BallotBox, EncryptedBallot, Scoreboard, TrackingCode, VotingMachine}, elgamal::{self, Ciphertext, Group, PublicKey, ThresholdKey}, trustees::PartialDecryption};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+type Hash = Vec<u8>;
+
+fn hash_leaf(entry: &BulletinEntry) -> Hash {
+    Sha256::new()
+        .chain_update([LEAF_PREFIX])
+        .chain_update(format!("{}:{}:{}", entry.tracking_code, entry.kind, entry.choice).as_bytes())
+        .finalize()
+        .to_vec()
+}
+
+fn hash_node(left: &[u8], right: &[u8]) -> Hash {
+    Sha256::new()
+        .chain_update([NODE_PREFIX])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .to_vec()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulletinEntry {
+    pub tracking_code: String,
+    pub kind: String,
+    pub choice: String,
+}
+
+impl BulletinEntry {
+    pub fn of(tracking_code: &TrackingCode, ballot: &Ballot) -> Self {
+        let (kind, choice) : (&str, String) = match ballot {
+            Ballot::Candidate(candidate) => ("candidate", candidate.0.clone()),
+            Ballot::WriteIn(name) => ("write_in", name.clone()),
+            Ballot::Blank => ("blank", String::new()),
+            Ballot::Invalid(reason) => ("invalid", reason.code().to_string()),
+            Ballot::Sealed(sealed) => ("sealed", serde_json::to_string(&sealed.0).unwrap_or_default()),
+        };
+        BulletinEntry { tracking_code: tracking_code.0.clone(), kind: kind.to_string(), choice }
+    }
+
+    pub fn entries(ballot_box: &BallotBox) -> Vec<BulletinEntry> {
+        ballot_box.0.iter().map(|(tracking_code, ballot)| BulletinEntry::of(tracking_code, ballot)).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub side: Side,
+    pub hash: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    #[serde(flatten)]
+    pub entry: BulletinEntry,
+    pub index: usize,
+    pub path: Vec<ProofStep>,
+    pub root: String,
+}
+
+impl InclusionProof {
+    pub fn computed_root(&self) -> Result<String, VerificationFailure> {
+        let mut hash : Hash = hash_leaf(&self.entry);
+        for step in &self.path {
+            let sibling : Hash = hex::decode(&step.hash)
+                .map_err(|_| VerificationFailure::InvalidProof(self.entry.tracking_code.clone()))?;
+            hash = match step.side {
+                Side::Left => hash_node(&sibling, &hash),
+                Side::Right => hash_node(&hash, &sibling),
+            };
+        }
+        Ok(hex::encode(hash))
+    }
+
+    pub fn verify(&self) -> Result<(), VerificationFailure> {
+        if self.computed_root()? != self.root {
+            return Err(VerificationFailure::InvalidProof(self.entry.tracking_code.clone()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree {
+    entries: Vec<BulletinEntry>,
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn build(entries: Vec<BulletinEntry>) -> Self {
+        let mut levels : Vec<Vec<Hash>> = vec![entries.iter().map(hash_leaf).collect()];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let parents : Vec<Hash> = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(parents);
+        }
+        MerkleTree { entries, levels }
+    }
+
+    pub fn of(ballot_box: &BallotBox) -> Self {
+        MerkleTree::build(BulletinEntry::entries(ballot_box))
+    }
+
+    pub fn root(&self) -> String {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => hex::encode(root),
+            None => hex::encode(Sha256::digest(b"")),
+        }
+    }
+
+    pub fn proof(&self, tracking_code: &str) -> Option<InclusionProof> {
+        let tracking_code : TrackingCode = TrackingCode::normalized(tracking_code);
+        let index : usize = self.entries.iter().position(|entry| entry.tracking_code == tracking_code.0)?;
+        let mut position : usize = index;
+        let mut path : Vec<ProofStep> = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling : usize = position ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                let side : Side = if sibling < position { Side::Left } else { Side::Right };
+                path.push(ProofStep { side, hash: hex::encode(hash) });
+            }
+            position /= 2;
+        }
+        Some(InclusionProof { entry: self.entries[index].clone(), index, path, root: self.root() })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedTally {
+    pub candidates: Map<String, usize>,
+    #[serde(default)]
+    pub write_ins: Map<String, usize>,
+    pub blank: usize,
+    #[serde(default)]
+    pub invalid: Map<String, usize>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub sealed: usize,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+impl PublishedTally {
+    pub fn recount(entries: &[BulletinEntry]) -> Result<Self, VerificationFailure> {
+        let mut tally : PublishedTally = PublishedTally::default();
+        for entry in entries {
+            match entry.kind.as_str() {
+                "candidate" => *tally.candidates.entry(entry.choice.clone()).or_insert(0) += 1,
+                "write_in" => *tally.write_ins.entry(entry.choice.clone()).or_insert(0) += 1,
+                "blank" => tally.blank += 1,
+                "invalid" => *tally.invalid.entry(entry.choice.clone()).or_insert(0) += 1,
+                "sealed" => tally.sealed += 1,
+                _ => return Err(VerificationFailure::UnknownBallotKind(entry.kind.clone())),
+            }
+        }
+        Ok(tally)
+    }
+
+    fn counts(&self) -> Map<String, usize> {
+        let mut counts : Map<String, usize> = Map::new();
+        counts.extend(self.candidates.iter().map(|(candidate, votes)| (format!("candidate:{}", candidate), *votes)));
+        counts.extend(self.write_ins.iter().map(|(name, votes)| (format!("write_in:{}", name), *votes)));
+        counts.insert(String::from("blank"), self.blank);
+        counts.extend(self.invalid.iter().map(|(reason, votes)| (format!("invalid:{}", reason), *votes)));
+        counts.insert(String::from("sealed"), self.sealed);
+        counts.retain(|_, votes| *votes > 0);
+        counts
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts().values().sum()
+    }
+
+    fn absorb(&mut self, slot: &str, votes: usize) {
+        match slot.split_once(':') {
+            Some(("candidate", candidate)) => *self.candidates.entry(candidate.to_string()).or_insert(0) += votes,
+            Some(("write_in", name)) => *self.write_ins.entry(name.to_string()).or_insert(0) += votes,
+            Some(("invalid", reason)) => *self.invalid.entry(reason.to_string()).or_insert(0) += votes,
+            _ => self.blank += votes,
+        }
+    }
+
+    pub fn compare(&self, counted: &PublishedTally) -> Result<(), VerificationFailure> {
+        let published : Map<String, usize> = self.counts();
+        let counted : Map<String, usize> = counted.counts();
+        match published.keys().chain(counted.keys()).find(|choice| published.get(*choice) != counted.get(*choice)) {
+            Some(choice) => Err(VerificationFailure::TallyMismatch {
+                choice: choice.clone(),
+                published: published.get(choice).copied().unwrap_or(0),
+                counted: counted.get(choice).copied().unwrap_or(0),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl From<&Scoreboard> for PublishedTally {
+    fn from(scoreboard: &Scoreboard) -> Self {
+        PublishedTally {
+            candidates: scoreboard.scores.iter().map(|(candidate, score)| (candidate.0.clone(), score.0)).collect(),
+            write_ins: scoreboard.write_in_scores.iter().map(|(name, score)| (name.clone(), score.0)).collect(),
+            blank: scoreboard.blank_scores.0,
+            invalid: scoreboard.invalid_reasons.iter().map(|(reason, score)| (reason.code().to_string(), score.0)).collect(),
+            sealed: scoreboard.sealed_scores.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedDecryption {
+    pub election_key: PublicKey,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold_key: Option<ThresholdKey>,
+    pub aggregate: Map<String, Ciphertext>,
+    pub partials: Vec<PartialDecryption>,
+}
+
+impl PublishedDecryption {
+    fn verification_key(&self, trustee: u64) -> Option<BigUint> {
+        match &self.threshold_key {
+            Some(threshold_key) => (threshold_key.has_trustee(trustee) && threshold_key.commitments.first() == Some(&self.election_key.h))
+                .then(|| threshold_key.verification_key(&self.election_key.group, trustee)),
+            None => (trustee == 0 && self.partials.len() == 1).then(|| self.election_key.h.clone()),
+        }
+    }
+
+    fn threshold(&self) -> usize {
+        self.threshold_key.as_ref().map_or(1, |threshold_key| threshold_key.threshold)
+    }
+
+    pub fn counts(&self, aggregate: &EncryptedBallot, ballots: usize) -> Result<Map<String, usize>, VerificationFailure> {
+        if self.aggregate != aggregate.0 {
+            return Err(VerificationFailure::InvalidDecryption(String::from("aggregate")));
+        }
+        let trustees : Set<u64> = self.partials.iter().map(|partial| partial.trustee).collect();
+        if trustees.len() != self.partials.len() || trustees.len() < self.threshold() {
+            return Err(VerificationFailure::InvalidDecryption(format!("{} of {} trustees", trustees.len(), self.threshold())));
+        }
+        let group : &Group = &self.election_key.group;
+        for partial in &self.partials {
+            let verification_key : BigUint = self.verification_key(partial.trustee)
+                .ok_or_else(|| VerificationFailure::InvalidDecryption(format!("trustee {}", partial.trustee)))?;
+            if !partial.decrypts(aggregate) || partial.factors.values().any(|factor| !factor.verify(group, &verification_key)) {
+                return Err(VerificationFailure::InvalidDecryption(format!("trustee {}", partial.trustee)));
+            }
+        }
+        aggregate.0.iter()
+            .map(|(slot, ciphertext)| {
+                let factors : Vec<(u64, BigUint)> = self.partials.iter().map(|partial| (partial.trustee, partial.factors[slot].factor.clone())).collect();
+                elgamal::combine(group, ciphertext, &factors, ballots as u64)
+                    .map(|votes| (slot.clone(), votes as usize))
+                    .ok_or_else(|| VerificationFailure::InvalidDecryption(slot.clone()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedBulletin {
+    pub root: String,
+    pub ballots: Vec<BulletinEntry>,
+    pub tally: PublishedTally,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decryption: Option<PublishedDecryption>,
+}
+
+impl PublishedBulletin {
+    pub fn of(machine: &VotingMachine) -> Self {
+        let tree : MerkleTree = MerkleTree::of(&machine.ballot_box);
+        let aggregate : EncryptedBallot = machine.encrypted_tally().unwrap_or_default();
+        let partials : Vec<PartialDecryption> = match (&machine.decryption, &machine.scoreboard.threshold_key) {
+            (Some(partial), _) => vec![partial.clone()],
+            (None, Some(_)) => machine.partial_decryptions.values().filter(|partial| partial.decrypts(&aggregate)).cloned().collect(),
+            (None, None) => Vec::new(),
+        };
+        let decryption : Option<PublishedDecryption> = machine.scoreboard.election_key.clone()
+            .filter(|_| !partials.is_empty())
+            .map(|election_key| PublishedDecryption {
+                election_key,
+                threshold_key: machine.scoreboard.threshold_key.clone(),
+                aggregate: aggregate.0,
+                partials,
+            });
+        PublishedBulletin { root: tree.root(), ballots: tree.entries, tally: PublishedTally::from(&machine.scoreboard), decryption }
+    }
+
+    pub fn sealed_aggregate(&self, group: &Group) -> Result<EncryptedBallot, VerificationFailure> {
+        self.ballots.iter()
+            .filter(|entry| entry.kind == "sealed")
+            .try_fold(EncryptedBallot::default(), |total, entry| {
+                let sealed : Map<String, Ciphertext> = serde_json::from_str(&entry.choice)
+                    .map_err(|_| VerificationFailure::MalformedBallot(entry.tracking_code.clone()))?;
+                Ok(total.add(&EncryptedBallot(sealed), group))
+            })
+    }
+
+    pub fn verify(&self) -> Result<(), VerificationFailure> {
+        let computed : String = MerkleTree::build(self.ballots.clone()).root();
+        if computed != self.root {
+            return Err(VerificationFailure::RootMismatch { published: self.root.clone(), computed });
+        }
+        let mut counted : PublishedTally = PublishedTally::recount(&self.ballots)?;
+        if counted.sealed > 0 && self.tally.sealed == 0 {
+            let decryption : &PublishedDecryption = self.decryption.as_ref().ok_or(VerificationFailure::MissingDecryption)?;
+            let aggregate : EncryptedBallot = self.sealed_aggregate(&decryption.election_key.group)?;
+            for (slot, votes) in decryption.counts(&aggregate, counted.sealed)? {
+                counted.absorb(&slot, votes);
+            }
+            counted.sealed = 0;
+        }
+        self.tally.compare(&counted)
+    }
+
+    pub fn verify_proof(&self, proof: &InclusionProof) -> Result<(), VerificationFailure> {
+        proof.verify()?;
+        if proof.root != self.root {
+            return Err(VerificationFailure::RootMismatch { published: self.root.clone(), computed: proof.root.clone() });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationFailure {
+    RootMismatch { published: String, computed: String },
+    TallyMismatch { choice: String, published: usize, counted: usize },
+    UnknownBallotKind(String),
+    InvalidProof(String),
+    MalformedBallot(String),
+    MissingDecryption,
+    InvalidDecryption(String),
+}
+
+#[cfg(test)]
+mod tests
+{
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::domain::{Candidate, InvalidReason, Revelation, TrackingCode, Voter, VoteOutcome, VotingMachine};
+    use crate::elgamal::{SecretKey, tests::small_group};
+    use super::{BulletinEntry, MerkleTree, PublishedBulletin, VerificationFailure};
+
+    fn setup_voting_machine() -> VotingMachine
+    {
+        let mut voting_machine : VotingMachine = VotingMachine::new(vec![Candidate("E.Macron".to_string()), Candidate("M.Lepen".to_string())]);
+        let outcomes : Vec<VoteOutcome> = vec![
+            VoteOutcome::AcceptedVote(Voter("Jean".to_string()), Candidate("E.Macron".to_string())),
+            VoteOutcome::AcceptedVote(Voter("Paul".to_string()), Candidate("M.Lepen".to_string())),
+            VoteOutcome::AcceptedVote(Voter("Marie".to_string()), Candidate("E.Macron".to_string())),
+            VoteOutcome::BlankVote(Voter("Luc".to_string())),
+            VoteOutcome::InvalidVote(Voter("Anne".to_string()), InvalidReason::UnknownCandidate),
+        ];
+        for (index, outcome) in outcomes.iter().enumerate()
+        {
+            voting_machine.deposit(outcome, TrackingCode(format!("CODE-000{}", index)));
+        }
+        voting_machine
+    }
+
+    #[test]
+    fn every_ballot_has_a_proof_leading_to_the_root()
+    {
+        let voting_machine : VotingMachine = setup_voting_machine();
+        let tree : MerkleTree = MerkleTree::of(&voting_machine.ballot_box);
+
+        for index in 0..5
+        {
+            let proof = tree.proof(&format!("code-000{}", index)).expect("ballot is published");
+            assert_eq!(proof.root, tree.root());
+            assert_eq!(proof.verify(), Ok(()));
+        }
+        assert_eq!(tree.proof("CODE-0009"), None);
+        assert_eq!(MerkleTree::of(&setup_voting_machine().ballot_box).root(), tree.root());
+    }
+
+    #[test]
+    fn tampered_proof_is_rejected()
+    {
+        let tree : MerkleTree = MerkleTree::of(&setup_voting_machine().ballot_box);
+        let mut proof = tree.proof("CODE-0001").expect("ballot is published");
+        proof.entry.choice = "E.Macron".to_string();
+
+        assert_eq!(proof.verify(), Err(VerificationFailure::InvalidProof("CODE-0001".to_string())));
+    }
+
+    #[test]
+    fn published_bulletin_is_checked_against_its_ballots()
+    {
+        let voting_machine : VotingMachine = setup_voting_machine();
+        let bulletin : PublishedBulletin = PublishedBulletin::of(&voting_machine);
+        assert_eq!(bulletin.verify(), Ok(()));
+
+        let mut inflated : PublishedBulletin = bulletin.clone();
+        inflated.tally.candidates.insert("M.Lepen".to_string(), 2);
+        assert_eq!(inflated.verify(), Err(VerificationFailure::TallyMismatch { choice: "candidate:M.Lepen".to_string(), published: 2, counted: 1 }));
+
+        let mut rewritten : PublishedBulletin = bulletin.clone();
+        rewritten.ballots[1] = BulletinEntry { tracking_code: "CODE-0001".to_string(), kind: "candidate".to_string(), choice: "E.Macron".to_string() };
+        assert!(matches!(rewritten.verify(), Err(VerificationFailure::RootMismatch { .. })));
+    }
+
+    #[test]
+    fn revealed_encrypted_bulletin_is_checked_per_candidate()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(46);
+        let key : SecretKey = SecretKey::generate(small_group(), &mut rng);
+        let mut voting_machine : VotingMachine = VotingMachine::new(vec![Candidate("E.Macron".to_string()), Candidate("M.Lepen".to_string())]);
+        voting_machine.scoreboard.election_key = Some(key.public_key());
+        for (index, (voter, candidate)) in [("Jean", "E.Macron"), ("Paul", "M.Lepen"), ("Marie", "E.Macron")].iter().enumerate()
+        {
+            let outcome : VoteOutcome = VoteOutcome::AcceptedVote(Voter(voter.to_string()), Candidate(candidate.to_string()));
+            let sealed = voting_machine.scoreboard.seal(&outcome, &mut rng).expect("election is encrypted");
+            voting_machine.deposit_sealed(outcome.voter(), sealed, TrackingCode(format!("CODE-000{}", index)));
+        }
+        voting_machine.close();
+        assert_eq!(PublishedBulletin::of(&voting_machine).verify(), Ok(()));
+        assert_eq!(voting_machine.reveal(&key, &mut rng), Revelation::Revealed(3));
+
+        let bulletin : PublishedBulletin = PublishedBulletin::of(&voting_machine);
+        assert_eq!(bulletin.verify(), Ok(()));
+
+        let mut swapped : PublishedBulletin = bulletin.clone();
+        swapped.tally.candidates.insert("E.Macron".to_string(), 1);
+        swapped.tally.candidates.insert("M.Lepen".to_string(), 2);
+        assert_eq!(swapped.verify(), Err(VerificationFailure::TallyMismatch { choice: "candidate:E.Macron".to_string(), published: 1, counted: 2 }));
+
+        let unproven : PublishedBulletin = PublishedBulletin { decryption: None, ..bulletin };
+        assert_eq!(unproven.verify(), Err(VerificationFailure::MissingDecryption));
+    }
+}
@@ -1,17 +1,317 @@
-use clap::Parser;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use clap::Subcommand;
 use clap::ValueEnum;
+use clap::parser::ValueSource;
+use serde::Deserialize;
 
-#[derive(Clone, Copy, ValueEnum)]
+use std::collections::BTreeMap as Map;
+
+use crate::audit::AuditOutcome;
+use crate::domain::{Candidate, CandidateProfile, RevotePolicy, Schedule, WithdrawalPolicy};
+use crate::election_file::{ConfigurationError, ElectionFile};
+use crate::messages::Language;
+
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StorageType {
     Memory,
     File,
 }
 
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Subcommand)]
+pub enum Command {
+    Init {
+        #[arg(long)]
+        encrypted: bool,
+        #[arg(long, requires_all = ["encrypted", "threshold"])]
+        trustees: Option<usize>,
+        #[arg(long, requires = "trustees")]
+        threshold: Option<usize>,
+        #[arg(long = "share-file", num_args = 1.., requires = "trustees")]
+        share_files: Vec<String>,
+    },
+    Vote {
+        voter: String,
+        candidate: Option<String>,
+        #[arg(long)]
+        code: Option<String>,
+        #[arg(long)]
+        on_behalf_of: Option<String>,
+    },
+    Voters,
+    Scores,
+    Close,
+    Rename {
+        candidate: String,
+        name: String,
+    },
+    IssueCodes {
+        voters: String,
+        #[arg(long)]
+        export: String,
+    },
+    Withdraw {
+        candidate: String,
+        #[arg(long, default_value = "keep")]
+        policy: WithdrawalPolicy,
+    },
+    RegisterProxy {
+        principal: String,
+        holder: String,
+        #[arg(long)]
+        valid_from: Option<u64>,
+        #[arg(long)]
+        valid_until: u64,
+    },
+    Bulletin,
+    Track {
+        tracking_code: String,
+    },
+    Proof {
+        tracking_code: String,
+    },
+    Verify {
+        #[arg(long, required_unless_present = "proof")]
+        bulletin: Option<String>,
+        #[arg(long)]
+        proof: Option<String>,
+        #[arg(long, conflicts_with = "bulletin")]
+        root: Option<String>,
+    },
+    PartialDecrypt {
+        #[arg(long)]
+        share: String,
+        #[arg(long)]
+        bulletin: String,
+    },
+    SubmitPartial {
+        partial: String,
+    },
+    Combine,
+    Audit {
+        #[arg(long)]
+        action: Option<String>,
+        #[arg(long)]
+        source: Option<String>,
+        #[arg(long)]
+        outcome: Option<AuditOutcome>,
+        #[arg(long)]
+        detail: Option<String>,
+        #[arg(long)]
+        since: Option<u64>,
+        #[arg(long)]
+        until: Option<u64>,
+    },
+    Shell,
+    Tui,
+}
+
+impl Command {
+    pub fn is_privileged(&self) -> bool {
+        matches!(self,
+            Command::Init { .. } | Command::Voters | Command::Scores | Command::Close | Command::Rename { .. } | Command::IssueCodes { .. }
+            | Command::Withdraw { .. } | Command::RegisterProxy { .. } | Command::SubmitPartial { .. } | Command::Combine)
+    }
+}
+
 #[derive(Parser)]
 pub struct Configuration {
-    #[arg(short = 'c', long, required = true, num_args = 1..)]
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    #[arg(short = 'c', long, global = true, num_args = 1..)]
     pub candidates: Vec<String>,
 
-    #[arg(short = 's', long, default_value = "memory")]
+    #[arg(short = 's', long, global = true, default_value = "memory")]
     pub storage_type: StorageType,
-}
\ No newline at end of file
+
+    #[arg(long, global = true, default_value = "data.txt")]
+    pub file_path: String,
+
+    #[arg(short = 'o', long, global = true, default_value = "table")]
+    pub output: OutputFormat,
+
+    #[arg(short = 'l', long, global = true)]
+    pub lang: Option<Language>,
+
+    #[arg(short = 'r', long)]
+    pub restore: Option<String>,
+
+    #[arg(short = 'f', long, global = true)]
+    pub force: bool,
+
+    #[arg(long, global = true, action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_value = "false", default_missing_value = "true")]
+    pub write_in: bool,
+
+    #[arg(long, global = true, default_value = "reject")]
+    pub revote: RevotePolicy,
+
+    #[arg(long, global = true, default_value_t = 1)]
+    pub max_proxies: usize,
+
+    #[arg(long)]
+    pub migrate_to: Option<StorageType>,
+
+    #[arg(long, required_if_eq("migrate_to", "file"))]
+    pub migrate_file_path: Option<String>,
+
+    #[arg(long)]
+    pub serve: Option<String>,
+
+    #[arg(long, global = true, env = "VOTING_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    #[arg(long, global = true, default_value = "audit.log")]
+    pub audit_log: String,
+
+    #[arg(long, global = true)]
+    pub election_key: Option<String>,
+
+    #[arg(long, global = true)]
+    pub title: Option<String>,
+
+    #[arg(long, global = true, requires = "closes_at")]
+    pub opens_at: Option<u64>,
+
+    #[arg(long, global = true, requires = "opens_at")]
+    pub closes_at: Option<u64>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(skip)]
+    pub schedule: Option<Schedule>,
+
+    #[arg(skip)]
+    pub profiles: Map<Candidate, CandidateProfile>,
+}
+
+fn given_on_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+impl Configuration {
+    pub fn load() -> Result<Self, ConfigurationError> {
+        Configuration::load_from(std::env::args_os())
+    }
+
+    pub fn load_from<I, T>(args: I) -> Result<Self, ConfigurationError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let matches : ArgMatches = Configuration::command().get_matches_from(args);
+        let mut configuration : Configuration = Configuration::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+        if let (Some(opens_at), Some(closes_at)) = (configuration.opens_at, configuration.closes_at) {
+            if closes_at <= opens_at {
+                return Err(ConfigurationError::invalid("closes_at", "the election must close after it opens"));
+            }
+            configuration.schedule = Some(Schedule { opens_at, closes_at });
+        }
+        if let Some(path) = configuration.config.clone() {
+            configuration.apply(ElectionFile::load(&path)?, &matches);
+        }
+        Ok(configuration)
+    }
+
+    fn apply(&mut self, election: ElectionFile, matches: &ArgMatches) {
+        if !given_on_command_line(matches, "candidates") {
+            self.candidates = election.candidates.iter().map(|candidate| candidate.id().to_string()).collect();
+            self.profiles = election.candidates
+                .iter()
+                .enumerate()
+                .map(|(index, candidate)| (Candidate(candidate.id().to_string()), candidate.profile(index)))
+                .collect();
+        }
+        if let (false, Some(storage_type)) = (given_on_command_line(matches, "storage_type"), election.storage.storage_type) {
+            self.storage_type = storage_type;
+        }
+        if let (false, Some(file_path)) = (given_on_command_line(matches, "file_path"), election.storage.file_path) {
+            self.file_path = file_path;
+        }
+        if let (None, Some(token)) = (matches.value_source("admin_token"), election.admin.token) {
+            self.admin_token = Some(token);
+        }
+        if let (false, Some(audit_log)) = (given_on_command_line(matches, "audit_log"), election.admin.audit_log) {
+            self.audit_log = audit_log;
+        }
+        if let (false, Some(write_in)) = (given_on_command_line(matches, "write_in"), election.write_in) {
+            self.write_in = write_in;
+        }
+        if let (false, Some(revote)) = (given_on_command_line(matches, "revote"), election.revote) {
+            self.revote = revote;
+        }
+        if let (false, Some(max_proxies)) = (given_on_command_line(matches, "max_proxies"), election.max_proxies) {
+            self.max_proxies = max_proxies;
+        }
+        if let (false, Some(title)) = (given_on_command_line(matches, "title"), election.title) {
+            self.title = Some(title);
+        }
+        if let (false, Some(schedule)) = (given_on_command_line(matches, "opens_at"), election.schedule) {
+            self.schedule = Some(Schedule { opens_at: schedule.opens_at, closes_at: schedule.closes_at });
+        }
+    }
+
+    pub fn language(&self) -> Language {
+        self.lang.unwrap_or_else(Language::from_env)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+
+    use super::{Configuration, StorageType};
+
+    #[test]
+    fn command_line_overrides_configuration_file() -> anyhow::Result<()>
+    {
+        let path = std::env::temp_dir().join("v050_configuration_override.toml");
+        fs::write(&path, "candidates = [\"E.Macron\", \"M.Lepen\"]\n[storage]\ntype = \"file\"\nfile_path = \"from_file.json\"\n")?;
+        let config_path : &str = path.to_str().unwrap_or_default();
+
+        let from_file : Configuration = Configuration::load_from(["vote", "--config", config_path])?;
+        let overridden : Configuration = Configuration::load_from(["vote", "--config", config_path, "-c", "J.Chirac", "--file-path", "cli.json", "scores"])?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(from_file.candidates, vec!["E.Macron", "M.Lepen"]);
+        assert_eq!(from_file.file_path, "from_file.json");
+        assert!(matches!(from_file.storage_type, StorageType::File));
+        assert_eq!(overridden.candidates, vec!["J.Chirac"]);
+        assert_eq!(overridden.file_path, "cli.json");
+        assert!(matches!(overridden.storage_type, StorageType::File));
+        Ok(())
+    }
+
+    #[test]
+    fn command_line_overrides_election_settings_of_the_file() -> anyhow::Result<()>
+    {
+        let path = std::env::temp_dir().join("v050_configuration_election_override.toml");
+        fs::write(&path, "title = \"Présidentielle\"\nwrite_in = true\ncandidates = [\"E.Macron\"]\n[schedule]\nopens_at = 1000\ncloses_at = 2000\n")?;
+        let config_path : &str = path.to_str().unwrap_or_default();
+
+        let from_file : Configuration = Configuration::load_from(["vote", "--config", config_path])?;
+        let overridden : Configuration = Configuration::load_from(["vote", "--config", config_path, "--write-in=false", "--title", "Municipales", "--opens-at", "3000", "--closes-at", "4000", "scores"])?;
+        let reversed = Configuration::load_from(["vote", "--opens-at", "4000", "--closes-at", "3000", "scores"]);
+        fs::remove_file(&path)?;
+
+        assert!(from_file.write_in);
+        assert_eq!(from_file.title.as_deref(), Some("Présidentielle"));
+        assert_eq!(from_file.schedule.map(|schedule| (schedule.opens_at, schedule.closes_at)), Some((1000, 2000)));
+        assert!(!overridden.write_in);
+        assert_eq!(overridden.title.as_deref(), Some("Municipales"));
+        assert_eq!(overridden.schedule.map(|schedule| (schedule.opens_at, schedule.closes_at)), Some((3000, 4000)));
+        assert!(Configuration::load_from(["vote", "--write-in", "scores"])?.write_in);
+        assert!(reversed.is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,15 @@
 use std::collections::BTreeMap as Map;
 use std::collections::BTreeSet as Set;
+use std::fmt;
+use std::str::FromStr;
+
+use hmac::{Hmac, KeyInit, Mac};
+use num_bigint::BigUint;
+use rand::Rng;
+use sha2::Sha256;
+
+use crate::elgamal::{self, Ciphertext, Group, PublicKey, SecretKey, ThresholdKey};
+use crate::trustees::PartialDecryption;
 
 #[derive(Debug, Ord, PartialOrd, PartialEq, Eq, Clone)]
 pub struct Voter(pub String);
@@ -7,17 +17,415 @@ pub struct Voter(pub String);
 #[derive(Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
 pub struct Candidate(pub String);
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandidateProfile {
+    pub name: String,
+    pub party: Option<String>,
+    pub ballot_order: usize,
+    pub description: Option<String>,
+    pub withdrawn: bool,
+}
+
+impl CandidateProfile {
+    pub fn named(name: &str, ballot_order: usize) -> Self {
+        CandidateProfile {
+            name: name.to_string(),
+            party: None,
+            ballot_order,
+            description: None,
+            withdrawn: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub enum InvalidReason {
+    UnknownCandidate,
+    WithdrawnCandidate,
+}
+
+impl InvalidReason {
+    pub const ALL: [InvalidReason; 2] = [
+        InvalidReason::UnknownCandidate,
+        InvalidReason::WithdrawnCandidate,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            InvalidReason::UnknownCandidate => "unknown_candidate",
+            InvalidReason::WithdrawnCandidate => "withdrawn_candidate",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        InvalidReason::ALL.into_iter().find(|reason| reason.code() == code)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WithdrawalPolicy {
+    Keep,
+    Invalidate,
+}
+
+impl WithdrawalPolicy {
+    pub fn code(&self) -> &'static str {
+        match self {
+            WithdrawalPolicy::Keep => "keep",
+            WithdrawalPolicy::Invalidate => "invalidate",
+        }
+    }
+}
+
+impl FromStr for WithdrawalPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "keep" => Ok(WithdrawalPolicy::Keep),
+            "invalidate" => Ok(WithdrawalPolicy::Invalidate),
+            _ => Err(format!("unknown withdrawal policy {}, expected keep or invalidate", policy)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RevotePolicy {
+    #[default]
+    Reject,
+    Supersede,
+}
+
+impl RevotePolicy {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RevotePolicy::Reject => "reject",
+            RevotePolicy::Supersede => "supersede",
+        }
+    }
+}
+
+impl FromStr for RevotePolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "reject" => Ok(RevotePolicy::Reject),
+            "supersede" => Ok(RevotePolicy::Supersede),
+            _ => Err(format!("unknown re-voting policy {}, expected reject or supersede", policy)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Withdrawal {
+    Withdrawn(Candidate),
+    UnknownCandidate(Candidate),
+    AlreadyWithdrawn(Candidate),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opening {
+    Opened,
+    AlreadyOpen,
+    Closed,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Score(pub usize);
 
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AttendanceSheet {
+    pub attendees: Set<Voter>,
+    pub proxy_holders: Map<Voter, Voter>,
+}
+
+impl AttendanceSheet {
+    pub fn holder_for(&self, voter: &Voter) -> Option<&Voter> {
+        self.proxy_holders.get(voter)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proxy {
+    pub holder: Voter,
+    pub valid_from: u64,
+    pub valid_until: u64,
+}
+
+impl Proxy {
+    pub fn is_valid_at(&self, at: u64) -> bool {
+        (self.valid_from..=self.valid_until).contains(&at)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub opens_at: u64,
+    pub closes_at: u64,
+}
+
+impl Schedule {
+    pub fn refusal(&self, voter: &Voter, at: u64) -> Option<VoteOutcome> {
+        if at < self.opens_at {
+            Some(VoteOutcome::NotYetOpen(voter.clone()))
+        } else if at >= self.closes_at {
+            Some(VoteOutcome::ElectionClosed(voter.clone()))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProxyRegister(pub Map<Voter, Proxy>);
+
+impl ProxyRegister {
+    pub fn held_by(&self, holder: &Voter, at: u64) -> usize {
+        self.0.values().filter(|proxy| proxy.holder == *holder && proxy.valid_until >= at).count()
+    }
+
+    pub fn authorizes(&self, principal: &Voter, holder: &Voter, at: u64) -> bool {
+        self.0.get(principal).is_some_and(|proxy| proxy.holder == *holder && proxy.is_valid_at(at))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Delegation {
+    Registered(Voter, Voter),
+    SelfDelegation(Voter),
+    InvalidPeriod(Voter),
+    UnknownVoter(Voter),
+    AlreadyVoted(Voter),
+    AlreadyDelegated(Voter),
+    TooManyProxies(Voter, usize),
+}
+
+impl Delegation {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Delegation::Registered(_, _) => "registered",
+            Delegation::SelfDelegation(_) => "self_delegation",
+            Delegation::InvalidPeriod(_) => "invalid_period",
+            Delegation::UnknownVoter(_) => "unknown_voter",
+            Delegation::AlreadyVoted(_) => "already_voted",
+            Delegation::AlreadyDelegated(_) => "already_delegated",
+            Delegation::TooManyProxies(_, _) => "too_many_proxies",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issuance {
+    Issued(Vec<(Voter, String)>),
+    AlreadyIssued(Vec<Voter>),
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct ElectionSecret(pub [u8; 32]);
+
+impl ElectionSecret {
+    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+        ElectionSecret(rng.gen())
+    }
+
+    pub fn checksum(&self, contents: &[u8]) -> String {
+        self.authenticate(&[b"archive:", contents])
+    }
+
+    fn authenticate(&self, parts: &[&[u8]]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        for part in parts {
+            mac.update(part);
+        }
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+impl fmt::Debug for ElectionSecret {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("ElectionSecret(..)")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeHash(pub String);
+
+impl CodeHash {
+    pub fn of(secret: &ElectionSecret, voter: &Voter, code: &str) -> Self {
+        let normalized_code : String = code.chars()
+            .filter(|character| character.is_ascii_alphanumeric())
+            .map(|character| character.to_ascii_uppercase())
+            .collect();
+        CodeHash(secret.authenticate(&[b"code:", voter.0.as_bytes(), b":", normalized_code.as_bytes()]))
+    }
+
+    pub fn of_admin_token(secret: &ElectionSecret, admin_token: &str) -> Self {
+        CodeHash(secret.authenticate(&[b"admin:", admin_token.as_bytes()]))
+    }
+}
+
+#[derive(Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct VoterToken(pub String);
+
+impl VoterToken {
+    pub fn of(secret: &ElectionSecret, voter: &Voter) -> Self {
+        VoterToken(secret.authenticate(&[b"token:", voter.0.as_bytes()]))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VoterRoll(pub Map<Voter, CodeHash>);
+
+impl VoterRoll {
+    pub fn issue(&mut self, secret: &ElectionSecret, voter: Voter, code: &str) {
+        let code_hash : CodeHash = CodeHash::of(secret, &voter, code);
+        self.0.insert(voter, code_hash);
+    }
+
+    pub fn authenticate(&self, secret: &ElectionSecret, ballot_paper: &BallotPaper) -> Option<VoteOutcome> {
+        let signer : &Voter = ballot_paper.signer();
+        let Some(code) = ballot_paper.code.as_deref().filter(|code| !code.trim().is_empty()) else {
+            return Some(VoteOutcome::MissingCode(signer.clone()));
+        };
+        match self.0.get(signer) {
+            Some(code_hash) if *code_hash == CodeHash::of(secret, signer, code) => None,
+            _ => Some(VoteOutcome::WrongCode(signer.clone())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub struct TrackingCode(pub String);
+
+impl TrackingCode {
+    pub fn normalized(code: &str) -> Self {
+        let characters : Vec<char> = code.chars()
+            .filter(|character| character.is_ascii_alphanumeric())
+            .map(|character| character.to_ascii_uppercase())
+            .collect();
+        TrackingCode(characters.chunks(4).map(|group| group.iter().collect::<String>()).collect::<Vec<String>>().join("-"))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncryptedBallot(pub Map<String, Ciphertext>);
+
+impl EncryptedBallot {
+    pub fn seal(key: &PublicKey, slots: &[String], chosen: &str, rng: &mut impl Rng) -> Self {
+        EncryptedBallot(slots.iter().map(|slot| (slot.clone(), key.encrypt(u64::from(slot == chosen), rng))).collect())
+    }
+
+    pub fn add(&self, other: &EncryptedBallot, group: &Group) -> Self {
+        let mut total : EncryptedBallot = self.clone();
+        for (slot, ciphertext) in &other.0 {
+            let sum : Ciphertext = total.0.get(slot).cloned().unwrap_or_else(Ciphertext::zero).add(ciphertext, group);
+            total.0.insert(slot.clone(), sum);
+        }
+        total
+    }
+
+    pub fn transfer(&mut self, from: &str, to: &str, group: &Group) {
+        if let Some(moved) = self.0.insert(from.to_string(), Ciphertext::zero()) {
+            let sum : Ciphertext = self.0.get(to).cloned().unwrap_or_else(Ciphertext::zero).add(&moved, group);
+            self.0.insert(to.to_string(), sum);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ballot {
+    Candidate(Candidate),
+    WriteIn(String),
+    Blank,
+    Invalid(InvalidReason),
+    Sealed(EncryptedBallot),
+}
+
+impl Ballot {
+    pub fn of(outcome: &VoteOutcome) -> Option<Self> {
+        match outcome {
+            VoteOutcome::AcceptedVote(_, candidate) => Some(Ballot::Candidate(candidate.clone())),
+            VoteOutcome::WriteInVote(_, name) => Some(Ballot::WriteIn(name.clone())),
+            VoteOutcome::BlankVote(_) => Some(Ballot::Blank),
+            VoteOutcome::InvalidVote(_, reason) => Some(Ballot::Invalid(*reason)),
+            VoteOutcome::HasAlreadyVoted(_) | VoteOutcome::NotYetOpen(_) | VoteOutcome::ElectionClosed(_) | VoteOutcome::MissingCode(_) | VoteOutcome::WrongCode(_) | VoteOutcome::InvalidProxy(_, _) => None,
+        }
+    }
+
+    pub fn slot(&self) -> String {
+        match self {
+            Ballot::Candidate(candidate) => format!("candidate:{}", candidate.0),
+            Ballot::WriteIn(name) => format!("write_in:{}", name),
+            Ballot::Blank => String::from("blank"),
+            Ballot::Invalid(reason) => format!("invalid:{}", reason.code()),
+            Ballot::Sealed(_) => String::from("sealed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Revelation {
+    Revealed(usize),
+    NotEncrypted,
+    StillOpen,
+    WrongKey,
+    Undecryptable,
+    NotShared,
+    MissingShares { submitted: usize, threshold: usize },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Submission {
+    Accepted { submitted: usize, threshold: usize },
+    NotShared,
+    StillOpen,
+    UnknownTrustee(u64),
+    StaleTally(u64),
+    InvalidProof(u64),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BallotBox(pub Map<TrackingCode, Ballot>);
+
+impl BallotBox {
+    pub fn contains(&self, tracking_code: &TrackingCode) -> bool {
+        self.0.contains_key(tracking_code)
+    }
+
+    pub fn lookup(&self, tracking_code: &str) -> Option<&Ballot> {
+        self.0.get(&TrackingCode::normalized(tracking_code))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
-pub struct AttendanceSheet(pub Set<Voter>);
+pub struct Receipt {
+    pub outcome: VoteOutcome,
+    pub tracking_code: Option<TrackingCode>,
+    pub superseded: Option<TrackingCode>,
+}
+
+impl Receipt {
+    pub fn refused(outcome: VoteOutcome) -> Self {
+        Receipt { outcome, tracking_code: None, superseded: None }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Scoreboard {
     pub scores: Map<Candidate, Score>,
+    pub profiles: Map<Candidate, CandidateProfile>,
     pub blank_scores: Score,
     pub invalid_scores: Score,
+    pub invalid_reasons: Map<InvalidReason, Score>,
+    pub write_in: bool,
+    pub write_in_scores: Map<String, Score>,
+    pub sealed_scores: Score,
+    pub election_key: Option<PublicKey>,
+    pub threshold_key: Option<ThresholdKey>,
+    pub revote: RevotePolicy,
+    pub max_proxies: usize,
+    pub schedule: Option<Schedule>,
 }
 
 impl Scoreboard {
@@ -25,8 +433,10 @@ impl Scoreboard {
     pub fn new(candidates: Vec<Candidate>) -> Self {
         
         let mut scores: Map<Candidate, Score> = Map::new();
+        let mut profiles: Map<Candidate, CandidateProfile> = Map::new();
 
-        for candidate in candidates {
+        for (ballot_order, candidate) in candidates.into_iter().enumerate() {
+            profiles.insert(candidate.clone(), CandidateProfile::named(&candidate.0, ballot_order));
             scores.insert(candidate, Score(0));
         }
 
@@ -35,73 +445,570 @@ impl Scoreboard {
 
         Self {
             scores,
+            profiles,
             blank_scores,
             invalid_scores,
+            invalid_reasons: Map::new(),
+            write_in: false,
+            write_in_scores: Map::new(),
+            sealed_scores: Score(0),
+            election_key: None,
+            threshold_key: None,
+            revote: RevotePolicy::Reject,
+            max_proxies: 1,
+            schedule: None,
+        }
+    }
+
+    pub fn with_profiles(profiles: Map<Candidate, CandidateProfile>) -> Self {
+        let mut scoreboard : Scoreboard = Scoreboard::new(profiles.keys().cloned().collect());
+        scoreboard.profiles = profiles;
+        scoreboard
+    }
+
+    pub fn display_name<'a>(&'a self, candidate: &'a Candidate) -> &'a str {
+        self.profiles.get(candidate).map(|profile| profile.name.as_str()).unwrap_or(&candidate.0)
+    }
+
+    pub fn ballot(&self) -> Vec<&Candidate> {
+        let mut candidates : Vec<&Candidate> = self.scores.keys().collect();
+        candidates.sort_by_key(|candidate| self.profiles.get(*candidate).map(|profile| profile.ballot_order).unwrap_or(usize::MAX));
+        candidates
+    }
+
+    pub fn rename(&mut self, candidate: &Candidate, name: &str) -> bool {
+        if !self.scores.contains_key(candidate) {
+            return false;
+        }
+        let ballot_order : usize = self.profiles.len();
+        self.profiles.entry(candidate.clone())
+            .or_insert_with(|| CandidateProfile::named(name, ballot_order))
+            .name = name.to_string();
+        true
+    }
+
+    pub fn total_votes(&self) -> usize {
+        self.expressed_votes() + self.blank_scores.0 + self.invalid_scores.0 + self.sealed_scores.0
+    }
+
+    pub fn expressed_votes(&self) -> usize {
+        self.scores.values().chain(self.write_in_scores.values()).map(|score| score.0).sum::<usize>()
+    }
+
+    pub fn is_withdrawn(&self, candidate: &Candidate) -> bool {
+        self.profiles.get(candidate).is_some_and(|profile| profile.withdrawn)
+    }
+
+    pub fn withdraw(&mut self, candidate: &Candidate, policy: WithdrawalPolicy) -> Withdrawal {
+        if !self.scores.contains_key(candidate) {
+            return Withdrawal::UnknownCandidate(candidate.clone());
+        }
+        if self.is_withdrawn(candidate) {
+            return Withdrawal::AlreadyWithdrawn(candidate.clone());
+        }
+
+        match policy {
+            WithdrawalPolicy::Keep => {}
+            WithdrawalPolicy::Invalidate => {
+                if let Some(score) = self.scores.get_mut(candidate) {
+                    self.invalid_scores.0 += score.0;
+                    self.invalid_reasons.entry(InvalidReason::WithdrawnCandidate).or_insert(Score(0)).0 += score.0;
+                    score.0 = 0;
+                }
+            }
+        }
+
+        let ballot_order : usize = self.profiles.len();
+        self.profiles.entry(candidate.clone())
+            .or_insert_with(|| CandidateProfile::named(&candidate.0, ballot_order))
+            .withdrawn = true;
+        Withdrawal::Withdrawn(candidate.clone())
+    }
+
+    pub fn slots(&self) -> Vec<String> {
+        let mut slots : Vec<String> = self.scores.keys().map(|candidate| Ballot::Candidate(candidate.clone()).slot()).collect();
+        slots.push(Ballot::Blank.slot());
+        slots.extend(InvalidReason::ALL.iter().map(|reason| Ballot::Invalid(*reason).slot()));
+        slots
+    }
+
+    pub fn seal(&self, outcome: &VoteOutcome, rng: &mut impl Rng) -> Option<EncryptedBallot> {
+        let key : &PublicKey = self.election_key.as_ref()?;
+        let ballot : Ballot = Ballot::of(outcome)?;
+        Some(EncryptedBallot::seal(key, &self.slots(), &ballot.slot(), rng))
+    }
+
+    pub fn reveal(&mut self, totals: &Map<String, usize>) {
+        let count = |slot: String| totals.get(&slot).copied().unwrap_or(0);
+        for (candidate, score) in self.scores.iter_mut() {
+            score.0 = count(Ballot::Candidate(candidate.clone()).slot());
+        }
+        self.blank_scores = Score(count(Ballot::Blank.slot()));
+        self.invalid_reasons = InvalidReason::ALL.iter()
+            .map(|reason| (*reason, Score(count(Ballot::Invalid(*reason).slot()))))
+            .filter(|(_, score)| score.0 > 0)
+            .collect();
+        self.invalid_scores = Score(self.invalid_reasons.values().map(|score| score.0).sum());
+        self.sealed_scores = Score(0);
+    }
+
+    pub fn outcome_of(&self, ballot_paper: BallotPaper) -> VoteOutcome {
+        match ballot_paper.candidate {
+            Some(candidate) => {
+                if self.is_withdrawn(&candidate) {
+                    VoteOutcome::InvalidVote(ballot_paper.voter, InvalidReason::WithdrawnCandidate)
+                } else if self.scores.contains_key(&candidate) {
+                    VoteOutcome::AcceptedVote(ballot_paper.voter, candidate)
+                } else if self.write_in && self.election_key.is_none() && !candidate.0.trim().is_empty() {
+                    VoteOutcome::WriteInVote(ballot_paper.voter, candidate.0.trim().to_string())
+                } else {
+                    VoteOutcome::InvalidVote(ballot_paper.voter, InvalidReason::UnknownCandidate)
+                }
+            }
+            None => {
+                VoteOutcome::BlankVote(ballot_paper.voter)
+            }
+        }
+    }
+
+    pub fn record(&mut self, outcome: &VoteOutcome) {
+        match outcome {
+            VoteOutcome::AcceptedVote(_, candidate) => {
+                self.scores.entry(candidate.clone()).and_modify(|score| score.0 += 1);
+            }
+            VoteOutcome::WriteInVote(_, name) => {
+                self.write_in_scores.entry(name.clone()).or_insert(Score(0)).0 += 1;
+            }
+            VoteOutcome::BlankVote(_) => self.blank_scores.0 += 1,
+            VoteOutcome::InvalidVote(_, reason) => {
+                self.invalid_scores.0 += 1;
+                self.invalid_reasons.entry(*reason).or_insert(Score(0)).0 += 1;
+            }
+            VoteOutcome::HasAlreadyVoted(_) | VoteOutcome::NotYetOpen(_) | VoteOutcome::ElectionClosed(_) | VoteOutcome::MissingCode(_) | VoteOutcome::WrongCode(_) | VoteOutcome::InvalidProxy(_, _) => {}
+        }
+    }
+
+    pub fn count(&mut self, ballot: &Ballot) {
+        match ballot {
+            Ballot::Candidate(candidate) => {
+                self.scores.entry(candidate.clone()).and_modify(|score| score.0 += 1);
+            }
+            Ballot::WriteIn(name) => {
+                self.write_in_scores.entry(name.clone()).or_insert(Score(0)).0 += 1;
+            }
+            Ballot::Blank => self.blank_scores.0 += 1,
+            Ballot::Invalid(reason) => {
+                self.invalid_scores.0 += 1;
+                self.invalid_reasons.entry(*reason).or_insert(Score(0)).0 += 1;
+            }
+            Ballot::Sealed(_) => self.sealed_scores.0 += 1,
+        }
+    }
+
+    pub fn unrecord(&mut self, ballot: &Ballot) {
+        let decrement = |score: &mut Score| score.0 = score.0.saturating_sub(1);
+        match ballot {
+            Ballot::Candidate(candidate) => {
+                self.scores.entry(candidate.clone()).and_modify(decrement);
+            }
+            Ballot::WriteIn(name) => {
+                self.write_in_scores.entry(name.clone()).and_modify(decrement);
+                self.write_in_scores.retain(|_, score| score.0 > 0);
+            }
+            Ballot::Blank => decrement(&mut self.blank_scores),
+            Ballot::Invalid(reason) => {
+                decrement(&mut self.invalid_scores);
+                self.invalid_reasons.entry(*reason).and_modify(decrement);
+                self.invalid_reasons.retain(|_, score| score.0 > 0);
+            }
+            Ballot::Sealed(_) => decrement(&mut self.sealed_scores),
         }
     }
 }
 
+#[derive(Clone)]
 pub struct BallotPaper 
 {
     pub voter: Voter,
-    pub candidate: Option<Candidate>
+    pub candidate: Option<Candidate>,
+    pub code: Option<String>,
+    pub holder: Option<Voter>,
+}
+
+impl BallotPaper {
+    pub fn signer(&self) -> &Voter {
+        self.holder.as_ref().unwrap_or(&self.voter)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum VoteOutcome {
     AcceptedVote(Voter, Candidate),
+    WriteInVote(Voter, String),
     BlankVote(Voter),
-    InvalidVote(Voter),
+    InvalidVote(Voter, InvalidReason),
     HasAlreadyVoted(Voter),
+    NotYetOpen(Voter),
+    ElectionClosed(Voter),
+    MissingCode(Voter),
+    WrongCode(Voter),
+    InvalidProxy(Voter, Voter),
+}
+
+impl VoteOutcome {
+    pub fn voter(&self) -> &Voter {
+        match self {
+            VoteOutcome::AcceptedVote(voter, _) => voter,
+            VoteOutcome::WriteInVote(voter, _) => voter,
+            VoteOutcome::BlankVote(voter) => voter,
+            VoteOutcome::InvalidVote(voter, _) => voter,
+            VoteOutcome::HasAlreadyVoted(voter) => voter,
+            VoteOutcome::NotYetOpen(voter) => voter,
+            VoteOutcome::ElectionClosed(voter) => voter,
+            VoteOutcome::MissingCode(voter) => voter,
+            VoteOutcome::WrongCode(voter) => voter,
+            VoteOutcome::InvalidProxy(voter, _) => voter,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            VoteOutcome::AcceptedVote(_, _) => "accepted",
+            VoteOutcome::WriteInVote(_, _) => "write_in",
+            VoteOutcome::BlankVote(_) => "blank",
+            VoteOutcome::InvalidVote(_, _) => "invalid",
+            VoteOutcome::HasAlreadyVoted(_) => "already_voted",
+            VoteOutcome::NotYetOpen(_) => "not_yet_open",
+            VoteOutcome::ElectionClosed(_) => "closed",
+            VoteOutcome::MissingCode(_) => "missing_code",
+            VoteOutcome::WrongCode(_) => "wrong_code",
+            VoteOutcome::InvalidProxy(_, _) => "invalid_proxy",
+        }
+    }
+
+    pub fn is_recorded(&self) -> bool {
+        !matches!(self, VoteOutcome::HasAlreadyVoted(_) | VoteOutcome::NotYetOpen(_) | VoteOutcome::ElectionClosed(_) | VoteOutcome::MissingCode(_) | VoteOutcome::WrongCode(_) | VoteOutcome::InvalidProxy(_, _))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct VotingMachine {
     pub voters: AttendanceSheet,
     pub scoreboard: Scoreboard,
+    pub closed: bool,
+    pub roll: Option<VoterRoll>,
+    pub ballot_box: BallotBox,
+    pub partial_decryptions: Map<u64, PartialDecryption>,
+    pub ballot_tokens: Map<VoterToken, TrackingCode>,
+    pub proxies: ProxyRegister,
+    pub secret: ElectionSecret,
+    pub admin: Option<CodeHash>,
+    pub decryption: Option<PartialDecryption>,
 }
 
 impl VotingMachine {
     pub fn new(candidates: Vec<Candidate>) -> Self {
         
         let scoreboard: Scoreboard = Scoreboard::new(candidates);
-        let voters: AttendanceSheet = AttendanceSheet(Set::new());
+        let voters: AttendanceSheet = AttendanceSheet::default();
 
         Self {
             scoreboard,
-            voters
+            voters,
+            closed: false,
+            roll: None,
+            ballot_box: BallotBox::default(),
+            partial_decryptions: Map::new(),
+            ballot_tokens: Map::new(),
+            proxies: ProxyRegister::default(),
+            secret: ElectionSecret::generate(&mut rand::thread_rng()),
+            admin: None,
+            decryption: None,
         }
     }
 
-    pub fn recover_from(voters: AttendanceSheet, scoreboard: Scoreboard) -> Self {
-        Self { voters, scoreboard }
+    pub fn with_scoreboard(scoreboard: Scoreboard) -> Self {
+        Self::recover_from(AttendanceSheet::default(), scoreboard, false)
     }
 
-    pub fn vote(&mut self, ballot_paper: BallotPaper) -> VoteOutcome {
-        if self.voters.0.contains(&ballot_paper.voter) {
+    pub fn recover_from(voters: AttendanceSheet, scoreboard: Scoreboard, closed: bool) -> Self {
+        Self { voters, scoreboard, closed, roll: None, ballot_box: BallotBox::default(), partial_decryptions: Map::new(), ballot_tokens: Map::new(), proxies: ProxyRegister::default(), secret: ElectionSecret::generate(&mut rand::thread_rng()), admin: None, decryption: None }
+    }
+
+    pub fn vote(&self, ballot_paper: BallotPaper, at: u64) -> VoteOutcome {
+        if self.closed {
+            return VoteOutcome::ElectionClosed(ballot_paper.voter);
+        }
+
+        if let Some(refusal) = self.scoreboard.schedule.as_ref().and_then(|schedule| schedule.refusal(&ballot_paper.voter, at)) {
+            return refusal;
+        }
+
+        if let Some(refusal) = self.authenticate(&ballot_paper, at) {
+            return refusal;
+        }
+
+        if self.voters.attendees.contains(&ballot_paper.voter) && self.superseded_ballot(&ballot_paper.voter).is_none() {
             return VoteOutcome::HasAlreadyVoted(ballot_paper.voter);
         } 
 
-        match ballot_paper.candidate {
-            Some(candidate) => {
-                if self.scoreboard.scores.contains_key(&candidate) {
-                    return VoteOutcome::AcceptedVote(ballot_paper.voter, candidate);
-                } else {
-                    return VoteOutcome::InvalidVote(ballot_paper.voter);
-                }
+        self.scoreboard.outcome_of(ballot_paper)
+    }
+
+    pub fn authenticate(&self, ballot_paper: &BallotPaper, at: u64) -> Option<VoteOutcome> {
+        if let Some(holder) = &ballot_paper.holder {
+            if !self.proxies.authorizes(&ballot_paper.voter, holder, at) {
+                return Some(VoteOutcome::InvalidProxy(ballot_paper.voter.clone(), holder.clone()));
+            }
+        }
+        self.roll.as_ref().and_then(|roll| roll.authenticate(&self.secret, ballot_paper))
+    }
+
+    pub fn register_proxy(&mut self, principal: Voter, proxy: Proxy, at: u64) -> Delegation {
+        if principal == proxy.holder {
+            return Delegation::SelfDelegation(principal);
+        }
+        if proxy.valid_until < proxy.valid_from.max(at) {
+            return Delegation::InvalidPeriod(principal);
+        }
+        if let Some(unknown) = [&principal, &proxy.holder].into_iter().find(|voter| self.roll.as_ref().is_some_and(|roll| !roll.0.contains_key(voter))) {
+            return Delegation::UnknownVoter(unknown.clone());
+        }
+        if self.voters.attendees.contains(&principal) {
+            return Delegation::AlreadyVoted(principal);
+        }
+        if self.proxies.0.get(&principal).is_some_and(|existing| existing.valid_until >= at) {
+            return Delegation::AlreadyDelegated(principal);
+        }
+        if self.proxies.held_by(&proxy.holder, at) >= self.scoreboard.max_proxies {
+            return Delegation::TooManyProxies(proxy.holder, self.scoreboard.max_proxies);
+        }
+
+        let delegation : Delegation = Delegation::Registered(principal.clone(), proxy.holder.clone());
+        self.proxies.0.insert(principal, proxy);
+        delegation
+    }
+
+    pub fn countersign(&mut self, voter: &Voter, holder: Option<Voter>) {
+        match holder {
+            Some(holder) => {
+                self.voters.proxy_holders.insert(voter.clone(), holder);
             }
             None => {
-                return VoteOutcome::BlankVote(ballot_paper.voter);
+                self.voters.proxy_holders.remove(voter);
+            }
+        }
+    }
+
+    pub fn ballot_token(&self, voter: &Voter) -> Option<VoterToken> {
+        (self.scoreboard.revote == RevotePolicy::Supersede).then(|| VoterToken::of(&self.secret, voter))
+    }
+
+    pub fn superseded_ballot(&self, voter: &Voter) -> Option<&TrackingCode> {
+        self.ballot_tokens.get(&self.ballot_token(voter)?)
+    }
+
+    fn supersede(&mut self, token: Option<VoterToken>, tracking_code: &TrackingCode) {
+        let Some(token) = token else {
+            return;
+        };
+        if let Some(previous) = self.ballot_tokens.insert(token, tracking_code.clone()) {
+            if let Some(ballot) = self.ballot_box.0.remove(&previous) {
+                self.scoreboard.unrecord(&ballot);
+            }
+        }
+    }
+
+    pub fn attend(&mut self, voter: &Voter, holder: Option<Voter>) {
+        self.voters.attendees.insert(voter.clone());
+        self.countersign(voter, holder);
+    }
+
+    pub fn file_ballot(&mut self, ballot: Ballot, token: Option<VoterToken>, tracking_code: TrackingCode) {
+        self.supersede(token, &tracking_code);
+        self.scoreboard.count(&ballot);
+        self.ballot_box.0.insert(tracking_code, ballot);
+    }
+
+    pub fn issue_code(&mut self, voter: Voter, code: &str) {
+        self.roll.get_or_insert_with(VoterRoll::default).issue(&self.secret, voter, code);
+    }
+
+    pub fn holds_code(&self, voter: &Voter) -> bool {
+        self.roll.as_ref().is_some_and(|roll| roll.0.contains_key(voter))
+    }
+
+    pub fn withdraw(&mut self, candidate: &Candidate, policy: WithdrawalPolicy) -> Withdrawal {
+        let withdrawal : Withdrawal = self.scoreboard.withdraw(candidate, policy);
+        if let (Withdrawal::Withdrawn(_), WithdrawalPolicy::Invalidate) = (&withdrawal, policy) {
+            let candidate_slot : String = Ballot::Candidate(candidate.clone()).slot();
+            let invalid_slot : String = Ballot::Invalid(InvalidReason::WithdrawnCandidate).slot();
+            for ballot in self.ballot_box.0.values_mut() {
+                match ballot {
+                    Ballot::Candidate(chosen) if chosen == candidate => *ballot = Ballot::Invalid(InvalidReason::WithdrawnCandidate),
+                    Ballot::Sealed(sealed) => {
+                        if let Some(key) = &self.scoreboard.election_key {
+                            sealed.transfer(&candidate_slot, &invalid_slot, &key.group);
+                        }
+                    }
+                    _ => {}
+                }
             }
         }
+        withdrawal
+    }
+
+    pub fn deposit_sealed(&mut self, voter: &Voter, ballot: EncryptedBallot, tracking_code: TrackingCode) {
+        self.voters.attendees.insert(voter.clone());
+        self.file_ballot(Ballot::Sealed(ballot), self.ballot_token(voter), tracking_code);
+    }
+
+    pub fn encrypted_tally(&self) -> Option<EncryptedBallot> {
+        let key : &PublicKey = self.scoreboard.election_key.as_ref()?;
+        Some(self.ballot_box.0.values()
+            .filter_map(|ballot| match ballot {
+                Ballot::Sealed(sealed) => Some(sealed),
+                _ => None,
+            })
+            .fold(EncryptedBallot::default(), |total, sealed| total.add(sealed, &key.group)))
+    }
+
+    pub fn reveal(&mut self, key: &SecretKey, rng: &mut impl Rng) -> Revelation {
+        let Some(election_key) = self.scoreboard.election_key.clone() else {
+            return Revelation::NotEncrypted;
+        };
+        if !self.closed {
+            return Revelation::StillOpen;
+        }
+        if key.public_key() != election_key {
+            return Revelation::WrongKey;
+        }
+
+        let ballots : usize = self.sealed_ballots();
+        let aggregate : EncryptedBallot = self.encrypted_tally().unwrap_or_default();
+        let totals : Option<Map<String, usize>> = aggregate.0
+            .iter()
+            .map(|(slot, ciphertext)| key.decrypt(ciphertext, ballots as u64).map(|count| (slot.clone(), count as usize)))
+            .collect();
+        let revelation : Revelation = self.reveal_totals(totals, ballots);
+        if let Revelation::Revealed(_) = revelation {
+            self.decryption = Some(PartialDecryption::compute(&key.as_share(), &aggregate, rng));
+        }
+        revelation
+    }
+
+    pub fn submit_partial(&mut self, partial: PartialDecryption) -> Submission {
+        let (Some(threshold_key), Some(election_key)) = (self.scoreboard.threshold_key.clone(), self.scoreboard.election_key.clone()) else {
+            return Submission::NotShared;
+        };
+        if !self.closed {
+            return Submission::StillOpen;
+        }
+        if !threshold_key.has_trustee(partial.trustee) {
+            return Submission::UnknownTrustee(partial.trustee);
+        }
+        if !partial.decrypts(&self.encrypted_tally().unwrap_or_default()) {
+            return Submission::StaleTally(partial.trustee);
+        }
+        let verification_key : BigUint = threshold_key.verification_key(&election_key.group, partial.trustee);
+        if partial.factors.values().any(|factor| !factor.verify(&election_key.group, &verification_key)) {
+            return Submission::InvalidProof(partial.trustee);
+        }
+
+        self.partial_decryptions.insert(partial.trustee, partial);
+        Submission::Accepted { submitted: self.partial_decryptions.len(), threshold: threshold_key.threshold }
+    }
+
+    pub fn combine(&mut self) -> Revelation {
+        let (Some(threshold_key), Some(election_key)) = (&self.scoreboard.threshold_key, &self.scoreboard.election_key) else {
+            return Revelation::NotShared;
+        };
+        if !self.closed {
+            return Revelation::StillOpen;
+        }
+
+        let aggregate : EncryptedBallot = self.encrypted_tally().unwrap_or_default();
+        let partials : Vec<&PartialDecryption> = self.partial_decryptions.values()
+            .filter(|partial| partial.decrypts(&aggregate))
+            .take(threshold_key.threshold)
+            .collect();
+        if partials.len() < threshold_key.threshold {
+            return Revelation::MissingShares { submitted: partials.len(), threshold: threshold_key.threshold };
+        }
+
+        let ballots : usize = self.sealed_ballots();
+        let totals : Option<Map<String, usize>> = aggregate.0
+            .iter()
+            .map(|(slot, ciphertext)| {
+                let factors : Vec<(u64, BigUint)> = partials.iter().map(|partial| (partial.trustee, partial.factors[slot].factor.clone())).collect();
+                elgamal::combine(&election_key.group, ciphertext, &factors, ballots as u64).map(|count| (slot.clone(), count as usize))
+            })
+            .collect();
+        self.reveal_totals(totals, ballots)
+    }
+
+    fn sealed_ballots(&self) -> usize {
+        self.ballot_box.0.values().filter(|ballot| matches!(ballot, Ballot::Sealed(_))).count()
+    }
+
+    fn reveal_totals(&mut self, totals: Option<Map<String, usize>>, ballots: usize) -> Revelation {
+        match totals {
+            Some(totals) => {
+                self.scoreboard.reveal(&totals);
+                Revelation::Revealed(ballots)
+            }
+            None => Revelation::Undecryptable,
+        }
+    }
+
+    pub fn deposit(&mut self, outcome: &VoteOutcome, tracking_code: TrackingCode) {
+        if let Some(ballot) = Ballot::of(outcome) {
+            self.voters.attendees.insert(outcome.voter().clone());
+            self.file_ballot(ballot, self.ballot_token(outcome.voter()), tracking_code);
+        }
     }
 
     pub fn get_scoreboard(&mut self) -> &mut Scoreboard {
-        return &mut self.scoreboard;
+        &mut self.scoreboard
     }
 
     pub fn get_voters(&mut self) -> &mut AttendanceSheet {
-        return &mut self.voters;
+        &mut self.voters
+    }
+
+    pub fn has_votes(&self) -> bool {
+        !self.voters.attendees.is_empty()
+            || self.scoreboard.blank_scores.0 > 0
+            || self.scoreboard.invalid_scores.0 > 0
+            || self.scoreboard.expressed_votes() > 0
+    }
+
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    pub fn open(&mut self, at: u64) -> Opening {
+        if self.closed {
+            return Opening::Closed;
+        }
+        match &mut self.scoreboard.schedule {
+            Some(schedule) if schedule.opens_at > at => {
+                schedule.opens_at = at;
+                Opening::Opened
+            }
+            _ => Opening::AlreadyOpen,
+        }
+    }
+
+    pub fn protect(&mut self, admin_token: &str) {
+        self.admin = Some(CodeHash::of_admin_token(&self.secret, admin_token));
+    }
+
+    pub fn authorizes(&self, admin_token: Option<&str>) -> bool {
+        match (&self.admin, admin_token) {
+            (Some(credential), Some(admin_token)) => *credential == CodeHash::of_admin_token(&self.secret, admin_token),
+            _ => false,
+        }
     }
 
 }
@@ -109,15 +1016,19 @@ impl VotingMachine {
 #[cfg(test)]
 mod tests 
 {
-    use super::{VotingMachine, Candidate, BallotPaper, Voter, VoteOutcome};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::elgamal::{SecretKey, tests::small_group};
+    use super::{VotingMachine, Candidate, BallotPaper, Voter, VoteOutcome, InvalidReason, Withdrawal, WithdrawalPolicy, Ballot, EncryptedBallot, Revelation, RevotePolicy, TrackingCode, Delegation, Proxy, ElectionSecret, VoterToken, Opening, Schedule};
 
     fn setup_voting_machine() -> VotingMachine
     {
-        let mut candidates : Vec<Candidate> = Vec::new();
-        candidates.push(Candidate("E.Macron".to_string()));
-        candidates.push(Candidate("M.Lepen".to_string()));
-        candidates.push(Candidate("JL.Mélanchon".to_string()));
-        return VotingMachine::new(candidates);
+        let candidates : Vec<Candidate> = vec![
+            Candidate("E.Macron".to_string()),
+            Candidate("M.Lepen".to_string()),
+            Candidate("JL.Mélanchon".to_string()),
+        ];
+        VotingMachine::new(candidates)
     }
 
     #[test]
@@ -126,10 +1037,10 @@ mod tests
         let current_voter : Voter = Voter("Jean".to_string());
         let current_candidate : Candidate = Candidate("E.Macron".to_string());
 
-        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(current_candidate.clone()) };
-        let mut voting_machine : VotingMachine = setup_voting_machine();
+        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(current_candidate.clone()), code: None, holder: None };
+        let voting_machine : VotingMachine = setup_voting_machine();
 
-        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper);
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
 
         assert_eq!(vote_outcome, VoteOutcome::AcceptedVote(current_voter, current_candidate));
     }
@@ -139,10 +1050,10 @@ mod tests
     {
         let current_voter : Voter = Voter("Jean".to_string());
 
-        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: None };
-        let mut voting_machine : VotingMachine = setup_voting_machine();
+        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: None, code: None, holder: None };
+        let voting_machine : VotingMachine = setup_voting_machine();
 
-        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper);
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
 
         assert_eq!(vote_outcome, VoteOutcome::BlankVote(current_voter));
     }
@@ -153,12 +1064,12 @@ mod tests
         let current_voter : Voter  = Voter("Jean".to_string());
         let current_candidate : Candidate = Candidate("J.Chirac".to_string());
 
-        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(current_candidate.clone()) };
-        let mut voting_machine : VotingMachine = setup_voting_machine();
+        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(current_candidate.clone()), code: None, holder: None };
+        let voting_machine : VotingMachine = setup_voting_machine();
 
-        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper);
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
 
-        assert_eq!(vote_outcome, VoteOutcome::InvalidVote(current_voter));
+        assert_eq!(vote_outcome, VoteOutcome::InvalidVote(current_voter, InvalidReason::UnknownCandidate));
     }
 
     #[test]
@@ -169,11 +1080,317 @@ mod tests
 
         let mut voting_machine : VotingMachine = setup_voting_machine();
 
-        voting_machine.get_voters().0.insert(current_voter.clone());
+        voting_machine.get_voters().attendees.insert(current_voter.clone());
 
-        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(current_candidate.clone()) };
-        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper);
+        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(current_candidate.clone()), code: None, holder: None };
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
 
         assert_eq!(vote_outcome, VoteOutcome::HasAlreadyVoted(current_voter));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn vote_after_close()
+    {
+        let current_voter : Voter = Voter("Jean".to_string());
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+
+        voting_machine.close();
+
+        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(Candidate("E.Macron".to_string())), code: None, holder: None };
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
+
+        assert_eq!(vote_outcome, VoteOutcome::ElectionClosed(current_voter));
+    }
+
+    #[test]
+    fn rename_changes_display_name_only_for_known_candidates()
+    {
+        let current_voter : Voter = Voter("Jean".to_string());
+        let current_candidate : Candidate = Candidate("E.Macron".to_string());
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+
+        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter, candidate: Some(current_candidate.clone()), code: None, holder: None };
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
+        voting_machine.deposit(&vote_outcome, TrackingCode::normalized("AAAA-AAAA-AAAA"));
+
+        assert!(voting_machine.get_scoreboard().rename(&current_candidate, "Emmanuel Macron"));
+        assert!(!voting_machine.get_scoreboard().rename(&Candidate("J.Chirac".to_string()), "Jacques Chirac"));
+
+        let scoreboard = voting_machine.get_scoreboard();
+        assert_eq!(scoreboard.display_name(&current_candidate), "Emmanuel Macron");
+        assert_eq!(scoreboard.scores[&current_candidate].0, 1);
+        assert_eq!(scoreboard.ballot()[0], &current_candidate);
+    }
+
+    #[test]
+    fn withdrawn_candidate_votes_follow_the_policy()
+    {
+        let current_candidate : Candidate = Candidate("E.Macron".to_string());
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+
+        let ballot_paper : BallotPaper = BallotPaper { voter: Voter("Jean".to_string()), candidate: Some(current_candidate.clone()), code: None, holder: None };
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
+        voting_machine.deposit(&vote_outcome, TrackingCode::normalized("AAAA-AAAA-AAAA"));
+
+        let scoreboard = voting_machine.get_scoreboard();
+        assert_eq!(scoreboard.withdraw(&current_candidate, WithdrawalPolicy::Invalidate), Withdrawal::Withdrawn(current_candidate.clone()));
+        assert_eq!(scoreboard.withdraw(&current_candidate, WithdrawalPolicy::Keep), Withdrawal::AlreadyWithdrawn(current_candidate.clone()));
+        assert_eq!(scoreboard.scores[&current_candidate].0, 0);
+        assert_eq!(scoreboard.invalid_scores.0, 1);
+
+        let ballot_paper : BallotPaper = BallotPaper { voter: Voter("Paul".to_string()), candidate: Some(current_candidate), code: None, holder: None };
+        assert_eq!(voting_machine.vote(ballot_paper, 0), VoteOutcome::InvalidVote(Voter("Paul".to_string()), InvalidReason::WithdrawnCandidate));
+        assert_eq!(voting_machine.get_scoreboard().invalid_reasons[&InvalidReason::WithdrawnCandidate].0, 1);
+    }
+
+    #[test]
+    fn write_in_vote_is_counted_separately()
+    {
+        let current_voter : Voter = Voter("Jean".to_string());
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.get_scoreboard().write_in = true;
+
+        let ballot_paper : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: Some(Candidate(" J.Chirac ".to_string())), code: None, holder: None };
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
+        voting_machine.deposit(&vote_outcome, TrackingCode::normalized("AAAA-AAAA-AAAA"));
+
+        assert_eq!(vote_outcome, VoteOutcome::WriteInVote(current_voter, "J.Chirac".to_string()));
+        assert_eq!(voting_machine.get_scoreboard().write_in_scores["J.Chirac"].0, 1);
+        assert_eq!(voting_machine.get_scoreboard().invalid_scores.0, 0);
+    }
+
+    #[test]
+    fn vote_requires_the_issued_code()
+    {
+        let current_voter : Voter = Voter("Jean".to_string());
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.issue_code(current_voter.clone(), "ABCD-2345");
+
+        let missing : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: None, code: None, holder: None };
+        let wrong : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: None, code: Some("ABCD-2346".to_string()), holder: None };
+        let unknown : BallotPaper = BallotPaper { voter: Voter("Paul".to_string()), candidate: None, code: Some("ABCD-2345".to_string()), holder: None };
+        let right : BallotPaper = BallotPaper { voter: current_voter.clone(), candidate: None, code: Some("abcd 2345".to_string()), holder: None };
+
+        assert_eq!(voting_machine.vote(missing, 0), VoteOutcome::MissingCode(current_voter.clone()));
+        assert_eq!(voting_machine.vote(wrong, 0), VoteOutcome::WrongCode(current_voter.clone()));
+        assert_eq!(voting_machine.vote(unknown, 0), VoteOutcome::WrongCode(Voter("Paul".to_string())));
+        assert_eq!(voting_machine.vote(right, 0), VoteOutcome::BlankVote(current_voter));
+    }
+
+    #[test]
+    fn deposited_ballot_is_found_by_its_tracking_code()
+    {
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        let outcome : VoteOutcome = VoteOutcome::AcceptedVote(Voter("Jean".to_string()), Candidate("M.Lepen".to_string()));
+
+        voting_machine.deposit(&outcome, TrackingCode::normalized("abcd2345wxyz"));
+        voting_machine.deposit(&VoteOutcome::HasAlreadyVoted(Voter("Jean".to_string())), TrackingCode::normalized("ZZZZ-ZZZZ-ZZZZ"));
+
+        assert_eq!(voting_machine.ballot_box.lookup("ABCD-2345-WXYZ"), Some(&Ballot::Candidate(Candidate("M.Lepen".to_string()))));
+        assert_eq!(voting_machine.ballot_box.lookup("abcd 2345 wxyz"), voting_machine.ballot_box.lookup("ABCD-2345-WXYZ"));
+        assert_eq!(voting_machine.ballot_box.0.len(), 1);
+        assert_eq!(voting_machine.scoreboard.scores[&Candidate("M.Lepen".to_string())].0, 1);
+    }
+    #[test]
+    fn invalidating_withdrawal_rewrites_deposited_ballots()
+    {
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        let candidate : Candidate = Candidate("M.Lepen".to_string());
+
+        voting_machine.deposit(&VoteOutcome::AcceptedVote(Voter("Jean".to_string()), candidate.clone()), TrackingCode::normalized("ABCD-2345-WXYZ"));
+        voting_machine.withdraw(&candidate, WithdrawalPolicy::Invalidate);
+
+        assert_eq!(voting_machine.ballot_box.lookup("ABCD-2345-WXYZ"), Some(&Ballot::Invalid(InvalidReason::WithdrawnCandidate)));
+        assert_eq!(voting_machine.scoreboard.invalid_reasons[&InvalidReason::WithdrawnCandidate].0, 1);
+    }
+
+    #[test]
+    fn superseding_policy_replaces_the_previous_ballot()
+    {
+        let voter : Voter = Voter("Jean".to_string());
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.scoreboard.revote = RevotePolicy::Supersede;
+
+        voting_machine.deposit(&VoteOutcome::AcceptedVote(voter.clone(), Candidate("M.Lepen".to_string())), TrackingCode::normalized("AAAA-AAAA-AAAA"));
+        let ballot_paper : BallotPaper = BallotPaper { voter: voter.clone(), candidate: None, code: None, holder: None };
+        let vote_outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
+        voting_machine.deposit(&vote_outcome, TrackingCode::normalized("BBBB-BBBB-BBBB"));
+
+        assert_eq!(vote_outcome, VoteOutcome::BlankVote(voter.clone()));
+        assert_eq!(voting_machine.ballot_box.lookup("AAAA-AAAA-AAAA"), None);
+        assert_eq!(voting_machine.superseded_ballot(&voter), Some(&TrackingCode::normalized("BBBB-BBBB-BBBB")));
+        assert_eq!(voting_machine.scoreboard.scores[&Candidate("M.Lepen".to_string())].0, 0);
+        assert_eq!((voting_machine.scoreboard.blank_scores.0, voting_machine.scoreboard.total_votes()), (1, 1));
+    }
+
+    #[test]
+    fn rejecting_policy_keeps_the_first_ballot()
+    {
+        let voter : Voter = Voter("Jean".to_string());
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+
+        voting_machine.deposit(&VoteOutcome::AcceptedVote(voter.clone(), Candidate("M.Lepen".to_string())), TrackingCode::normalized("AAAA-AAAA-AAAA"));
+        let ballot_paper : BallotPaper = BallotPaper { voter: voter.clone(), candidate: None, code: None, holder: None };
+
+        assert_eq!(voting_machine.vote(ballot_paper, 0), VoteOutcome::HasAlreadyVoted(voter.clone()));
+        assert_eq!(voting_machine.superseded_ballot(&voter), None);
+        assert!(voting_machine.ballot_tokens.is_empty());
+    }
+
+    #[test]
+    fn proxies_are_limited_per_holder_and_valid_only_within_their_period()
+    {
+        let (jean, paul, marie) : (Voter, Voter, Voter) = (Voter("Jean".to_string()), Voter("Paul".to_string()), Voter("Marie".to_string()));
+        let held_by_marie = |valid_from: u64, valid_until: u64| Proxy { holder: Voter("Marie".to_string()), valid_from, valid_until };
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+
+        assert_eq!(voting_machine.register_proxy(marie.clone(), held_by_marie(100, 200), 150), Delegation::SelfDelegation(marie.clone()));
+        assert_eq!(voting_machine.register_proxy(jean.clone(), held_by_marie(100, 120), 150), Delegation::InvalidPeriod(jean.clone()));
+        assert_eq!(voting_machine.register_proxy(jean.clone(), held_by_marie(100, 200), 150), Delegation::Registered(jean.clone(), marie.clone()));
+        assert_eq!(voting_machine.register_proxy(jean.clone(), held_by_marie(100, 300), 150), Delegation::AlreadyDelegated(jean.clone()));
+        assert_eq!(voting_machine.register_proxy(paul.clone(), held_by_marie(100, 200), 150), Delegation::TooManyProxies(marie.clone(), 1));
+        assert_eq!(voting_machine.register_proxy(paul.clone(), held_by_marie(300, 400), 250), Delegation::Registered(paul.clone(), marie.clone()));
+
+        assert!(voting_machine.proxies.authorizes(&jean, &marie, 200));
+        assert!(!voting_machine.proxies.authorizes(&jean, &marie, 201));
+        assert!(!voting_machine.proxies.authorizes(&jean, &paul, 150));
+        assert!(!voting_machine.proxies.authorizes(&paul, &marie, 250));
+    }
+
+    #[test]
+    fn proxy_vote_is_signed_by_the_holder()
+    {
+        let (jean, marie) : (Voter, Voter) = (Voter("Jean".to_string()), Voter("Marie".to_string()));
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.issue_code(marie.clone(), "ABCD-2345");
+        voting_machine.issue_code(jean.clone(), "WXYZ-6789");
+        voting_machine.register_proxy(jean.clone(), Proxy { holder: marie.clone(), valid_from: 0, valid_until: 100 }, 0);
+
+        let principal_code : BallotPaper = BallotPaper { voter: jean.clone(), candidate: None, code: Some("WXYZ-6789".to_string()), holder: Some(marie.clone()) };
+        let usurped : BallotPaper = BallotPaper { voter: marie.clone(), candidate: None, code: Some("ABCD-2345".to_string()), holder: Some(jean.clone()) };
+        let delegated : BallotPaper = BallotPaper { voter: jean.clone(), candidate: None, code: Some("ABCD-2345".to_string()), holder: Some(marie.clone()) };
+
+        assert_eq!(voting_machine.vote(principal_code, 0), VoteOutcome::WrongCode(marie.clone()));
+        assert_eq!(voting_machine.vote(usurped, 0), VoteOutcome::InvalidProxy(marie.clone(), jean.clone()));
+        assert_eq!(voting_machine.vote(delegated.clone(), 101), VoteOutcome::InvalidProxy(jean.clone(), marie.clone()));
+        let vote_outcome : VoteOutcome = voting_machine.vote(delegated, 100);
+        voting_machine.deposit(&vote_outcome, TrackingCode::normalized("AAAA-AAAA-AAAA"));
+        voting_machine.countersign(vote_outcome.voter(), Some(marie.clone()));
+
+        assert_eq!(vote_outcome, VoteOutcome::BlankVote(jean.clone()));
+        assert!(voting_machine.voters.attendees.contains(&jean) && !voting_machine.voters.attendees.contains(&marie));
+        assert_eq!(voting_machine.voters.holder_for(&jean), Some(&marie));
+    }
+
+    #[test]
+    fn write_in_is_refused_when_the_election_is_encrypted()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(47);
+        let key : SecretKey = SecretKey::generate(small_group(), &mut rng);
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.scoreboard.write_in = true;
+        voting_machine.scoreboard.election_key = Some(key.public_key());
+        let voter : Voter = Voter("Jean".to_string());
+
+        let ballot_paper : BallotPaper = BallotPaper { voter: voter.clone(), candidate: Some(Candidate("J.Chirac".to_string())), code: None, holder: None };
+        let outcome : VoteOutcome = voting_machine.vote(ballot_paper, 0);
+        let sealed : EncryptedBallot = voting_machine.scoreboard.seal(&outcome, &mut rng).expect("election is encrypted");
+        voting_machine.deposit_sealed(&voter, sealed, TrackingCode::normalized("AAAA-AAAA-AAAA"));
+        voting_machine.close();
+
+        assert_eq!(outcome, VoteOutcome::InvalidVote(voter, InvalidReason::UnknownCandidate));
+        assert_eq!(voting_machine.reveal(&key, &mut rng), Revelation::Revealed(1));
+        assert_eq!(voting_machine.scoreboard.invalid_reasons[&InvalidReason::UnknownCandidate].0, 1);
+        assert!(voting_machine.scoreboard.write_in_scores.is_empty());
+    }
+
+    #[test]
+    fn invalidating_withdrawal_moves_sealed_votes_homomorphically()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(48);
+        let key : SecretKey = SecretKey::generate(small_group(), &mut rng);
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.scoreboard.election_key = Some(key.public_key());
+        let candidate : Candidate = Candidate("M.Lepen".to_string());
+
+        for (index, voter) in ["Jean", "Paul", "Marie"].iter().enumerate()
+        {
+            let outcome : VoteOutcome = VoteOutcome::AcceptedVote(Voter(voter.to_string()), candidate.clone());
+            let sealed : EncryptedBallot = voting_machine.scoreboard.seal(&outcome, &mut rng).expect("election is encrypted");
+            voting_machine.deposit_sealed(outcome.voter(), sealed, TrackingCode(format!("CODE-000{}", index)));
+        }
+        voting_machine.withdraw(&candidate, WithdrawalPolicy::Invalidate);
+        voting_machine.close();
+
+        assert_eq!(voting_machine.reveal(&key, &mut rng), Revelation::Revealed(3));
+        assert_eq!(voting_machine.scoreboard.scores[&candidate].0, 0);
+        assert_eq!(voting_machine.scoreboard.invalid_reasons[&InvalidReason::WithdrawnCandidate].0, 3);
+        assert_eq!(voting_machine.scoreboard.total_votes(), 3);
+    }
+
+    #[test]
+    fn admin_token_only_unlocks_its_own_election()
+    {
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        assert!(!voting_machine.authorizes(Some("s3cret")));
+
+        voting_machine.protect("s3cret");
+        let mut other_machine : VotingMachine = setup_voting_machine();
+        other_machine.admin = voting_machine.admin.clone();
+
+        assert!(voting_machine.authorizes(Some("s3cret")));
+        assert!(!voting_machine.authorizes(Some("guess")));
+        assert!(!voting_machine.authorizes(None));
+        assert!(!other_machine.authorizes(Some("s3cret")));
+    }
+
+    #[test]
+    fn voter_tokens_are_keyed_by_the_election_secret()
+    {
+        let voter : Voter = Voter("Jean".to_string());
+        let first : ElectionSecret = ElectionSecret([1; 32]);
+        let second : ElectionSecret = ElectionSecret([2; 32]);
+
+        assert_eq!(VoterToken::of(&first, &voter), VoterToken::of(&first, &voter));
+        assert_ne!(VoterToken::of(&first, &voter), VoterToken::of(&second, &voter));
+        assert_ne!(VoterToken::of(&first, &voter), VoterToken::of(&first, &Voter("Paul".to_string())));
+    }
+
+    #[test]
+    fn closed_election_cannot_be_reopened()
+    {
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        assert_eq!(voting_machine.open(0), Opening::AlreadyOpen);
+
+        voting_machine.close();
+
+        assert_eq!(voting_machine.open(0), Opening::Closed);
+        assert!(voting_machine.closed);
+    }
+
+    #[test]
+    fn vote_is_refused_outside_the_schedule()
+    {
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.scoreboard.schedule = Some(Schedule { opens_at: 100, closes_at: 200 });
+        let ballot_paper : BallotPaper = BallotPaper { voter: Voter("Jean".to_string()), candidate: None, code: None, holder: None };
+
+        assert_eq!(voting_machine.vote(ballot_paper.clone(), 99), VoteOutcome::NotYetOpen(Voter("Jean".to_string())));
+        assert_eq!(voting_machine.vote(ballot_paper.clone(), 150), VoteOutcome::BlankVote(Voter("Jean".to_string())));
+        assert_eq!(voting_machine.vote(ballot_paper, 200), VoteOutcome::ElectionClosed(Voter("Jean".to_string())));
+    }
+
+    #[test]
+    fn opening_starts_a_scheduled_election_early()
+    {
+        let mut voting_machine : VotingMachine = setup_voting_machine();
+        voting_machine.scoreboard.schedule = Some(Schedule { opens_at: 100, closes_at: 200 });
+        let voter : Voter = Voter("Jean".to_string());
+        assert_eq!(voting_machine.scoreboard.schedule.as_ref().and_then(|schedule| schedule.refusal(&voter, 50)), Some(VoteOutcome::NotYetOpen(voter.clone())));
+
+        assert_eq!(voting_machine.open(50), Opening::Opened);
+
+        assert_eq!(voting_machine.scoreboard.schedule.as_ref().and_then(|schedule| schedule.refusal(&voter, 50)), None);
+        assert_eq!(voting_machine.open(60), Opening::AlreadyOpen);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::configuration::StorageType;
+use crate::domain::{CandidateProfile, RevotePolicy};
+
+#[derive(Debug, Error)]
+pub enum ConfigurationError {
+    #[error("cannot read configuration file {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("unsupported configuration file {0}, expected a .toml or .json file")]
+    UnsupportedFormat(String),
+    #[error("syntax error in configuration file: {0}")]
+    Syntax(String),
+    #[error("invalid value for `{key}`: {message}")]
+    Invalid { key: String, message: String },
+}
+
+impl ConfigurationError {
+    pub(crate) fn invalid(key: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigurationError::Invalid { key: key.into(), message: message.into() }
+    }
+
+    fn from_path<E: std::fmt::Display>(error: serde_path_to_error::Error<E>) -> Self {
+        let key : String = error.path().to_string();
+        if key == "." {
+            return ConfigurationError::Syntax(error.into_inner().to_string());
+        }
+        ConfigurationError::invalid(key, error.into_inner().to_string())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CandidateEntry {
+    Name(String),
+    Detailed {
+        #[serde(default)]
+        id: Option<String>,
+        name: String,
+        #[serde(default)]
+        party: Option<String>,
+        #[serde(default)]
+        ballot_order: Option<usize>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+}
+
+impl CandidateEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            CandidateEntry::Name(name) => name,
+            CandidateEntry::Detailed { name, .. } => name,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        match self {
+            CandidateEntry::Detailed { id: Some(id), .. } => id,
+            _ => self.name(),
+        }
+    }
+
+    pub fn profile(&self, index: usize) -> CandidateProfile {
+        match self {
+            CandidateEntry::Name(name) => CandidateProfile::named(name, index),
+            CandidateEntry::Detailed { name, party, ballot_order, description, .. } => CandidateProfile {
+                name: name.clone(),
+                party: party.clone(),
+                ballot_order: ballot_order.unwrap_or(index),
+                description: description.clone(),
+                withdrawn: false,
+            },
+        }
+    }
+
+    fn key(&self, index: usize) -> String {
+        match self {
+            CandidateEntry::Name(_) => format!("candidates[{}]", index),
+            CandidateEntry::Detailed { .. } => format!("candidates[{}].name", index),
+        }
+    }
+
+    fn id_key(&self, index: usize) -> String {
+        match self {
+            CandidateEntry::Detailed { id: Some(_), .. } => format!("candidates[{}].id", index),
+            _ => self.key(index),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingMethod {
+    #[default]
+    Plurality,
+}
+
+fn revote_policy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<RevotePolicy>, D::Error> {
+    let policy : String = String::deserialize(deserializer)?;
+    policy.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageSection {
+    #[serde(rename = "type")]
+    pub storage_type: Option<StorageType>,
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleSection {
+    pub opens_at: u64,
+    pub closes_at: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdminSection {
+    pub token: Option<String>,
+    pub audit_log: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElectionFile {
+    pub title: Option<String>,
+    #[serde(default)]
+    pub candidates: Vec<CandidateEntry>,
+    #[serde(default)]
+    pub voting_method: VotingMethod,
+    pub write_in: Option<bool>,
+    #[serde(default, deserialize_with = "revote_policy")]
+    pub revote: Option<RevotePolicy>,
+    pub max_proxies: Option<usize>,
+    #[serde(default)]
+    pub storage: StorageSection,
+    pub schedule: Option<ScheduleSection>,
+    #[serde(default)]
+    pub admin: AdminSection,
+}
+
+impl ElectionFile {
+    pub fn load(path: &str) -> Result<Self, ConfigurationError> {
+        let content : String = fs::read_to_string(path)
+            .map_err(|source| ConfigurationError::Io { path: path.to_string(), source })?;
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => ElectionFile::from_toml(&content),
+            Some("json") => ElectionFile::from_json(&content),
+            _ => Err(ConfigurationError::UnsupportedFormat(path.to_string())),
+        }
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, ConfigurationError> {
+        let table : toml::Table = content.parse()
+            .map_err(|error: toml::de::Error| ConfigurationError::Syntax(error.to_string()))?;
+        let election : ElectionFile = serde_path_to_error::deserialize(toml::Value::Table(table))
+            .map_err(ConfigurationError::from_path)?;
+        election.validate()
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, ConfigurationError> {
+        let mut deserializer = serde_json::Deserializer::from_str(content);
+        let election : ElectionFile = serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(ConfigurationError::from_path)?;
+        election.validate()
+    }
+
+    fn validate(self) -> Result<Self, ConfigurationError> {
+        if let Some(title) = &self.title {
+            if title.trim().is_empty() {
+                return Err(ConfigurationError::invalid("title", "the title cannot be empty"));
+            }
+        }
+
+        let mut names : HashSet<&str> = HashSet::new();
+        for (index, candidate) in self.candidates.iter().enumerate() {
+            if candidate.name().trim().is_empty() {
+                return Err(ConfigurationError::invalid(candidate.key(index), "the candidate name cannot be empty"));
+            }
+            if candidate.id().trim().is_empty() {
+                return Err(ConfigurationError::invalid(candidate.id_key(index), "the candidate id cannot be empty"));
+            }
+            if !names.insert(candidate.id()) {
+                return Err(ConfigurationError::invalid(candidate.id_key(index), format!("duplicate candidate {}", candidate.id())));
+            }
+        }
+
+        if let Some(file_path) = &self.storage.file_path {
+            if file_path.trim().is_empty() {
+                return Err(ConfigurationError::invalid("storage.file_path", "the file path cannot be empty"));
+            }
+        }
+
+        if let Some(token) = &self.admin.token {
+            if token.trim().is_empty() {
+                return Err(ConfigurationError::invalid("admin.token", "the admin token cannot be empty"));
+            }
+        }
+
+        if self.max_proxies == Some(0) {
+            return Err(ConfigurationError::invalid("max_proxies", "a proxy holder must be allowed at least one proxy"));
+        }
+
+        if let Some(schedule) = &self.schedule {
+            if schedule.closes_at <= schedule.opens_at {
+                return Err(ConfigurationError::invalid("schedule.closes_at", "the election must close after it opens"));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::configuration::StorageType;
+    use crate::domain::RevotePolicy;
+    use super::{ConfigurationError, ElectionFile, VotingMethod};
+
+    fn invalid_key(error: ConfigurationError) -> String
+    {
+        match error {
+            ConfigurationError::Invalid { key, .. } => key,
+            other => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn toml_election_is_loaded() -> anyhow::Result<()>
+    {
+        let election : ElectionFile = ElectionFile::from_toml(r#"
+            title = "Présidentielle"
+            candidates = ["E.Macron", { id = "mlp", name = "M.Lepen", party = "RN", ballot_order = 0 }]
+            revote = "supersede"
+            max_proxies = 2
+
+            [storage]
+            type = "file"
+            file_path = "election.json"
+
+            [schedule]
+            opens_at = 1000
+            closes_at = 2000
+        "#)?;
+
+        assert_eq!(election.title.as_deref(), Some("Présidentielle"));
+        assert_eq!(election.candidates.iter().map(|candidate| candidate.id()).collect::<Vec<&str>>(), vec!["E.Macron", "mlp"]);
+        assert_eq!(election.candidates[1].profile(1).party.as_deref(), Some("RN"));
+        assert_eq!(election.candidates[1].profile(1).ballot_order, 0);
+        assert_eq!(election.voting_method, VotingMethod::Plurality);
+        assert!(matches!(election.storage.storage_type, Some(StorageType::File)));
+        assert_eq!(election.revote, Some(RevotePolicy::Supersede));
+        assert_eq!(election.max_proxies, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn json_error_points_to_the_offending_key()
+    {
+        let error = ElectionFile::from_json(r#"{"candidates": ["E.Macron"], "storage": {"type": "cloud"}}"#).unwrap_err();
+
+        assert_eq!(invalid_key(error), "storage.type");
+    }
+
+    #[test]
+    fn semantic_errors_point_to_the_offending_key()
+    {
+        let duplicate = ElectionFile::from_toml(r#"candidates = ["E.Macron", { name = "E.Macron" }]"#).unwrap_err();
+        let duplicate_id = ElectionFile::from_toml(r#"candidates = [{ id = "em", name = "E.Macron" }, { id = "em", name = "M.Lepen" }]"#).unwrap_err();
+        let schedule = ElectionFile::from_toml("[schedule]\nopens_at = 2000\ncloses_at = 1000").unwrap_err();
+        let method = ElectionFile::from_toml(r#"voting_method = "borda""#).unwrap_err();
+        let token = ElectionFile::from_toml("[admin]\ntoken = \" \"").unwrap_err();
+        let revote = ElectionFile::from_toml(r#"revote = "twice""#).unwrap_err();
+
+        assert_eq!(invalid_key(duplicate), "candidates[1].name");
+        assert_eq!(invalid_key(duplicate_id), "candidates[1].id");
+        assert_eq!(invalid_key(schedule), "schedule.closes_at");
+        assert_eq!(invalid_key(method), "voting_method");
+        assert_eq!(invalid_key(token), "admin.token");
+        assert_eq!(invalid_key(revote), "revote");
+    }
+}
@@ -0,0 +1,416 @@
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const MODP_2048: &str = concat!(
+    "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD1",
+    "29024E088A67CC74020BBEA63B139B22514A08798E3404DD",
+    "EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245",
+    "E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED",
+    "EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3D",
+    "C2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F",
+    "83655D23DCA3AD961C62F356208552BB9ED529077096966D",
+    "670C354E4ABC9804F1746C08CA18217C32905E462E36CE3B",
+    "E39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9",
+    "DE2BCBF6955817183995497CEA956AE515D2261898FA0510",
+    "15728E5A8AACAA68FFFFFFFFFFFFFFFF",
+);
+
+mod integer {
+    use num_bigint::BigUint;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub enum Encoded {
+        Hex(String),
+        Number(u64),
+    }
+
+    impl Encoded {
+        pub fn decode<E: Error>(self) -> Result<BigUint, E> {
+            match self {
+                Encoded::Hex(hex) => BigUint::parse_bytes(hex.as_bytes(), 16).ok_or_else(|| E::custom(format!("invalid hexadecimal integer {hex}"))),
+                Encoded::Number(number) => Ok(BigUint::from(number)),
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(value: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_str_radix(16))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+        Encoded::deserialize(deserializer)?.decode()
+    }
+}
+
+mod integers {
+    use num_bigint::BigUint;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::integer::Encoded;
+
+    pub fn serialize<S: Serializer>(values: &[BigUint], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|value| value.to_str_radix(16)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<BigUint>, D::Error> {
+        Vec::<Encoded>::deserialize(deserializer)?.into_iter().map(Encoded::decode).collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    #[serde(with = "integer")]
+    pub p: BigUint,
+    #[serde(with = "integer")]
+    pub q: BigUint,
+    #[serde(with = "integer")]
+    pub g: BigUint,
+}
+
+impl Group {
+    pub fn election() -> Group {
+        let p : BigUint = BigUint::parse_bytes(MODP_2048.as_bytes(), 16).expect("RFC 3526 group 14 prime");
+        let q : BigUint = (&p - 1u32) >> 1;
+        Group { p, q, g: BigUint::from(4u32) }
+    }
+
+    pub fn mul(&self, left: &BigUint, right: &BigUint) -> BigUint {
+        (left * right) % &self.p
+    }
+
+    pub fn pow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        base.modpow(exponent, &self.p)
+    }
+
+    pub fn inverse(&self, value: &BigUint) -> BigUint {
+        self.pow(value, &(&self.p - 2u32))
+    }
+
+    pub fn random_exponent(&self, rng: &mut impl Rng) -> BigUint {
+        rng.gen_biguint_range(&BigUint::one(), &self.q)
+    }
+
+    fn exponent(&self, value: u64) -> BigUint {
+        BigUint::from(value) % &self.q
+    }
+
+    fn add_exponents(&self, left: &BigUint, right: &BigUint) -> BigUint {
+        (left + right) % &self.q
+    }
+
+    fn sub_exponents(&self, left: &BigUint, right: &BigUint) -> BigUint {
+        self.add_exponents(left, &(&self.q - right % &self.q))
+    }
+
+    fn mul_exponents(&self, left: &BigUint, right: &BigUint) -> BigUint {
+        (left * right) % &self.q
+    }
+
+    fn inverse_exponent(&self, value: &BigUint) -> BigUint {
+        value.modpow(&(&self.q - 2u32), &self.q)
+    }
+
+    fn challenge(&self, values: &[&BigUint]) -> BigUint {
+        let digest = values.iter()
+            .map(|value| value.to_bytes_be())
+            .fold(Sha256::new(), |hasher, bytes| hasher.chain_update((bytes.len() as u64).to_be_bytes()).chain_update(bytes))
+            .finalize();
+        BigUint::from_bytes_be(&digest) % &self.q
+    }
+
+    fn lagrange_at_zero(&self, trustee: u64, trustees: &[u64]) -> BigUint {
+        trustees.iter()
+            .filter(|other| **other != trustee)
+            .fold(BigUint::one(), |coefficient, other| {
+                let other : BigUint = self.exponent(*other);
+                let ratio : BigUint = self.mul_exponents(&other, &self.inverse_exponent(&self.sub_exponents(&other, &self.exponent(trustee))));
+                self.mul_exponents(&coefficient, &ratio)
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ciphertext {
+    #[serde(with = "integer")]
+    pub a: BigUint,
+    #[serde(with = "integer")]
+    pub b: BigUint,
+}
+
+impl Ciphertext {
+    pub fn zero() -> Ciphertext {
+        Ciphertext { a: BigUint::one(), b: BigUint::one() }
+    }
+
+    pub fn add(&self, other: &Ciphertext, group: &Group) -> Ciphertext {
+        Ciphertext { a: group.mul(&self.a, &other.a), b: group.mul(&self.b, &other.b) }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicKey {
+    pub group: Group,
+    #[serde(with = "integer")]
+    pub h: BigUint,
+}
+
+impl PublicKey {
+    pub fn encrypt_with(&self, message: u64, randomness: &BigUint) -> Ciphertext {
+        let group : &Group = &self.group;
+        Ciphertext {
+            a: group.pow(&group.g, randomness),
+            b: group.mul(&group.pow(&group.g, &BigUint::from(message)), &group.pow(&self.h, randomness)),
+        }
+    }
+
+    pub fn encrypt(&self, message: u64, rng: &mut impl Rng) -> Ciphertext {
+        self.encrypt_with(message, &self.group.random_exponent(rng))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretKey {
+    pub group: Group,
+    #[serde(with = "integer")]
+    pub x: BigUint,
+}
+
+impl SecretKey {
+    pub fn generate(group: Group, rng: &mut impl Rng) -> Self {
+        let x : BigUint = group.random_exponent(rng);
+        SecretKey { group, x }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey { group: self.group.clone(), h: self.group.pow(&self.group.g, &self.x) }
+    }
+
+    pub fn decrypt(&self, ciphertext: &Ciphertext, max: u64) -> Option<u64> {
+        let group : &Group = &self.group;
+        let encoded : BigUint = group.mul(&ciphertext.b, &group.inverse(&group.pow(&ciphertext.a, &self.x)));
+        discrete_log(group, &encoded, max)
+    }
+
+    pub fn as_share(&self) -> KeyShare {
+        KeyShare { group: self.group.clone(), trustee: 0, share: self.x.clone() }
+    }
+
+    pub fn split(&self, threshold: usize, trustees: usize, rng: &mut impl Rng) -> (ThresholdKey, Vec<KeyShare>) {
+        let group : &Group = &self.group;
+        let mut coefficients : Vec<BigUint> = vec![self.x.clone()];
+        coefficients.extend((1..threshold).map(|_| group.random_exponent(rng)));
+
+        let shares : Vec<KeyShare> = (1..=trustees as u64)
+            .map(|trustee| {
+                let point : BigUint = group.exponent(trustee);
+                let share : BigUint = coefficients.iter().rev().fold(BigUint::zero(), |value, coefficient| group.add_exponents(&group.mul_exponents(&value, &point), coefficient));
+                KeyShare { group: group.clone(), trustee, share }
+            })
+            .collect();
+        let commitments : Vec<BigUint> = coefficients.iter().map(|coefficient| group.pow(&group.g, coefficient)).collect();
+        (ThresholdKey { threshold, trustees, commitments }, shares)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdKey {
+    pub threshold: usize,
+    pub trustees: usize,
+    #[serde(with = "integers")]
+    pub commitments: Vec<BigUint>,
+}
+
+impl ThresholdKey {
+    pub fn verification_key(&self, group: &Group, trustee: u64) -> BigUint {
+        let point : BigUint = group.exponent(trustee);
+        let mut power : BigUint = BigUint::one();
+        self.commitments.iter().fold(BigUint::one(), |key, commitment| {
+            let term : BigUint = group.pow(commitment, &power);
+            power = group.mul_exponents(&power, &point);
+            group.mul(&key, &term)
+        })
+    }
+
+    pub fn has_trustee(&self, trustee: u64) -> bool {
+        (1..=self.trustees as u64).contains(&trustee)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyShare {
+    pub group: Group,
+    pub trustee: u64,
+    #[serde(with = "integer")]
+    pub share: BigUint,
+}
+
+impl KeyShare {
+    pub fn decryption_factor(&self, ciphertext: &Ciphertext, rng: &mut impl Rng) -> DecryptionFactor {
+        let group : &Group = &self.group;
+        let verification_key : BigUint = group.pow(&group.g, &self.share);
+        let factor : BigUint = group.pow(&ciphertext.a, &self.share);
+        let nonce : BigUint = group.random_exponent(rng);
+        let challenge : BigUint = group.challenge(&[&verification_key, &ciphertext.a, &factor, &group.pow(&group.g, &nonce), &group.pow(&ciphertext.a, &nonce)]);
+        let response : BigUint = group.add_exponents(&nonce, &group.mul_exponents(&challenge, &self.share));
+        DecryptionFactor { a: ciphertext.a.clone(), factor, challenge, response }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecryptionFactor {
+    #[serde(with = "integer")]
+    pub a: BigUint,
+    #[serde(with = "integer")]
+    pub factor: BigUint,
+    #[serde(with = "integer")]
+    pub challenge: BigUint,
+    #[serde(with = "integer")]
+    pub response: BigUint,
+}
+
+impl DecryptionFactor {
+    pub fn verify(&self, group: &Group, verification_key: &BigUint) -> bool {
+        let generator_commitment : BigUint = group.mul(&group.pow(&group.g, &self.response), &group.inverse(&group.pow(verification_key, &self.challenge)));
+        let ciphertext_commitment : BigUint = group.mul(&group.pow(&self.a, &self.response), &group.inverse(&group.pow(&self.factor, &self.challenge)));
+        group.challenge(&[verification_key, &self.a, &self.factor, &generator_commitment, &ciphertext_commitment]) == self.challenge
+    }
+}
+
+pub fn combine(group: &Group, ciphertext: &Ciphertext, factors: &[(u64, BigUint)], max: u64) -> Option<u64> {
+    let trustees : Vec<u64> = factors.iter().map(|(trustee, _)| *trustee).collect();
+    let masking : BigUint = factors.iter().fold(BigUint::one(), |masking, (trustee, factor)| group.mul(&masking, &group.pow(factor, &group.lagrange_at_zero(*trustee, &trustees))));
+    discrete_log(group, &group.mul(&ciphertext.b, &group.inverse(&masking)), max)
+}
+
+pub fn discrete_log(group: &Group, encoded: &BigUint, max: u64) -> Option<u64> {
+    let mut candidate : BigUint = BigUint::one();
+    for message in 0..=max {
+        if &candidate == encoded {
+            return Some(message);
+        }
+        candidate = group.mul(&candidate, &group.g);
+    }
+    None
+}
+
+#[cfg(test)]
+pub mod tests
+{
+    use num_bigint::BigUint;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::{Ciphertext, DecryptionFactor, Group, KeyShare, PublicKey, SecretKey, ThresholdKey, combine};
+
+    pub fn small_group() -> Group
+    {
+        Group { p: BigUint::from(23u32), q: BigUint::from(11u32), g: BigUint::from(4u32) }
+    }
+
+    #[test]
+    fn election_group_is_the_2048_bit_modp_group()
+    {
+        let group : Group = Group::election();
+
+        assert_eq!(group.p.bits(), 2048);
+        assert_eq!(&group.q * 2u32 + 1u32, group.p);
+        assert_eq!(group.pow(&group.g, &group.q), BigUint::from(1u32));
+    }
+
+    #[test]
+    fn small_key_round_trip()
+    {
+        let key : SecretKey = SecretKey { group: small_group(), x: BigUint::from(3u32) };
+        let public : PublicKey = key.public_key();
+
+        assert_eq!(public.h, BigUint::from(18u32));
+        assert_eq!(public.encrypt_with(2, &BigUint::from(5u32)), Ciphertext { a: BigUint::from(12u32), b: BigUint::from(2u32) });
+        for message in 0..5
+        {
+            assert_eq!(key.decrypt(&public.encrypt_with(message, &BigUint::from(7u32)), 10), Some(message));
+        }
+    }
+
+    #[test]
+    fn keys_serialize_as_hexadecimal_and_accept_legacy_numbers()
+    {
+        let key : SecretKey = SecretKey { group: small_group(), x: BigUint::from(3u32) };
+
+        assert_eq!(serde_json::to_string(&key).unwrap(), r#"{"group":{"p":"17","q":"b","g":"4"},"x":"3"}"#);
+        assert_eq!(serde_json::from_str::<SecretKey>(r#"{"group":{"p":23,"q":11,"g":4},"x":3}"#).unwrap(), key);
+    }
+
+    #[test]
+    fn sum_of_ciphertexts_decrypts_to_the_sum_of_messages()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(46);
+        let group : Group = small_group();
+        let key : SecretKey = SecretKey::generate(group.clone(), &mut rng);
+        let public : PublicKey = key.public_key();
+
+        let total : Ciphertext = [1, 0, 1, 1, 0]
+            .iter()
+            .map(|vote| public.encrypt(*vote, &mut rng))
+            .fold(Ciphertext::zero(), |total, ciphertext| total.add(&ciphertext, &group));
+
+        assert_eq!(key.decrypt(&total, 5), Some(3));
+    }
+
+    #[test]
+    fn election_group_keys_hide_identical_votes()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(47);
+        let group : Group = Group::election();
+        let key : SecretKey = SecretKey::generate(group.clone(), &mut rng);
+        let public : PublicKey = key.public_key();
+
+        let first : Ciphertext = public.encrypt(1, &mut rng);
+        let second : Ciphertext = public.encrypt(1, &mut rng);
+
+        assert_ne!(first, second);
+        assert_eq!(key.decrypt(&first.add(&second, &group), 10), Some(2));
+        assert_eq!(SecretKey::generate(group, &mut rng).decrypt(&first, 10), None);
+    }
+
+    #[test]
+    fn any_threshold_of_trustees_decrypts_the_total()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(48);
+        let group : Group = small_group();
+        let key : SecretKey = SecretKey { group: group.clone(), x: BigUint::from(7u32) };
+        let public : PublicKey = key.public_key();
+        let (threshold_key, shares) : (ThresholdKey, Vec<KeyShare>) = key.split(2, 3, &mut rng);
+        let total : Ciphertext = public.encrypt(1, &mut rng).add(&public.encrypt(1, &mut rng), &group);
+
+        assert_eq!(threshold_key.commitments[0], public.h);
+        for pair in [[0, 1], [0, 2], [1, 2]]
+        {
+            let factors : Vec<(u64, BigUint)> = pair.iter()
+                .map(|index| &shares[*index])
+                .map(|share| (share.trustee, share.decryption_factor(&total, &mut rng).factor))
+                .collect();
+            assert_eq!(combine(&group, &total, &factors, 5), Some(2));
+        }
+    }
+
+    #[test]
+    fn decryption_factors_prove_the_trustee_share()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(49);
+        let group : Group = Group::election();
+        let key : SecretKey = SecretKey::generate(group.clone(), &mut rng);
+        let (threshold_key, shares) : (ThresholdKey, Vec<KeyShare>) = key.split(3, 5, &mut rng);
+        let ciphertext : Ciphertext = key.public_key().encrypt(1, &mut rng);
+
+        let factor : DecryptionFactor = shares[3].decryption_factor(&ciphertext, &mut rng);
+        let forged : DecryptionFactor = KeyShare { share: &shares[3].share + 1u32, ..shares[3].clone() }.decryption_factor(&ciphertext, &mut rng);
+
+        assert!(factor.verify(&group, &threshold_key.verification_key(&group, 4)));
+        assert!(!factor.verify(&group, &threshold_key.verification_key(&group, 3)));
+        assert!(!forged.verify(&group, &threshold_key.verification_key(&group, 4)));
+    }
+}
@@ -1,5 +1,16 @@
 pub mod configuration;
+pub mod election_file;
 pub mod app_builder;
-mod domain;
-mod storage;
-mod use_cases;
\ No newline at end of file
+pub mod domain;
+pub mod storage;
+pub mod use_cases;
+pub mod server;
+pub mod results;
+pub mod messages;
+pub mod shell;
+pub mod tui;
+pub mod admin;
+pub mod audit;
+pub mod bulletin;
+pub mod elgamal;
+pub mod trustees;
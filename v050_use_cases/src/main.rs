@@ -1,11 +1,22 @@
-use clap::Parser;
+use std::process::ExitCode;
+
 use v050_use_cases::configuration::Configuration;
-use v050_use_cases::app_builder::run_app;
-use anyhow::Result;
+use v050_use_cases::app_builder::{run_app, EXIT_STORAGE_ERROR, EXIT_USAGE_ERROR};
 
 #[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
-    let configuration = Configuration::parse();
-    run_app(configuration).await?;
-    Ok(())
-}
\ No newline at end of file
+async fn main() -> ExitCode {
+    let configuration = match Configuration::load() {
+        Ok(configuration) => configuration,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_USAGE_ERROR);
+        }
+    };
+    match run_app(configuration).await {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::from(EXIT_STORAGE_ERROR)
+        }
+    }
+}
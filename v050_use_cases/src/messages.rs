@@ -0,0 +1,563 @@
+use std::{env, io};
+
+use clap::ValueEnum;
+
+use crate::{bulletin::VerificationFailure, domain::{Delegation, InvalidReason, Revelation, Submission, Voter, VoteOutcome, Withdrawal}, storage::StorageError};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Language {
+    #[default]
+    Fr,
+    En,
+}
+
+impl Language {
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let code : String = locale.chars().take_while(|character| character.is_ascii_alphabetic()).collect::<String>().to_lowercase();
+        match code.as_str() {
+            "fr" => Some(Language::Fr),
+            "en" => Some(Language::En),
+            _ => None,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|variable| env::var(variable).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| Language::from_locale(&locale))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keyword {
+    Vote,
+    Voters,
+    Scores,
+    Backup,
+    Help,
+    Quit,
+    Admin,
+    Logout,
+    Open,
+    Close,
+    AddVoter,
+    Withdraw,
+}
+
+impl Language {
+    pub fn confirmation_key(&self) -> char {
+        match self {
+            Language::Fr => 'o',
+            Language::En => 'y',
+        }
+    }
+}
+
+impl Keyword {
+    pub const ALL: [Keyword; 12] = [
+        Keyword::Vote, Keyword::Voters, Keyword::Scores, Keyword::Backup, Keyword::Help, Keyword::Quit,
+        Keyword::Admin, Keyword::Logout, Keyword::Open, Keyword::Close, Keyword::AddVoter, Keyword::Withdraw,
+    ];
+
+    pub fn word(&self, language: Language) -> &'static str {
+        match (self, language) {
+            (Keyword::Vote, Language::Fr) => "voter",
+            (Keyword::Vote, Language::En) => "vote",
+            (Keyword::Voters, Language::Fr) => "votants",
+            (Keyword::Voters, Language::En) => "voters",
+            (Keyword::Scores, _) => "scores",
+            (Keyword::Backup, Language::Fr) => "sauvegarder",
+            (Keyword::Backup, Language::En) => "backup",
+            (Keyword::Help, Language::Fr) => "aide",
+            (Keyword::Help, Language::En) => "help",
+            (Keyword::Quit, Language::Fr) => "quitter",
+            (Keyword::Quit, Language::En) => "quit",
+            (Keyword::Admin, _) => "admin",
+            (Keyword::Logout, Language::Fr) => "deconnexion",
+            (Keyword::Logout, Language::En) => "logout",
+            (Keyword::Open, Language::Fr) => "ouvrir",
+            (Keyword::Open, Language::En) => "open",
+            (Keyword::Close, Language::Fr) => "clore",
+            (Keyword::Close, Language::En) => "close",
+            (Keyword::AddVoter, Language::Fr) => "inscrire",
+            (Keyword::AddVoter, Language::En) => "add-voter",
+            (Keyword::Withdraw, Language::Fr) => "retirer",
+            (Keyword::Withdraw, Language::En) => "withdraw",
+        }
+    }
+
+    pub fn parse(word: &str, language: Language) -> Option<Self> {
+        Keyword::ALL.into_iter().find(|keyword| keyword.word(language) == word)
+    }
+}
+
+pub enum Message<'a> {
+    Help,
+    Usage(Keyword),
+    UnknownCommand(&'a str),
+    UnterminatedQuote,
+    MissingCandidates,
+    PersistentStorageRequired,
+    MemoryMigration,
+    ElectionInitialized(usize),
+    ElectionClosed,
+    ElectionOpened,
+    ElectionAlreadyOpen,
+    AdminRequired,
+    AdminGranted,
+    AdminDenied,
+    AdminLoggedOut,
+    AdminTokenRequired,
+    AdminTokenRejected,
+    ReopenRefused,
+    VoterAdded(&'a str, &'a str),
+    AuditFailed(&'a io::Error),
+    AuditTitle,
+    AuditUnreadable(&'a io::Error),
+    AuditChainBroken(usize),
+    TrackingCode(&'a str),
+    BallotSuperseded(&'a str),
+    BulletinTitle,
+    BallotFound(&'a str, &'a str),
+    BallotNotFound(&'a str),
+    InvalidBallot(&'a InvalidReason),
+    BulletinRoot(&'a str),
+    BulletinVerified(usize, &'a str),
+    BulletinNotPublished,
+    ProofVerified(&'a str),
+    VerificationFailed(&'a VerificationFailure),
+    FileUnreadable(&'a str, &'a str),
+    SealedBallot,
+    SealedVotes,
+    ElectionKeyExported(&'a str),
+    ElectionKeyExists(&'a str),
+    ElectionKeyRequired,
+    EncryptedWriteIn,
+    ResultsSealed,
+    Revelation(&'a Revelation),
+    InvalidThreshold(usize, usize),
+    SharesExported(usize, usize),
+    ShareExported(u64, &'a str),
+    ShareFilesRequired(usize),
+    ResultsAwaitTrustees(usize, usize),
+    Submission(&'a Submission),
+    CandidateRenamed(&'a str, &'a str),
+    CodesIssued(usize, &'a str),
+    CodesAlreadyIssued(&'a [Voter]),
+    VotingCodeField,
+    VotingCodeFieldHelp,
+    CodesTitle,
+    Withdrawal(&'a Withdrawal),
+    Delegation(&'a Delegation),
+    ProxyMarker(&'a str),
+    WriteInMarker,
+    WithdrawnMarker,
+    UnknownCandidate(&'a str),
+    ElectionRestored(&'a str),
+    ElectionMigrated(usize),
+    ElectionSaved(&'a str),
+    BackupFailed(&'a StorageError),
+    Listening(&'a str),
+    VoterField,
+    PrincipalField,
+    PrincipalFieldHelp,
+    CandidatesTitle,
+    ResultsTitle,
+    BlankChoice,
+    ConfirmVote(&'a str, &'a str),
+    VoterFieldHelp,
+    CandidatesHelp,
+    Vote(&'a VoteOutcome),
+    Storage(&'a StorageError),
+    ScoresTitle,
+    VotersTitle,
+    BlankVotes,
+    InvalidVotes,
+    InvalidReason(&'a InvalidReason),
+    Turnout,
+}
+
+impl Message<'_> {
+    pub fn text(&self, language: Language) -> String {
+        match language {
+            Language::Fr => self.french(),
+            Language::En => self.english(),
+        }
+    }
+
+    fn french(&self) -> String {
+        match self {
+            Message::Help => format!(
+                "\n -{vote} <votant> <candidat> : voter pour un candidat\n\n -{vote} <votant> : vote blanc\n\n -{vote} <votant> <candidat> <code> : voter avec son code de vote (\"\" pour un vote blanc)\n\n -{vote} <mandataire> <candidat> <code> <mandant> : voter par procuration (\"\" sans code de vote)\n\n -{voters} : voir les votants\n\n -{scores} : voir les scores\n\n -{backup} <fichier> : sauvegarder l'élection dans une archive\n\n -{help} : afficher cette aide\n\n -{quit} : quitter\n\n Commandes d'administration :\n\n -{admin} <phrase secrète> : passer en mode administrateur\n\n -{open} : ouvrir le scrutin avant l'heure prévue\n\n -{close} : clore le scrutin\n\n -{add_voter} <votant> : inscrire un votant et générer son code de vote\n\n -{withdraw} <candidat> [keep|invalidate] : retirer un candidat\n\n -{logout} : quitter le mode administrateur\n\n Les noms contenant des espaces s'écrivent entre guillemets : {vote} \"Jean Dupont\" \"Marine Le Pen\"",
+                vote = Keyword::Vote.word(Language::Fr),
+                voters = Keyword::Voters.word(Language::Fr),
+                scores = Keyword::Scores.word(Language::Fr),
+                backup = Keyword::Backup.word(Language::Fr),
+                help = Keyword::Help.word(Language::Fr),
+                quit = Keyword::Quit.word(Language::Fr),
+                admin = Keyword::Admin.word(Language::Fr),
+                open = Keyword::Open.word(Language::Fr),
+                close = Keyword::Close.word(Language::Fr),
+                add_voter = Keyword::AddVoter.word(Language::Fr),
+                withdraw = Keyword::Withdraw.word(Language::Fr),
+                logout = Keyword::Logout.word(Language::Fr),
+            ),
+            Message::Usage(keyword) => format!("Usage : {}", match keyword {
+                Keyword::Vote => "voter <votant> [candidat] [code] [mandant]",
+                Keyword::Voters => "votants",
+                Keyword::Scores => "scores",
+                Keyword::Backup => "sauvegarder <fichier>",
+                Keyword::Help => "aide",
+                Keyword::Quit => "quitter",
+                Keyword::Admin => "admin <phrase secrète>",
+                Keyword::Logout => "deconnexion",
+                Keyword::Open => "ouvrir",
+                Keyword::Close => "clore",
+                Keyword::AddVoter => "inscrire <votant>",
+                Keyword::Withdraw => "retirer <candidat> [keep|invalidate]",
+            }),
+            Message::UnknownCommand(word) => format!("Commande invalide : {} (tapez {} pour la liste des commandes)", word, Keyword::Help.word(Language::Fr)),
+            Message::UnterminatedQuote => String::from("Guillemet non fermé"),
+            Message::MissingCandidates => String::from("Veuillez renseigner au moins un candidat (-c)"),
+            Message::PersistentStorageRequired => String::from("Cette commande nécessite un stockage persistant (-s file)"),
+            Message::MemoryMigration => String::from("La migration nécessite un stockage persistant en source et en destination (-s file --migrate-to file)"),
+            Message::ElectionInitialized(count) => format!("Élection initialisée avec {} candidats", count),
+            Message::ElectionClosed => String::from("Le scrutin est clos"),
+            Message::ElectionOpened => String::from("Le scrutin est ouvert"),
+            Message::ElectionAlreadyOpen => String::from("Le scrutin est déjà ouvert"),
+            Message::AdminRequired => format!("Cette commande est réservée à l'administrateur (tapez {} <phrase secrète>)", Keyword::Admin.word(Language::Fr)),
+            Message::AdminGranted => String::from("Mode administrateur activé"),
+            Message::AdminDenied => String::from("Phrase secrète incorrecte"),
+            Message::AdminLoggedOut => String::from("Mode administrateur désactivé"),
+            Message::AdminTokenRequired => String::from("Cette commande exige le jeton d'administration (--admin-token ou VOTING_ADMIN_TOKEN)"),
+            Message::AdminTokenRejected => String::from("Le jeton d'administration ne correspond pas à cette élection"),
+            Message::ReopenRefused => String::from("Le scrutin est clos et ne peut pas être rouvert"),
+            Message::VoterAdded(voter, code) => format!("{} est inscrit, code de vote : {}", voter, code),
+            Message::AuditFailed(error) => format!("Impossible d'écrire dans le journal d'audit : {}", error),
+            Message::AuditTitle => String::from("Journal d'audit :"),
+            Message::AuditUnreadable(error) => format!("Impossible de lire le journal d'audit : {}", error),
+            Message::AuditChainBroken(line) => format!("La ligne {} du journal d'audit ne suit pas la précédente : le journal a été modifié", line),
+            Message::TrackingCode(code) => format!("Code de suivi de votre bulletin : {}", code),
+            Message::BallotSuperseded(code) => format!("Votre bulletin précédent {} est remplacé et ne sera pas compté", code),
+            Message::BulletinTitle => String::from("Bulletins publiés :"),
+            Message::BallotFound(code, ballot) => format!("Le bulletin {} a été compté : {}", code, ballot),
+            Message::BallotNotFound(code) => format!("Aucun bulletin ne correspond au code de suivi {}", code),
+            Message::InvalidBallot(reason) => format!("Bulletin nul ({})", Message::InvalidReason(reason).french()),
+            Message::BulletinRoot(root) => format!("Empreinte racine des bulletins publiés : {}", root),
+            Message::BulletinNotPublished => String::from("Les bulletins sont publiés à la clôture du scrutin"),
+            Message::BulletinVerified(count, root) => format!("Publication vérifiée : {} bulletins, racine {}, le décompte correspond", count, root),
+            Message::ProofVerified(code) => format!("Preuve d'inclusion valide pour le bulletin {}", code),
+            Message::VerificationFailed(failure) => match failure {
+                VerificationFailure::RootMismatch { published, computed } => format!("Échec de la vérification : racine publiée {} mais racine recalculée {}", published, computed),
+                VerificationFailure::TallyMismatch { choice, published, counted } => format!("Échec de la vérification : {} annonce {} voix mais {} bulletins publiés", choice, published, counted),
+                VerificationFailure::UnknownBallotKind(kind) => format!("Échec de la vérification : type de bulletin inconnu {}", kind),
+                VerificationFailure::InvalidProof(code) => format!("Échec de la vérification : la preuve du bulletin {} ne mène pas à la racine annoncée", code),
+                VerificationFailure::MalformedBallot(code) => format!("Échec de la vérification : le bulletin chiffré {} est illisible", code),
+                VerificationFailure::MissingDecryption => String::from("Échec de la vérification : le décompte dévoilé n'est accompagné d'aucune preuve de déchiffrement"),
+                VerificationFailure::InvalidDecryption(part) => format!("Échec de la vérification : la preuve de déchiffrement est invalide ({})", part),
+            },
+            Message::FileUnreadable(path, error) => format!("Impossible de lire {} : {}", path, error),
+            Message::SealedBallot => String::from("Bulletin chiffré"),
+            Message::SealedVotes => String::from("bulletins chiffrés"),
+            Message::ElectionKeyExported(path) => format!("Clé de dépouillement exportée dans {}, conservez-la hors de la machine de vote", path),
+            Message::ElectionKeyExists(path) => format!("La clé {} existe déjà, utilisez --force pour la remplacer", path),
+            Message::ElectionKeyRequired => String::from("Un scrutin chiffré nécessite --election-key pour exporter la clé de dépouillement, ou --trustees et --threshold pour la partager"),
+            Message::EncryptedWriteIn => String::from("Les votes libres ne sont pas disponibles dans un scrutin chiffré"),
+            Message::ResultsSealed => String::from("Les résultats restent chiffrés, relancez la clôture avec --election-key pour les dépouiller"),
+            Message::Revelation(revelation) => match revelation {
+                Revelation::Revealed(count) => format!("{} bulletins chiffrés dépouillés", count),
+                Revelation::NotEncrypted => String::from("Ce scrutin n'est pas chiffré"),
+                Revelation::StillOpen => String::from("Le scrutin doit être clos avant le dépouillement"),
+                Revelation::WrongKey => String::from("Cette clé ne correspond pas à la clé du scrutin"),
+                Revelation::Undecryptable => String::from("Le total chiffré ne peut pas être déchiffré"),
+                Revelation::NotShared => String::from("La clé de ce scrutin n'est pas partagée entre des garants"),
+                Revelation::MissingShares { submitted, threshold } => format!("{} déchiffrements partiels valides sur les {} nécessaires", submitted, threshold),
+            },
+            Message::InvalidThreshold(threshold, trustees) => format!("Le seuil doit être compris entre 1 et le nombre de garants ({}), pas {}", trustees, threshold),
+            Message::SharesExported(trustees, threshold) => format!("{} parts de clé exportées, {} garants devront déchiffrer les résultats", trustees, threshold),
+            Message::ShareExported(trustee, path) => format!("Part du garant {} exportée dans {} : remettez-la à ce seul garant puis supprimez-la de cette machine", trustee, path),
+            Message::ShareFilesRequired(trustees) => format!("Indiquez un fichier par garant avec --share-file ({} attendus)", trustees),
+            Message::ResultsAwaitTrustees(threshold, trustees) => format!("Les résultats restent chiffrés, {} des {} garants doivent soumettre leur déchiffrement partiel", threshold, trustees),
+            Message::Submission(submission) => match submission {
+                Submission::Accepted { submitted, threshold } => format!("Déchiffrement partiel accepté ({} sur {})", submitted, threshold),
+                Submission::NotShared => String::from("La clé de ce scrutin n'est pas partagée entre des garants"),
+                Submission::StillOpen => String::from("Le scrutin doit être clos avant le dépouillement"),
+                Submission::UnknownTrustee(trustee) => format!("Le garant {} n'existe pas dans ce scrutin", trustee),
+                Submission::StaleTally(trustee) => format!("Le déchiffrement partiel du garant {} ne porte pas sur les bulletins actuels", trustee),
+                Submission::InvalidProof(trustee) => format!("La preuve du déchiffrement partiel du garant {} est invalide", trustee),
+            },
+            Message::CodesIssued(count, filepath) => format!("{} codes de vote générés et exportés dans {}", count, filepath),
+            Message::CodesAlreadyIssued(voters) => format!("Un code de vote a déjà été remis à {}, utilisez --force pour le remplacer", voters.iter().map(|voter| voter.0.as_str()).collect::<Vec<&str>>().join(", ")),
+            Message::VotingCodeField => String::from("Code de vote"),
+            Message::VotingCodeFieldHelp => String::from("Entrée : valider   Échap : retour"),
+            Message::CodesTitle => String::from("Codes de vote :"),
+            Message::CandidateRenamed(candidate, name) => format!("Le candidat {} s'affiche désormais sous le nom {}", candidate, name),
+            Message::UnknownCandidate(candidate) => format!("Le candidat : {} n'existe pas !", candidate),
+            Message::Withdrawal(withdrawal) => match withdrawal {
+                Withdrawal::Withdrawn(candidate) => format!("Le candidat {} s'est retiré du scrutin", candidate.0),
+                Withdrawal::UnknownCandidate(candidate) => format!("Le candidat : {} n'existe pas !", candidate.0),
+                Withdrawal::AlreadyWithdrawn(candidate) => format!("Le candidat {} s'est déjà retiré", candidate.0),
+            },
+            Message::Delegation(delegation) => match delegation {
+                Delegation::Registered(principal, holder) => format!("Procuration enregistrée : {} votera au nom de {}", holder.0, principal.0),
+                Delegation::SelfDelegation(principal) => format!("{} ne peut pas se donner procuration à lui-même", principal.0),
+                Delegation::InvalidPeriod(principal) => format!("La période de validité de la procuration de {} est vide ou déjà échue", principal.0),
+                Delegation::UnknownVoter(voter) => format!("{} n'est pas inscrit sur la liste électorale", voter.0),
+                Delegation::AlreadyVoted(principal) => format!("{} a déjà voté, la procuration n'a pas été enregistrée", principal.0),
+                Delegation::AlreadyDelegated(principal) => format!("{} a déjà donné une procuration en cours de validité", principal.0),
+                Delegation::TooManyProxies(holder, max) => format!("{} détient déjà le maximum de {} procuration(s)", holder.0, max),
+            },
+            Message::ProxyMarker(holder) => format!("par procuration : {}", holder),
+            Message::WriteInMarker => String::from("vote libre"),
+            Message::WithdrawnMarker => String::from("retiré"),
+            Message::ElectionRestored(path) => format!("Élection restaurée depuis {}", path),
+            Message::ElectionMigrated(count) => format!("Élection migrée : {} votants vérifiés", count),
+            Message::ElectionSaved(path) => format!("Élection sauvegardée dans {}", path),
+            Message::BackupFailed(error) => format!("Échec de la sauvegarde : {}", Message::Storage(error).french()),
+            Message::Listening(address) => format!("Serveur de vote à l'écoute sur {}", address),
+            Message::VoterField => String::from("Identifiant du votant"),
+            Message::PrincipalField => String::from("Mandant (vote par procuration)"),
+            Message::PrincipalFieldHelp => String::from("Entrée : valider   Échap : retour"),
+            Message::CandidatesTitle => String::from("Candidats"),
+            Message::ResultsTitle => String::from("Résultats"),
+            Message::BlankChoice => String::from("Vote blanc"),
+            Message::ConfirmVote(voter, choice) => format!("Confirmer le vote de {} : {} ? (o/n)", voter, choice),
+            Message::VoterFieldHelp => String::from("Entrée : valider   Tab : voter par procuration   Échap : quitter"),
+            Message::CandidatesHelp => String::from("↑/↓ : choisir   Entrée : valider   Échap : retour"),
+            Message::Vote(outcome) => match outcome {
+                VoteOutcome::AcceptedVote(_, _) => String::from("Vote accepté !"),
+                VoteOutcome::WriteInVote(_, name) => format!("Vote libre enregistré pour {}", name),
+                VoteOutcome::BlankVote(_) => String::from("Vote blanc"),
+                VoteOutcome::InvalidVote(_, reason) => format!("Vote invalide : {}", Message::InvalidReason(reason).french()),
+                VoteOutcome::HasAlreadyVoted(voter) => format!("{} à déjà voté. Il ne peut pas voter 2 fois !", voter.0),
+                VoteOutcome::NotYetOpen(_) => String::from("Le scrutin n'est pas encore ouvert, le vote n'a pas été enregistré"),
+                VoteOutcome::ElectionClosed(_) => String::from("Le scrutin est clos, le vote n'a pas été enregistré"),
+                VoteOutcome::MissingCode(voter) => format!("{} doit présenter son code de vote, le vote n'a pas été enregistré", voter.0),
+                VoteOutcome::WrongCode(voter) => format!("Code de vote incorrect pour {}, le vote n'a pas été enregistré", voter.0),
+                VoteOutcome::InvalidProxy(voter, holder) => format!("{} n'a pas de procuration valide pour voter au nom de {}, le vote n'a pas été enregistré", holder.0, voter.0),
+            },
+            Message::Storage(error) => match error {
+                StorageError::Missing(filepath) => format!("Le fichier {} est introuvable", filepath),
+                StorageError::Corrupted(details) => format!("Les données enregistrées sont corrompues ({})", details),
+                StorageError::LockPoisoned => String::from("Le stockage est inaccessible suite à une erreur interne, redémarrez la machine"),
+                StorageError::Conflict(details) => format!("Conflit avec l'élection enregistrée ({})", details),
+                StorageError::Io(error) => format!("Erreur d'accès au stockage : {}", error),
+            },
+            Message::ScoresTitle => String::from("Scores :"),
+            Message::VotersTitle => String::from("Votants :"),
+            Message::BlankVotes => String::from("votes blancs"),
+            Message::InvalidVotes => String::from("votes invalides"),
+            Message::InvalidReason(reason) => match reason {
+                InvalidReason::UnknownCandidate => String::from("candidat inconnu"),
+                InvalidReason::WithdrawnCandidate => String::from("candidat retiré"),
+            },
+            Message::Turnout => String::from("participation"),
+        }
+    }
+
+    fn english(&self) -> String {
+        match self {
+            Message::Help => format!(
+                "\n -{vote} <voter> <candidate> : vote for a candidate\n\n -{vote} <voter> : blank vote\n\n -{vote} <voter> <candidate> <code> : vote with a voting code (\"\" for a blank vote)\n\n -{vote} <holder> <candidate> <code> <principal> : vote by proxy (\"\" without a voting code)\n\n -{voters} : list the voters\n\n -{scores} : show the scores\n\n -{backup} <file> : save the election to an archive\n\n -{help} : show this help\n\n -{quit} : quit\n\n Administration commands:\n\n -{admin} <passphrase> : switch to administrator mode\n\n -{open} : open the election before its scheduled time\n\n -{close} : close the election\n\n -{add_voter} <voter> : register a voter and issue a voting code\n\n -{withdraw} <candidate> [keep|invalidate] : withdraw a candidate\n\n -{logout} : leave administrator mode\n\n Names containing spaces must be quoted: {vote} \"John Smith\" \"Marine Le Pen\"",
+                vote = Keyword::Vote.word(Language::En),
+                voters = Keyword::Voters.word(Language::En),
+                scores = Keyword::Scores.word(Language::En),
+                backup = Keyword::Backup.word(Language::En),
+                help = Keyword::Help.word(Language::En),
+                quit = Keyword::Quit.word(Language::En),
+                admin = Keyword::Admin.word(Language::En),
+                open = Keyword::Open.word(Language::En),
+                close = Keyword::Close.word(Language::En),
+                add_voter = Keyword::AddVoter.word(Language::En),
+                withdraw = Keyword::Withdraw.word(Language::En),
+                logout = Keyword::Logout.word(Language::En),
+            ),
+            Message::Usage(keyword) => format!("Usage: {}", match keyword {
+                Keyword::Vote => "vote <voter> [candidate] [code] [principal]",
+                Keyword::Voters => "voters",
+                Keyword::Scores => "scores",
+                Keyword::Backup => "backup <file>",
+                Keyword::Help => "help",
+                Keyword::Quit => "quit",
+                Keyword::Admin => "admin <passphrase>",
+                Keyword::Logout => "logout",
+                Keyword::Open => "open",
+                Keyword::Close => "close",
+                Keyword::AddVoter => "add-voter <voter>",
+                Keyword::Withdraw => "withdraw <candidate> [keep|invalidate]",
+            }),
+            Message::UnknownCommand(word) => format!("Invalid command: {} (type {} for the list of commands)", word, Keyword::Help.word(Language::En)),
+            Message::UnterminatedQuote => String::from("Unterminated quote"),
+            Message::MissingCandidates => String::from("Please provide at least one candidate (-c)"),
+            Message::PersistentStorageRequired => String::from("This command requires a persistent storage (-s file)"),
+            Message::MemoryMigration => String::from("Migration requires a persistent storage as both source and target (-s file --migrate-to file)"),
+            Message::ElectionInitialized(count) => format!("Election initialized with {} candidates", count),
+            Message::ElectionClosed => String::from("The election is closed"),
+            Message::ElectionOpened => String::from("The election is open"),
+            Message::ElectionAlreadyOpen => String::from("The election is already open"),
+            Message::AdminRequired => format!("This command is restricted to the administrator (type {} <passphrase>)", Keyword::Admin.word(Language::En)),
+            Message::AdminGranted => String::from("Administrator mode enabled"),
+            Message::AdminDenied => String::from("Wrong passphrase"),
+            Message::AdminLoggedOut => String::from("Administrator mode disabled"),
+            Message::AdminTokenRequired => String::from("This command requires the admin token (--admin-token or VOTING_ADMIN_TOKEN)"),
+            Message::AdminTokenRejected => String::from("The admin token does not match this election"),
+            Message::ReopenRefused => String::from("The election is closed and cannot be reopened"),
+            Message::VoterAdded(voter, code) => format!("{} is registered, voting code: {}", voter, code),
+            Message::AuditFailed(error) => format!("Cannot write to the audit log: {}", error),
+            Message::AuditTitle => String::from("Audit log:"),
+            Message::AuditUnreadable(error) => format!("Cannot read the audit log: {}", error),
+            Message::AuditChainBroken(line) => format!("Line {} of the audit log does not follow the previous one: the log was tampered with", line),
+            Message::TrackingCode(code) => format!("Ballot tracking code: {}", code),
+            Message::BallotSuperseded(code) => format!("Your previous ballot {} is replaced and will not be counted", code),
+            Message::BulletinTitle => String::from("Published ballots:"),
+            Message::BallotFound(code, ballot) => format!("Ballot {} was counted: {}", code, ballot),
+            Message::BallotNotFound(code) => format!("No ballot matches the tracking code {}", code),
+            Message::InvalidBallot(reason) => format!("Invalid ballot ({})", Message::InvalidReason(reason).english()),
+            Message::BulletinRoot(root) => format!("Root hash of the published ballots: {}", root),
+            Message::BulletinNotPublished => String::from("Ballots are published once the election is closed"),
+            Message::BulletinVerified(count, root) => format!("Bulletin verified: {} ballots, root {}, the tally matches", count, root),
+            Message::ProofVerified(code) => format!("Valid inclusion proof for ballot {}", code),
+            Message::VerificationFailed(failure) => match failure {
+                VerificationFailure::RootMismatch { published, computed } => format!("Verification failed: published root {} but recomputed root {}", published, computed),
+                VerificationFailure::TallyMismatch { choice, published, counted } => format!("Verification failed: {} is announced with {} votes but {} ballots are published", choice, published, counted),
+                VerificationFailure::UnknownBallotKind(kind) => format!("Verification failed: unknown ballot kind {}", kind),
+                VerificationFailure::InvalidProof(code) => format!("Verification failed: the proof of ballot {} does not lead to the announced root", code),
+                VerificationFailure::MalformedBallot(code) => format!("Verification failed: the encrypted ballot {} is unreadable", code),
+                VerificationFailure::MissingDecryption => String::from("Verification failed: the revealed tally comes without a decryption proof"),
+                VerificationFailure::InvalidDecryption(part) => format!("Verification failed: the decryption proof is invalid ({})", part),
+            },
+            Message::FileUnreadable(path, error) => format!("Cannot read {}: {}", path, error),
+            Message::SealedBallot => String::from("Encrypted ballot"),
+            Message::SealedVotes => String::from("encrypted ballots"),
+            Message::ElectionKeyExported(path) => format!("Election key exported to {}, keep it away from the voting machine", path),
+            Message::ElectionKeyExists(path) => format!("The key {} already exists, use --force to replace it", path),
+            Message::ElectionKeyRequired => String::from("An encrypted election needs --election-key to export the election key, or --trustees and --threshold to share it"),
+            Message::EncryptedWriteIn => String::from("Write-in votes are not available in an encrypted election"),
+            Message::ResultsSealed => String::from("Results remain encrypted, close again with --election-key to reveal them"),
+            Message::Revelation(revelation) => match revelation {
+                Revelation::Revealed(count) => format!("{} encrypted ballots counted", count),
+                Revelation::NotEncrypted => String::from("This election is not encrypted"),
+                Revelation::StillOpen => String::from("The election must be closed before counting"),
+                Revelation::WrongKey => String::from("This key does not match the election key"),
+                Revelation::Undecryptable => String::from("The encrypted total cannot be decrypted"),
+                Revelation::NotShared => String::from("The key of this election is not shared among trustees"),
+                Revelation::MissingShares { submitted, threshold } => format!("{} valid partial decryptions out of the {} required", submitted, threshold),
+            },
+            Message::InvalidThreshold(threshold, trustees) => format!("The threshold must be between 1 and the number of trustees ({}), not {}", trustees, threshold),
+            Message::SharesExported(trustees, threshold) => format!("{} key shares exported, {} trustees will have to decrypt the results", trustees, threshold),
+            Message::ShareExported(trustee, path) => format!("Key share of trustee {} exported to {}: hand it to that trustee only, then delete it from this machine", trustee, path),
+            Message::ShareFilesRequired(trustees) => format!("Give one file per trustee with --share-file ({} expected)", trustees),
+            Message::ResultsAwaitTrustees(threshold, trustees) => format!("Results remain encrypted, {} of the {} trustees must submit their partial decryption", threshold, trustees),
+            Message::Submission(submission) => match submission {
+                Submission::Accepted { submitted, threshold } => format!("Partial decryption accepted ({} of {})", submitted, threshold),
+                Submission::NotShared => String::from("The key of this election is not shared among trustees"),
+                Submission::StillOpen => String::from("The election must be closed before counting"),
+                Submission::UnknownTrustee(trustee) => format!("Trustee {} does not exist in this election", trustee),
+                Submission::StaleTally(trustee) => format!("The partial decryption of trustee {} does not cover the current ballots", trustee),
+                Submission::InvalidProof(trustee) => format!("The proof of the partial decryption of trustee {} is invalid", trustee),
+            },
+            Message::CodesIssued(count, filepath) => format!("{} voting codes generated and exported to {}", count, filepath),
+            Message::CodesAlreadyIssued(voters) => format!("A voting code was already issued to {}, use --force to replace it", voters.iter().map(|voter| voter.0.as_str()).collect::<Vec<&str>>().join(", ")),
+            Message::VotingCodeField => String::from("Voting code"),
+            Message::VotingCodeFieldHelp => String::from("Enter: validate   Esc: back"),
+            Message::CodesTitle => String::from("Voting codes:"),
+            Message::CandidateRenamed(candidate, name) => format!("Candidate {} is now displayed as {}", candidate, name),
+            Message::UnknownCandidate(candidate) => format!("Candidate {} does not exist!", candidate),
+            Message::Withdrawal(withdrawal) => match withdrawal {
+                Withdrawal::Withdrawn(candidate) => format!("Candidate {} has withdrawn from the election", candidate.0),
+                Withdrawal::UnknownCandidate(candidate) => format!("Candidate {} does not exist!", candidate.0),
+                Withdrawal::AlreadyWithdrawn(candidate) => format!("Candidate {} has already withdrawn", candidate.0),
+            },
+            Message::Delegation(delegation) => match delegation {
+                Delegation::Registered(principal, holder) => format!("Proxy registered: {} will vote on behalf of {}", holder.0, principal.0),
+                Delegation::SelfDelegation(principal) => format!("{} cannot give a proxy to themselves", principal.0),
+                Delegation::InvalidPeriod(principal) => format!("The validity period of the proxy of {} is empty or already over", principal.0),
+                Delegation::UnknownVoter(voter) => format!("{} is not on the voter roll", voter.0),
+                Delegation::AlreadyVoted(principal) => format!("{} has already voted, the proxy was not registered", principal.0),
+                Delegation::AlreadyDelegated(principal) => format!("{} has already given a proxy that is still valid", principal.0),
+                Delegation::TooManyProxies(holder, max) => format!("{} already holds the maximum of {} proxies", holder.0, max),
+            },
+            Message::ProxyMarker(holder) => format!("by proxy: {}", holder),
+            Message::WriteInMarker => String::from("write-in"),
+            Message::WithdrawnMarker => String::from("withdrawn"),
+            Message::ElectionRestored(path) => format!("Election restored from {}", path),
+            Message::ElectionMigrated(count) => format!("Election migrated: {} voters verified", count),
+            Message::ElectionSaved(path) => format!("Election saved to {}", path),
+            Message::BackupFailed(error) => format!("Backup failed: {}", Message::Storage(error).english()),
+            Message::Listening(address) => format!("Voting server listening on {}", address),
+            Message::VoterField => String::from("Voter id"),
+            Message::PrincipalField => String::from("Principal (proxy vote)"),
+            Message::PrincipalFieldHelp => String::from("Enter: validate   Esc: back"),
+            Message::CandidatesTitle => String::from("Candidates"),
+            Message::ResultsTitle => String::from("Results"),
+            Message::BlankChoice => String::from("Blank vote"),
+            Message::ConfirmVote(voter, choice) => format!("Confirm the vote of {}: {}? (y/n)", voter, choice),
+            Message::VoterFieldHelp => String::from("Enter: validate   Tab: vote by proxy   Esc: quit"),
+            Message::CandidatesHelp => String::from("Up/Down: choose   Enter: validate   Esc: back"),
+            Message::Vote(outcome) => match outcome {
+                VoteOutcome::AcceptedVote(_, _) => String::from("Vote accepted!"),
+                VoteOutcome::WriteInVote(_, name) => format!("Write-in vote recorded for {}", name),
+                VoteOutcome::BlankVote(_) => String::from("Blank vote"),
+                VoteOutcome::InvalidVote(_, reason) => format!("Invalid vote: {}", Message::InvalidReason(reason).english()),
+                VoteOutcome::HasAlreadyVoted(voter) => format!("{} has already voted and cannot vote twice!", voter.0),
+                VoteOutcome::NotYetOpen(_) => String::from("The election is not open yet, the vote was not recorded"),
+                VoteOutcome::ElectionClosed(_) => String::from("The election is closed, the vote was not recorded"),
+                VoteOutcome::MissingCode(voter) => format!("{} must provide a voting code, the vote was not recorded", voter.0),
+                VoteOutcome::WrongCode(voter) => format!("Wrong voting code for {}, the vote was not recorded", voter.0),
+                VoteOutcome::InvalidProxy(voter, holder) => format!("{} holds no valid proxy to vote on behalf of {}, the vote was not recorded", holder.0, voter.0),
+            },
+            Message::Storage(error) => match error {
+                StorageError::Missing(filepath) => format!("The file {} cannot be found", filepath),
+                StorageError::Corrupted(details) => format!("The stored data is corrupted ({})", details),
+                StorageError::LockPoisoned => String::from("The storage is unavailable after an internal error, restart the machine"),
+                StorageError::Conflict(details) => format!("Conflict with the stored election ({})", details),
+                StorageError::Io(error) => format!("Storage access error: {}", error),
+            },
+            Message::ScoresTitle => String::from("Scores:"),
+            Message::VotersTitle => String::from("Voters:"),
+            Message::BlankVotes => String::from("blank votes"),
+            Message::InvalidVotes => String::from("invalid votes"),
+            Message::InvalidReason(reason) => match reason {
+                InvalidReason::UnknownCandidate => String::from("unknown candidate"),
+                InvalidReason::WithdrawnCandidate => String::from("withdrawn candidate"),
+            },
+            Message::Turnout => String::from("turnout"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests 
+{
+    use crate::domain::{Voter, VoteOutcome};
+    use super::{Keyword, Language, Message};
+
+    #[test]
+    fn keywords_depend_on_language()
+    {
+        assert_eq!(Keyword::parse("voter", Language::Fr), Some(Keyword::Vote));
+        assert_eq!(Keyword::parse("vote", Language::En), Some(Keyword::Vote));
+        assert_eq!(Keyword::parse("voter", Language::En), None);
+        assert_eq!(Keyword::parse("backup", Language::En), Some(Keyword::Backup));
+    }
+
+    #[test]
+    fn locale_selects_language()
+    {
+        assert_eq!(Language::from_locale("en_US.UTF-8"), Some(Language::En));
+        assert_eq!(Language::from_locale("fr_FR.UTF-8"), Some(Language::Fr));
+        assert_eq!(Language::from_locale("C"), None);
+    }
+
+    #[test]
+    fn messages_are_translated()
+    {
+        let outcome : VoteOutcome = VoteOutcome::HasAlreadyVoted(Voter("Jean".to_string()));
+
+        assert_eq!(Message::Vote(&outcome).text(Language::Fr), "Jean à déjà voté. Il ne peut pas voter 2 fois !");
+        assert_eq!(Message::Vote(&outcome).text(Language::En), "Jean has already voted and cannot vote twice!");
+    }
+}
@@ -0,0 +1,406 @@
+use serde::Serialize;
+
+use crate::{audit::{AuditEntry, format_timestamp}, bulletin::PublishedBulletin, configuration::OutputFormat, domain::{AttendanceSheet, Ballot, InvalidReason, Scoreboard, Voter, VotingMachine}, messages::{Language, Message}};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CandidateResult {
+    pub id: String,
+    pub candidate: String,
+    pub party: Option<String>,
+    pub withdrawn: bool,
+    pub votes: usize,
+    pub percentage: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WriteInResult {
+    pub name: String,
+    pub votes: usize,
+    pub percentage: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct InvalidReasonResult {
+    pub reason: String,
+    pub votes: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct VoterResult {
+    pub voter: String,
+    pub proxy_holder: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Results {
+    pub candidates: Vec<CandidateResult>,
+    pub write_ins: Vec<WriteInResult>,
+    pub blank: usize,
+    pub invalid: usize,
+    pub invalid_reasons: Vec<InvalidReasonResult>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub sealed: usize,
+    pub turnout: usize,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+fn percentage(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (part as f64 * 10000.0 / total as f64).round() / 100.0
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl From<&Scoreboard> for Results {
+    fn from(scoreboard: &Scoreboard) -> Self {
+        let expressed : usize = scoreboard.expressed_votes();
+
+        let candidates : Vec<CandidateResult> = scoreboard.ballot()
+            .into_iter()
+            .map(|candidate| {
+                let votes : usize = scoreboard.scores[candidate].0;
+                CandidateResult { 
+                    id: candidate.0.clone(),
+                    candidate: scoreboard.display_name(candidate).to_string(), 
+                    party: scoreboard.profiles.get(candidate).and_then(|profile| profile.party.clone()),
+                    withdrawn: scoreboard.is_withdrawn(candidate),
+                    votes, 
+                    percentage: percentage(votes, expressed),
+                }
+            })
+            .collect();
+
+        let write_ins : Vec<WriteInResult> = scoreboard.write_in_scores
+            .iter()
+            .map(|(name, score)| WriteInResult { 
+                name: name.clone(), 
+                votes: score.0, 
+                percentage: percentage(score.0, expressed),
+            })
+            .collect();
+
+        Results {
+            candidates,
+            write_ins,
+            blank: scoreboard.blank_scores.0,
+            invalid: scoreboard.invalid_scores.0,
+            invalid_reasons: scoreboard.invalid_reasons
+                .iter()
+                .map(|(reason, score)| InvalidReasonResult { reason: reason.code().to_string(), votes: score.0 })
+                .collect(),
+            sealed: scoreboard.sealed_scores.0,
+            turnout: scoreboard.total_votes(),
+        }
+    }
+}
+
+impl Results {
+    pub fn render(&self, format: OutputFormat, language: Language) -> String {
+        match format {
+            OutputFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+            OutputFormat::Csv => {
+                let mut lines : Vec<String> = vec![String::from("kind,name,votes,percentage,party")];
+                for result in &self.candidates {
+                    let party : String = result.party.as_deref().map(csv_field).unwrap_or_default();
+                    lines.push(format!("candidate,{},{},{:.2},{}", csv_field(&result.candidate), result.votes, result.percentage, party));
+                }
+                for result in &self.write_ins {
+                    lines.push(format!("write_in,{},{},{:.2},", csv_field(&result.name), result.votes, result.percentage));
+                }
+                lines.push(format!("blank,,{},{:.2},", self.blank, percentage(self.blank, self.turnout)));
+                lines.push(format!("invalid,,{},{:.2},", self.invalid, percentage(self.invalid, self.turnout)));
+                for result in &self.invalid_reasons {
+                    lines.push(format!("invalid_reason,{},{},{:.2},", result.reason, result.votes, percentage(result.votes, self.turnout)));
+                }
+                if self.sealed > 0 {
+                    lines.push(format!("sealed,,{},,", self.sealed));
+                }
+                lines.push(format!("turnout,,{},,", self.turnout));
+                lines.join("\n")
+            }
+            OutputFormat::Table => {
+                let mut lines : Vec<String> = vec![Message::ScoresTitle.text(language)];
+                for result in &self.candidates {
+                    let mut label : String = result.candidate.clone();
+                    if let Some(party) = &result.party {
+                        label = format!("{} ({})", label, party);
+                    }
+                    if result.withdrawn {
+                        label = format!("{} [{}]", label, Message::WithdrawnMarker.text(language));
+                    }
+                    lines.push(format!(" - {} : {} ({:.2} %)", label, result.votes, result.percentage));
+                }
+                for result in &self.write_ins {
+                    lines.push(format!(" - {} [{}] : {} ({:.2} %)", result.name, Message::WriteInMarker.text(language), result.votes, result.percentage));
+                }
+                lines.push(format!(" - {} : {}", Message::BlankVotes.text(language), self.blank));
+                lines.push(format!(" - {} : {}", Message::InvalidVotes.text(language), self.invalid));
+                for result in &self.invalid_reasons {
+                    let label : String = InvalidReason::from_code(&result.reason).map(|reason| Message::InvalidReason(&reason).text(language)).unwrap_or_else(|| result.reason.clone());
+                    lines.push(format!("    - {} : {}", label, result.votes));
+                }
+                if self.sealed > 0 {
+                    lines.push(format!(" - {} : {}", Message::SealedVotes.text(language), self.sealed));
+                }
+                lines.push(format!(" - {} : {}", Message::Turnout.text(language), self.turnout));
+                lines.join("\n")
+            }
+        }
+    }
+}
+
+pub fn render_codes(codes: &[(Voter, String)], format: OutputFormat, language: Language) -> String {
+    match format {
+        OutputFormat::Json => {
+            let entries : Vec<serde_json::Value> = codes.iter()
+                .map(|(voter, code)| serde_json::json!({ "voter": voter.0, "code": code }))
+                .collect();
+            serde_json::to_string_pretty(&entries).unwrap_or_default()
+        }
+        OutputFormat::Csv => {
+            let mut lines : Vec<String> = vec![String::from("voter,code")];
+            lines.extend(codes.iter().map(|(voter, code)| format!("{},{}", csv_field(&voter.0), code)));
+            lines.join("\n")
+        }
+        OutputFormat::Table => {
+            let mut lines : Vec<String> = vec![Message::CodesTitle.text(language)];
+            lines.extend(codes.iter().map(|(voter, code)| format!(" - {} : {}", voter.0, code)));
+            lines.join("\n")
+        }
+    }
+}
+
+pub fn render_audit(entries: &[AuditEntry], format: OutputFormat, language: Language) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(entries).unwrap_or_default(),
+        OutputFormat::Csv => {
+            let mut lines : Vec<String> = vec![String::from("timestamp,source,action,detail,outcome,reason")];
+            lines.extend(entries.iter().map(|entry| format!("{},{},{},{},{},{}",
+                entry.timestamp,
+                csv_field(&entry.source),
+                csv_field(&entry.action),
+                csv_field(entry.detail.as_deref().unwrap_or_default()),
+                entry.outcome.code(),
+                csv_field(entry.reason.as_deref().unwrap_or_default()),
+            )));
+            lines.join("\n")
+        }
+        OutputFormat::Table => {
+            let mut lines : Vec<String> = vec![Message::AuditTitle.text(language)];
+            lines.extend(entries.iter().map(|entry| {
+                let detail : String = entry.detail.as_ref().map(|detail| format!(" {}", detail)).unwrap_or_default();
+                let reason : String = entry.reason.as_ref().map(|reason| format!(" ({})", reason)).unwrap_or_default();
+                format!(" - {} [{}] {}{} : {}{}", format_timestamp(entry.timestamp), entry.source, entry.action, detail, entry.outcome.code(), reason)
+            }));
+            lines.join("\n")
+        }
+    }
+}
+
+pub fn ballot_label(ballot: &Ballot, scoreboard: &Scoreboard, language: Language) -> String {
+    match ballot {
+        Ballot::Candidate(candidate) => scoreboard.display_name(candidate).to_string(),
+        Ballot::WriteIn(name) => format!("{} [{}]", name, Message::WriteInMarker.text(language)),
+        Ballot::Blank => Message::BlankChoice.text(language),
+        Ballot::Invalid(reason) => Message::InvalidBallot(reason).text(language),
+        Ballot::Sealed(_) => Message::SealedBallot.text(language),
+    }
+}
+
+pub fn render_bulletin(machine: &VotingMachine, format: OutputFormat, language: Language) -> String {
+    let bulletin : PublishedBulletin = PublishedBulletin::of(machine);
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(&bulletin).unwrap_or_default(),
+        OutputFormat::Csv => {
+            let mut lines : Vec<String> = vec![String::from("tracking_code,kind,choice")];
+            lines.extend(bulletin.ballots.iter().map(|entry| format!("{},{},{}", entry.tracking_code, entry.kind, csv_field(&entry.choice))));
+            lines.join("\n")
+        }
+        OutputFormat::Table => {
+            let mut lines : Vec<String> = vec![Message::BulletinTitle.text(language)];
+            lines.extend(machine.ballot_box.0.iter().map(|(tracking_code, ballot)| format!(" - {} : {}", tracking_code.0, ballot_label(ballot, &machine.scoreboard, language))));
+            lines.push(Message::BulletinRoot(&bulletin.root).text(language));
+            lines.join("\n")
+        }
+    }
+}
+
+pub fn render_voters(sheet: &AttendanceSheet, format: OutputFormat, language: Language) -> String {
+    let names : Vec<&str> = sheet.attendees.iter().map(|voter| voter.0.as_str()).collect();
+    let holders : Vec<Option<&str>> = sheet.attendees.iter().map(|voter| sheet.holder_for(voter).map(|holder| holder.0.as_str())).collect();
+    match format {
+        OutputFormat::Json => {
+            let voters : Vec<VoterResult> = names.iter().zip(&holders)
+                .map(|(name, holder)| VoterResult { voter: name.to_string(), proxy_holder: holder.map(String::from) })
+                .collect();
+            serde_json::to_string_pretty(&voters).unwrap_or_default()
+        }
+        OutputFormat::Csv => {
+            let mut lines : Vec<String> = vec![String::from("voter,proxy_holder")];
+            lines.extend(names.iter().zip(&holders).map(|(name, holder)| format!("{},{}", csv_field(name), csv_field(holder.unwrap_or_default()))));
+            lines.join("\n")
+        }
+        OutputFormat::Table => {
+            let mut lines : Vec<String> = vec![Message::VotersTitle.text(language)];
+            lines.extend(names.iter().zip(&holders).map(|(name, holder)| match holder {
+                Some(holder) => format!(" - {} ({})", name, Message::ProxyMarker(holder).text(language)),
+                None => format!(" - {}", name),
+            }));
+            lines.join("\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests 
+{
+    use std::collections::BTreeMap as Map;
+    use std::collections::BTreeSet as Set;
+
+    use crate::audit::{AuditEntry, AuditOutcome};
+    use crate::configuration::OutputFormat;
+    use crate::domain::{AttendanceSheet, Candidate, InvalidReason, Scoreboard, Voter, VoteOutcome, WithdrawalPolicy};
+    use crate::messages::Language;
+    use super::{Results, render_audit, render_voters};
+
+    fn setup_scoreboard() -> Scoreboard
+    {
+        let mut scoreboard : Scoreboard = Scoreboard::new(vec![
+            Candidate("E.Macron".to_string()),
+            Candidate("Le Pen, Marine".to_string()),
+        ]);
+        scoreboard.record(&VoteOutcome::AcceptedVote(Voter("Jean".to_string()), Candidate("E.Macron".to_string())));
+        scoreboard.record(&VoteOutcome::AcceptedVote(Voter("Paul".to_string()), Candidate("E.Macron".to_string())));
+        scoreboard.record(&VoteOutcome::AcceptedVote(Voter("Marie".to_string()), Candidate("Le Pen, Marine".to_string())));
+        scoreboard.record(&VoteOutcome::BlankVote(Voter("Luc".to_string())));
+        scoreboard
+    }
+
+    #[test]
+    fn percentages_are_computed_on_expressed_votes()
+    {
+        let results : Results = Results::from(&setup_scoreboard());
+
+        assert_eq!(results.candidates[0].percentage, 66.67);
+        assert_eq!(results.candidates[1].percentage, 33.33);
+        assert_eq!(results.turnout, 4);
+    }
+
+    #[test]
+    fn csv_output_escapes_names()
+    {
+        let mut scoreboard : Scoreboard = setup_scoreboard();
+        if let Some(profile) = scoreboard.profiles.get_mut(&Candidate("E.Macron".to_string())) {
+            profile.party = Some("Renaissance, centre".to_string());
+        }
+
+        let csv : String = Results::from(&scoreboard).render(OutputFormat::Csv, Language::Fr);
+
+        assert_eq!(csv, "kind,name,votes,percentage,party\n\
+            candidate,E.Macron,2,66.67,\"Renaissance, centre\"\n\
+            candidate,\"Le Pen, Marine\",1,33.33,\n\
+            blank,,1,25.00,\n\
+            invalid,,0,0.00,\n\
+            turnout,,4,,");
+    }
+
+    #[test]
+    fn table_follows_ballot_order_and_display_names()
+    {
+        let mut scoreboard : Scoreboard = setup_scoreboard();
+        let le_pen : Candidate = Candidate("Le Pen, Marine".to_string());
+        scoreboard.rename(&le_pen, "Marine Le Pen");
+        if let Some(profile) = scoreboard.profiles.get_mut(&le_pen) {
+            profile.ballot_order = 0;
+            profile.party = Some("RN".to_string());
+        }
+        if let Some(profile) = scoreboard.profiles.get_mut(&Candidate("E.Macron".to_string())) {
+            profile.ballot_order = 1;
+        }
+
+        let table : String = Results::from(&scoreboard).render(OutputFormat::Table, Language::Fr);
+
+        assert_eq!(table.lines().nth(1), Some(" - Marine Le Pen (RN) : 1 (33.33 %)"));
+        assert_eq!(table.lines().nth(2), Some(" - E.Macron : 2 (66.67 %)"));
+    }
+
+    #[test]
+    fn write_ins_and_withdrawals_are_listed()
+    {
+        let mut scoreboard : Scoreboard = setup_scoreboard();
+        scoreboard.withdraw(&Candidate("Le Pen, Marine".to_string()), WithdrawalPolicy::Keep);
+        scoreboard.record(&VoteOutcome::WriteInVote(Voter("Anne".to_string()), "J.Chirac".to_string()));
+
+        let table : String = Results::from(&scoreboard).render(OutputFormat::Table, Language::En);
+
+        assert_eq!(table.lines().nth(1), Some(" - E.Macron : 2 (50.00 %)"));
+        assert_eq!(table.lines().nth(2), Some(" - Le Pen, Marine [withdrawn] : 1 (25.00 %)"));
+        assert_eq!(table.lines().nth(3), Some(" - J.Chirac [write-in] : 1 (25.00 %)"));
+    }
+
+    #[test]
+    fn invalid_votes_are_broken_down_by_reason()
+    {
+        let mut scoreboard : Scoreboard = setup_scoreboard();
+        scoreboard.record(&VoteOutcome::InvalidVote(Voter("Anne".to_string()), InvalidReason::UnknownCandidate));
+        scoreboard.record(&VoteOutcome::InvalidVote(Voter("Marc".to_string()), InvalidReason::UnknownCandidate));
+        scoreboard.record(&VoteOutcome::InvalidVote(Voter("Lise".to_string()), InvalidReason::WithdrawnCandidate));
+
+        let results : Results = Results::from(&scoreboard);
+        let table : String = results.render(OutputFormat::Table, Language::Fr);
+        let csv : String = results.render(OutputFormat::Csv, Language::Fr);
+
+        assert!(table.contains(" - votes invalides : 3\n    - candidat inconnu : 2\n    - candidat retiré : 1"));
+        assert!(csv.contains("invalid,,3,42.86,\ninvalid_reason,unknown_candidate,2,28.57,\ninvalid_reason,withdrawn_candidate,1,14.29,"));
+    }
+
+    #[test]
+    fn json_output_lists_voters()
+    {
+        let voters : AttendanceSheet = AttendanceSheet { attendees: Set::from([Voter("Jean".to_string()), Voter("Paul".to_string())]), proxy_holders: Map::new() };
+
+        let json : String = render_voters(&voters, OutputFormat::Json, Language::Fr);
+
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&json).unwrap(), serde_json::json!([{ "voter": "Jean", "proxy_holder": null }, { "voter": "Paul", "proxy_holder": null }]));
+    }
+
+    #[test]
+    fn voters_signed_by_a_proxy_holder_are_marked()
+    {
+        let principal : Voter = Voter("Jean".to_string());
+        let voters : AttendanceSheet = AttendanceSheet { attendees: Set::from([principal.clone(), Voter("Paul".to_string())]), proxy_holders: Map::from([(principal, Voter("Marie".to_string()))]) };
+
+        let table : String = render_voters(&voters, OutputFormat::Table, Language::En);
+        let csv : String = render_voters(&voters, OutputFormat::Csv, Language::En);
+        let json : String = render_voters(&voters, OutputFormat::Json, Language::En);
+
+        assert!(table.contains(" - Jean (by proxy: Marie)"));
+        assert!(table.ends_with(" - Paul"));
+        assert_eq!(csv, "voter,proxy_holder\nJean,Marie\nPaul,");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&json).unwrap()[0], serde_json::json!({ "voter": "Jean", "proxy_holder": "Marie" }));
+    }
+
+    #[test]
+    fn audit_table_shows_utc_times_and_reasons()
+    {
+        let entry : AuditEntry = AuditEntry {
+            timestamp: 1_760_875_200,
+            ..AuditEntry::new("http", "vote", AuditOutcome::Denied).with_detail("Jean").with_reason("already_voted")
+        };
+
+        let table : String = render_audit(&[entry], OutputFormat::Table, Language::En);
+
+        assert_eq!(table, "Audit log:\n - 2025-10-19 12:00:00 UTC [http] vote Jean : denied (already_voted)");
+    }
+}
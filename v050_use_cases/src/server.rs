@@ -0,0 +1,357 @@
+use std::{convert::Infallible, future::Future, sync::Arc};
+
+use axum::{Json, Router, extract::{Path, State}, http::{HeaderMap, StatusCode, header::AUTHORIZATION}, response::{IntoResponse, Response, sse::{Event, KeepAlive, Sse}}, routing::{get, post}};
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, sync::{RwLock, broadcast}};
+use tokio_stream::{StreamExt, once, wrappers::BroadcastStream};
+
+use crate::{admin::{AdminAction, AdminCredential}, audit::{AuditEntry, AuditLog, AuditOutcome}, bulletin::{InclusionProof, MerkleTree, PublishedBulletin}, domain::{Candidate, Delegation, Issuance, Opening, Proxy, Receipt, Voter, VoteOutcome, Withdrawal, WithdrawalPolicy}, storage::{Storage, StorageError, StorageResult, archive::ArchiveDao, file::ScoreboardDao}, use_cases::{VoteForm, VoteEvent, VoteEvents, vote_and_publish, get_voting_machine, get_scoreboard, open, close, issue_codes, register_proxy, withdraw_candidate}};
+
+type SharedStore = Arc<RwLock<dyn Storage>>;
+
+pub const EVENTS_CAPACITY: usize = 64;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub store: SharedStore,
+    pub events: VoteEvents,
+    pub admin: Option<AdminCredential>,
+    pub audit: AuditLog,
+}
+
+impl AppState {
+    pub fn new(store: SharedStore) -> Self {
+        let (events, _) = broadcast::channel::<VoteEvent>(EVENTS_CAPACITY);
+        AppState { store, events, admin: None, audit: AuditLog::disabled() }
+    }
+
+    pub fn with_admin(self, admin: Option<AdminCredential>, audit: AuditLog) -> Self {
+        AppState { admin, audit, ..self }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ResultsResponse {
+    pub scoreboard: ScoreboardDao,
+    pub turnout: usize,
+}
+
+impl From<VoteEvent> for ResultsResponse {
+    fn from(event: VoteEvent) -> Self {
+        ResultsResponse { scoreboard: ScoreboardDao::from(event.scoreboard), turnout: event.turnout }
+    }
+}
+
+#[derive(Serialize)]
+pub struct VoteResponse {
+    pub voter: String,
+    pub outcome: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracking_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub superseded: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+#[derive(Serialize)]
+pub struct ElectionStatusResponse {
+    pub closed: bool,
+}
+
+#[derive(Serialize)]
+pub struct ClosedElectionResponse {
+    #[serde(flatten)]
+    pub scoreboard: ScoreboardDao,
+    pub bulletin_root: String,
+}
+
+#[derive(Deserialize)]
+pub struct VoterForm {
+    pub voter: String,
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize)]
+pub struct VoterCodeResponse {
+    pub voter: String,
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct WithdrawForm {
+    pub candidate: String,
+    #[serde(default)]
+    pub policy: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WithdrawResponse {
+    pub candidate: String,
+    pub outcome: String,
+}
+
+#[derive(Deserialize)]
+pub struct ProxyForm {
+    pub principal: String,
+    pub holder: String,
+    #[serde(default)]
+    pub valid_from: u64,
+    pub valid_until: u64,
+}
+
+#[derive(Serialize)]
+pub struct ProxyResponse {
+    pub principal: String,
+    pub holder: String,
+    pub outcome: String,
+}
+
+impl From<Withdrawal> for WithdrawResponse {
+    fn from(withdrawal: Withdrawal) -> Self {
+        let (candidate, label) : (Candidate, &str) = match withdrawal {
+            Withdrawal::Withdrawn(candidate) => (candidate, "withdrawn"),
+            Withdrawal::UnknownCandidate(candidate) => (candidate, "unknown_candidate"),
+            Withdrawal::AlreadyWithdrawn(candidate) => (candidate, "already_withdrawn"),
+        };
+        WithdrawResponse { candidate: candidate.0, outcome: label.to_string() }
+    }
+}
+
+fn error_response(status: StatusCode, error: &str) -> Response {
+    (status, Json(ErrorResponse { error: error.to_string() })).into_response()
+}
+
+impl From<Receipt> for VoteResponse {
+    fn from(receipt: Receipt) -> Self {
+        let Receipt { outcome, tracking_code, superseded } = receipt;
+        let reason : Option<String> = match &outcome {
+            VoteOutcome::InvalidVote(_, reason) => Some(reason.code().to_string()),
+            _ => None,
+        };
+        VoteResponse { voter: outcome.voter().0.clone(), outcome: outcome.code().to_string(), reason, tracking_code: tracking_code.map(|tracking_code| tracking_code.0), superseded: superseded.map(|tracking_code| tracking_code.0) }
+    }
+}
+
+impl IntoResponse for StorageError {
+    fn into_response(self) -> Response {
+        let status : StatusCode = match self {
+            StorageError::Conflict(_) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(ErrorResponse { error: self.to_string() })).into_response()
+    }
+}
+
+async fn post_vote(State(state): State<AppState>, Json(vote_form): Json<VoteForm>) -> Result<(StatusCode, Json<VoteResponse>), Response> {
+    let voter : Voter = Voter(vote_form.voter.clone());
+    let result : StorageResult<Receipt> = vote_and_publish(state.store, &state.events, vote_form).await;
+    if let Err(error) = state.audit.record(&AuditEntry::vote("http", &voter, &result)) {
+        return Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("the vote was processed but cannot be written to the audit log: {}", error)));
+    }
+    let receipt : Receipt = result.map_err(IntoResponse::into_response)?;
+    let status : StatusCode = match receipt.outcome {
+        VoteOutcome::HasAlreadyVoted(_) => StatusCode::CONFLICT,
+        VoteOutcome::NotYetOpen(_) | VoteOutcome::ElectionClosed(_) => StatusCode::FORBIDDEN,
+        VoteOutcome::MissingCode(_) | VoteOutcome::WrongCode(_) => StatusCode::UNAUTHORIZED,
+        VoteOutcome::InvalidProxy(_, _) => StatusCode::FORBIDDEN,
+        _ => StatusCode::OK,
+    };
+    Ok((status, Json(VoteResponse::from(receipt))))
+}
+
+async fn get_voters(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    run_audited(&state, &headers, AuditEntry::new("http", "voters", AuditOutcome::Succeeded), || async {
+        let mut machine = get_voting_machine(state.store.clone()).await?;
+        Ok(machine.get_voters().attendees.iter().map(|voter| voter.0.clone()).collect::<Vec<String>>())
+    }).await
+}
+
+async fn get_scores(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    run_audited(&state, &headers, AuditEntry::new("http", "scores", AuditOutcome::Succeeded), || async {
+        Ok(ScoreboardDao::from(get_scoreboard(state.store.clone()).await?))
+    }).await
+}
+
+const BULLETIN_NOT_PUBLISHED: &str = "ballots are published once the election is closed";
+
+async fn get_bulletin(State(state): State<AppState>) -> Result<Json<PublishedBulletin>, Response> {
+    let machine = get_voting_machine(state.store).await.map_err(IntoResponse::into_response)?;
+    if !machine.closed {
+        return Err(error_response(StatusCode::CONFLICT, BULLETIN_NOT_PUBLISHED));
+    }
+    Ok(Json(PublishedBulletin::of(&machine)))
+}
+
+async fn get_proof(State(state): State<AppState>, Path(tracking_code): Path<String>) -> Result<Json<InclusionProof>, Response> {
+    let machine = get_voting_machine(state.store).await.map_err(IntoResponse::into_response)?;
+    if !machine.closed {
+        return Err(error_response(StatusCode::CONFLICT, BULLETIN_NOT_PUBLISHED));
+    }
+    MerkleTree::of(&machine.ballot_box)
+        .proof(&tracking_code)
+        .map(Json)
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, &format!("no ballot matches the tracking code {}", tracking_code)))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers.get(AUTHORIZATION)?.to_str().ok()?.strip_prefix("Bearer ")
+}
+
+async fn admin_refusal(state: &AppState, headers: &HeaderMap, entry: &AuditEntry) -> Option<Response> {
+    let Some(credential) = &state.admin else {
+        return Some(error_response(StatusCode::FORBIDDEN, "the admin API is disabled, start the server with an admin token"));
+    };
+    let machine = match get_voting_machine(state.store.clone()).await {
+        Ok(machine) => machine,
+        Err(error) => return Some(error.into_response()),
+    };
+    let token : Option<&str> = bearer_token(headers);
+    if token.is_some_and(|token| credential.verify(token)) && machine.authorizes(token) {
+        return None;
+    }
+    Some(match state.audit.record(&AuditEntry { outcome: AuditOutcome::Denied, ..entry.clone() }) {
+        Ok(()) => error_response(StatusCode::UNAUTHORIZED, "missing or wrong admin token"),
+        Err(error) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("cannot write to the audit log: {}", error)),
+    })
+}
+
+async fn run_audited<T, F, Fut>(state: &AppState, headers: &HeaderMap, entry: AuditEntry, operation: F) -> Response
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = StorageResult<T>>,
+{
+    if let Some(refusal) = admin_refusal(state, headers, &entry).await {
+        return refusal;
+    }
+
+    let result : StorageResult<T> = operation().await;
+    if let Err(error) = state.audit.record(&entry.with_result(&result)) {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("cannot write to the audit log: {}", error));
+    }
+    match result {
+        Ok(value) => Json(value).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn run_admin<T, F, Fut>(state: &AppState, headers: &HeaderMap, action: AdminAction, operation: F) -> Response
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = StorageResult<T>>,
+{
+    run_audited(state, headers, AuditEntry::admin("http", &action, AuditOutcome::Succeeded), operation).await
+}
+
+async fn admin_open(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    run_admin(&state, &headers, AdminAction::Open, || async {
+        match open(state.store.clone()).await? {
+            Opening::Opened => Ok(ElectionStatusResponse { closed: false }),
+            Opening::AlreadyOpen => Err(StorageError::Conflict("the election is already open".to_string())),
+            Opening::Closed => Err(StorageError::Conflict("the election is closed and cannot be reopened".to_string())),
+        }
+    }).await
+}
+
+async fn admin_close(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    run_admin(&state, &headers, AdminAction::Close, || async {
+        let machine = close(state.store.clone()).await?;
+        Ok(ClosedElectionResponse { bulletin_root: MerkleTree::of(&machine.ballot_box).root(), scoreboard: ScoreboardDao::from(machine.scoreboard) })
+    }).await
+}
+
+async fn admin_add_voter(State(state): State<AppState>, headers: HeaderMap, Json(form): Json<VoterForm>) -> Response {
+    let voter : Voter = Voter(form.voter);
+    run_admin(&state, &headers, AdminAction::AddVoter(voter.clone()), || async {
+        let codes : Vec<(Voter, String)> = match issue_codes(state.store.clone(), vec![voter.clone()], form.force).await? {
+            Issuance::Issued(codes) => codes,
+            Issuance::AlreadyIssued(_) => return Err(StorageError::Conflict(format!("{} already holds a voting code", voter.0))),
+        };
+        codes.into_iter()
+            .next()
+            .map(|(voter, code)| VoterCodeResponse { voter: voter.0, code })
+            .ok_or_else(|| StorageError::Conflict(format!("{} has already voted", voter.0)))
+    }).await
+}
+
+async fn admin_withdraw(State(state): State<AppState>, headers: HeaderMap, Json(form): Json<WithdrawForm>) -> Response {
+    let policy : WithdrawalPolicy = match form.policy.as_deref().unwrap_or("keep").parse() {
+        Ok(policy) => policy,
+        Err(error) => return error_response(StatusCode::UNPROCESSABLE_ENTITY, &error),
+    };
+    let candidate : Candidate = Candidate(form.candidate);
+    run_admin(&state, &headers, AdminAction::Withdraw(candidate.clone(), policy), || async {
+        Ok(WithdrawResponse::from(withdraw_candidate(state.store.clone(), candidate.clone(), policy).await?))
+    }).await
+}
+
+async fn admin_register_proxy(State(state): State<AppState>, headers: HeaderMap, Json(form): Json<ProxyForm>) -> Response {
+    let principal : Voter = Voter(form.principal);
+    let proxy : Proxy = Proxy { holder: Voter(form.holder), valid_from: form.valid_from, valid_until: form.valid_until };
+    run_admin(&state, &headers, AdminAction::RegisterProxy(principal.clone(), proxy.holder.clone()), || async {
+        let delegation : Delegation = register_proxy(state.store.clone(), principal.clone(), proxy.clone()).await?;
+        Ok(ProxyResponse { principal: principal.0.clone(), holder: proxy.holder.0.clone(), outcome: delegation.code().to_string() })
+    }).await
+}
+
+async fn admin_export(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    run_admin(&state, &headers, AdminAction::Export("http".to_string()), || async {
+        ArchiveDao::new(get_voting_machine(state.store.clone()).await?)
+    }).await
+}
+
+fn results_event(event: VoteEvent) -> Result<Event, Infallible> {
+    let data : String = serde_json::to_string(&ResultsResponse::from(event)).unwrap_or_default();
+    Ok(Event::default().event("results").data(data))
+}
+
+async fn stream_results(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let entry : AuditEntry = AuditEntry::new("http", "results_stream", AuditOutcome::Succeeded);
+    if let Some(refusal) = admin_refusal(&state, &headers, &entry).await {
+        return refusal;
+    }
+
+    let receiver = state.events.subscribe();
+    let current : StorageResult<VoteEvent> = get_scoreboard(state.store.clone()).await.map(VoteEvent::from);
+    if let Err(error) = state.audit.record(&entry.with_result(&current)) {
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("cannot write to the audit log: {}", error));
+    }
+    let current : VoteEvent = match current {
+        Ok(current) => current,
+        Err(error) => return error.into_response(),
+    };
+
+    let updates = BroadcastStream::new(receiver).filter_map(|event| event.ok());
+    let stream = once(current).chain(updates).map(results_event);
+
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/vote", post(post_vote))
+        .route("/voters", get(get_voters))
+        .route("/scores", get(get_scores))
+        .route("/results/stream", get(stream_results))
+        .route("/bulletin", get(get_bulletin))
+        .route("/bulletin/:tracking_code", get(get_proof))
+        .route("/admin/open", post(admin_open))
+        .route("/admin/close", post(admin_close))
+        .route("/admin/voters", post(admin_add_voter))
+        .route("/admin/withdraw", post(admin_withdraw))
+        .route("/admin/proxies", post(admin_register_proxy))
+        .route("/admin/export", get(admin_export))
+        .with_state(state)
+}
+
+pub async fn serve(listener: TcpListener, state: AppState) -> std::io::Result<()> {
+    axum::serve(listener, router(state)).await
+}
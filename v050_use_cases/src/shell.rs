@@ -0,0 +1,184 @@
+use crate::domain::WithdrawalPolicy;
+use crate::messages::{Keyword, Language};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShellCommand {
+    Empty,
+    Help,
+    Quit,
+    Vote { voter: String, candidate: Option<String>, code: Option<String>, on_behalf_of: Option<String> },
+    Voters,
+    Scores,
+    Backup { filepath: String },
+    Admin { passphrase: String },
+    Logout,
+    Open,
+    Close,
+    AddVoter { voter: String },
+    Withdraw { candidate: String, policy: WithdrawalPolicy },
+}
+
+impl ShellCommand {
+    pub fn is_privileged(&self) -> bool {
+        matches!(self,
+            ShellCommand::Voters | ShellCommand::Scores | ShellCommand::Backup { .. }
+            | ShellCommand::Open | ShellCommand::Close | ShellCommand::AddVoter { .. } | ShellCommand::Withdraw { .. })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    UnterminatedQuote,
+    UnknownCommand(String),
+    Usage(Keyword),
+}
+
+pub fn tokenize(line: &str) -> Result<Vec<String>, ParseError> {
+    let mut tokens : Vec<String> = Vec::new();
+    let mut current : Option<String> = None;
+    let mut characters = line.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '"' | '\'' => {
+                let token : &mut String = current.get_or_insert_with(String::new);
+                loop {
+                    match characters.next() {
+                        Some(next) if next == character => break,
+                        Some('\\') if character == '"' => match characters.next() {
+                            Some(escaped) => token.push(escaped),
+                            None => return Err(ParseError::UnterminatedQuote),
+                        },
+                        Some(next) => token.push(next),
+                        None => return Err(ParseError::UnterminatedQuote),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(escaped) = characters.next() {
+                    current.get_or_insert_with(String::new).push(escaped);
+                }
+            }
+            character if character.is_whitespace() => {
+                if let Some(token) = current.take() {
+                    tokens.push(token);
+                }
+            }
+            character => current.get_or_insert_with(String::new).push(character),
+        }
+    }
+
+    if let Some(token) = current {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+pub fn parse(line: &str, language: Language) -> Result<ShellCommand, ParseError> {
+    let tokens : Vec<String> = tokenize(line)?;
+    let Some((word, args)) = tokens.split_first() else {
+        return Ok(ShellCommand::Empty);
+    };
+
+    let keyword : Keyword = Keyword::parse(word, language).ok_or_else(|| ParseError::UnknownCommand(word.clone()))?;
+
+    match (keyword, args) {
+        (Keyword::Help, []) => Ok(ShellCommand::Help),
+        (Keyword::Quit, []) => Ok(ShellCommand::Quit),
+        (Keyword::Voters, []) => Ok(ShellCommand::Voters),
+        (Keyword::Scores, []) => Ok(ShellCommand::Scores),
+        (Keyword::Vote, [voter]) => Ok(ShellCommand::Vote { voter: voter.clone(), candidate: None, code: None, on_behalf_of: None }),
+        (Keyword::Vote, [voter, candidate]) => Ok(ShellCommand::Vote { voter: voter.clone(), candidate: Some(candidate.clone()), code: None, on_behalf_of: None }),
+        (Keyword::Vote, [voter, candidate, code]) => Ok(ShellCommand::Vote { voter: voter.clone(), candidate: Some(candidate.clone()), code: Some(code.clone()), on_behalf_of: None }),
+        (Keyword::Vote, [voter, candidate, code, principal]) => Ok(ShellCommand::Vote { voter: voter.clone(), candidate: Some(candidate.clone()), code: Some(code.clone()).filter(|code| !code.is_empty()), on_behalf_of: Some(principal.clone()) }),
+        (Keyword::Backup, [filepath]) => Ok(ShellCommand::Backup { filepath: filepath.clone() }),
+        (Keyword::Admin, [passphrase]) => Ok(ShellCommand::Admin { passphrase: passphrase.clone() }),
+        (Keyword::Logout, []) => Ok(ShellCommand::Logout),
+        (Keyword::Open, []) => Ok(ShellCommand::Open),
+        (Keyword::Close, []) => Ok(ShellCommand::Close),
+        (Keyword::AddVoter, [voter]) => Ok(ShellCommand::AddVoter { voter: voter.clone() }),
+        (Keyword::Withdraw, [candidate]) => Ok(ShellCommand::Withdraw { candidate: candidate.clone(), policy: WithdrawalPolicy::Keep }),
+        (Keyword::Withdraw, [candidate, policy]) => match policy.parse::<WithdrawalPolicy>() {
+            Ok(policy) => Ok(ShellCommand::Withdraw { candidate: candidate.clone(), policy }),
+            Err(_) => Err(ParseError::Usage(Keyword::Withdraw)),
+        },
+        (keyword, _) => Err(ParseError::Usage(keyword)),
+    }
+}
+
+#[cfg(test)]
+mod tests 
+{
+    use crate::domain::WithdrawalPolicy;
+    use crate::messages::{Keyword, Language};
+    use super::{ParseError, ShellCommand, parse, tokenize};
+
+    #[test]
+    fn quoted_arguments_keep_spaces()
+    {
+        let command = parse("voter \"Jean Dupont\" 'Marine Le Pen'", Language::Fr);
+
+        assert_eq!(command, Ok(ShellCommand::Vote { voter: "Jean Dupont".to_string(), candidate: Some("Marine Le Pen".to_string()), code: None, on_behalf_of: None }));
+    }
+
+    #[test]
+    fn voting_code_follows_the_candidate()
+    {
+        let command = parse("voter Jean \"\" ABCD-2345", Language::Fr);
+
+        assert_eq!(command, Ok(ShellCommand::Vote { voter: "Jean".to_string(), candidate: Some(String::new()), code: Some("ABCD-2345".to_string()), on_behalf_of: None }));
+    }
+
+    #[test]
+    fn proxy_holder_names_the_principal_last()
+    {
+        let with_code = parse("vote Marie E.Macron ABCD-2345 Jean", Language::En);
+        let without_code = parse("voter Marie E.Macron \"\" Jean", Language::Fr);
+
+        assert_eq!(with_code, Ok(ShellCommand::Vote { voter: "Marie".to_string(), candidate: Some("E.Macron".to_string()), code: Some("ABCD-2345".to_string()), on_behalf_of: Some("Jean".to_string()) }));
+        assert_eq!(without_code, Ok(ShellCommand::Vote { voter: "Marie".to_string(), candidate: Some("E.Macron".to_string()), code: None, on_behalf_of: Some("Jean".to_string()) }));
+    }
+
+    #[test]
+    fn escaped_quotes_are_kept()
+    {
+        assert_eq!(tokenize(r#"vote "Jean \"JJ\" Dupont" L\'Autre"#), Ok(vec!["vote".to_string(), "Jean \"JJ\" Dupont".to_string(), "L'Autre".to_string()]));
+    }
+
+    #[test]
+    fn unterminated_quote_is_rejected()
+    {
+        assert_eq!(parse("voter \"Jean", Language::Fr), Err(ParseError::UnterminatedQuote));
+    }
+
+    #[test]
+    fn blank_line_is_empty()
+    {
+        assert_eq!(parse("   \n", Language::Fr), Ok(ShellCommand::Empty));
+    }
+
+    #[test]
+    fn wrong_arity_reports_usage()
+    {
+        assert_eq!(parse("voter", Language::Fr), Err(ParseError::Usage(Keyword::Vote)));
+        assert_eq!(parse("scores tous", Language::Fr), Err(ParseError::Usage(Keyword::Scores)));
+        assert_eq!(parse("vote a b c d e", Language::En), Err(ParseError::Usage(Keyword::Vote)));
+    }
+
+    #[test]
+    fn admin_commands_are_parsed()
+    {
+        assert_eq!(parse("admin \"phrase secrète\"", Language::Fr), Ok(ShellCommand::Admin { passphrase: "phrase secrète".to_string() }));
+        assert_eq!(parse("inscrire Jean", Language::Fr), Ok(ShellCommand::AddVoter { voter: "Jean".to_string() }));
+        assert_eq!(parse("withdraw M.Lepen invalidate", Language::En), Ok(ShellCommand::Withdraw { candidate: "M.Lepen".to_string(), policy: WithdrawalPolicy::Invalidate }));
+        assert_eq!(parse("retirer M.Lepen partout", Language::Fr), Err(ParseError::Usage(Keyword::Withdraw)));
+        assert_eq!(parse("clore", Language::Fr), Ok(ShellCommand::Close));
+    }
+
+    #[test]
+    fn unknown_command_is_reported()
+    {
+        assert_eq!(parse("vote Jean", Language::Fr), Err(ParseError::UnknownCommand("vote".to_string())));
+        assert_eq!(parse("quit", Language::En), Ok(ShellCommand::Quit));
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Serialize, Deserialize};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, AsyncReadExt};
+
+use crate::domain::{ElectionSecret, VotingMachine};
+use crate::storage::{StorageError, StorageResult, file::{VotingMachineDao, load_secret, open_file, secret_path, write_secret}};
+
+pub const ARCHIVE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct ArchiveDao {
+    pub version: u32,
+    pub created_at: u64,
+    pub candidates: Vec<String>,
+    pub checksum: String,
+    pub machine: VotingMachineDao,
+}
+
+fn checksum(secret: &ElectionSecret, machine_dao: &VotingMachineDao) -> StorageResult<String> {
+    let serialized_machine : Vec<u8> = serde_json::to_vec(machine_dao)?;
+    Ok(secret.checksum(&serialized_machine))
+}
+
+impl ArchiveDao {
+    pub fn new(machine: VotingMachine) -> StorageResult<Self> {
+        let secret : ElectionSecret = machine.secret.clone();
+        let machine_dao : VotingMachineDao = VotingMachineDao::from(machine);
+        let created_at : u64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        Ok(ArchiveDao {
+            version: ARCHIVE_VERSION,
+            created_at,
+            candidates: machine_dao.scoreboard.scores.keys().cloned().collect(),
+            checksum: checksum(&secret, &machine_dao)?,
+            machine: machine_dao,
+        })
+    }
+
+    pub fn verify(&self, secret: &ElectionSecret) -> StorageResult<()> {
+        if self.version != ARCHIVE_VERSION {
+            return Err(StorageError::Corrupted(format!("unsupported archive version {}", self.version)));
+        }
+
+        if self.checksum != checksum(secret, &self.machine)? {
+            return Err(StorageError::Corrupted("archive checksum mismatch".to_string()));
+        }
+
+        let candidates : Vec<&String> = self.machine.scoreboard.scores.keys().collect();
+        if candidates != self.candidates.iter().collect::<Vec<&String>>() {
+            return Err(StorageError::Corrupted("archive candidates do not match the stored scoreboard".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn export_archive(machine: VotingMachine, filepath: &str) -> StorageResult<()> {
+    write_secret(&machine.secret, &secret_path(filepath)).await?;
+    let archive : ArchiveDao = ArchiveDao::new(machine)?;
+    let serialized_archive : String = serde_json::to_string_pretty(&archive)?;
+
+    let mut file : File = File::create(filepath).await?;
+    file.write_all(serialized_archive.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+pub async fn import_archive(filepath: &str) -> StorageResult<VotingMachine> {
+    let mut file : File = open_file(filepath).await?;
+    let mut content : Vec<u8> = vec![];
+    file.read_to_end(&mut content).await?;
+
+    let archive : ArchiveDao = serde_json::from_slice::<ArchiveDao>(&content)?;
+    let secret : ElectionSecret = load_secret(&secret_path(filepath)).await?;
+    archive.verify(&secret)?;
+
+    let mut machine : VotingMachine = VotingMachine::try_from(archive.machine)?;
+    machine.secret = secret;
+    Ok(machine)
+}
+
+#[cfg(test)]
+mod tests 
+{
+    use std::fs;
+
+    use crate::domain::{VotingMachine, Candidate, ElectionSecret, Voter};
+    use crate::storage::file::secret_path;
+    use super::{ArchiveDao, export_archive, import_archive};
+
+    fn setup_voting_machine() -> VotingMachine
+    {
+        let candidates : Vec<Candidate> = vec![
+            Candidate("E.Macron".to_string()),
+            Candidate("M.Lepen".to_string()),
+            Candidate("JL.Mélanchon".to_string()),
+        ];
+        let mut machine : VotingMachine = VotingMachine::new(candidates);
+        machine.get_voters().attendees.insert(Voter("Jean".to_string()));
+        machine.get_scoreboard().scores.entry(Candidate("E.Macron".to_string())).and_modify(|score| score.0 += 1);
+        machine
+    }
+
+    #[tokio::test]
+    async fn export_then_import_restores_machine() -> anyhow::Result<()> 
+    {
+        let machine : VotingMachine = setup_voting_machine();
+        let filepath : String = std::env::temp_dir().join("archive_roundtrip.json").display().to_string();
+
+        export_archive(machine.clone(), &filepath).await?;
+        let restored_machine : VotingMachine = import_archive(&filepath).await?;
+        #[cfg(unix)]
+        let secret_mode : u32 = std::os::unix::fs::PermissionsExt::mode(&fs::metadata(secret_path(&filepath))?.permissions()) & 0o777;
+
+        fs::remove_file(&filepath)?;
+        let _ = fs::remove_file(secret_path(&filepath));
+
+        assert_eq!(restored_machine, machine);
+        #[cfg(unix)]
+        assert_eq!(secret_mode, 0o600);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tampered_archive_is_rejected() -> anyhow::Result<()> 
+    {
+        let machine : VotingMachine = setup_voting_machine();
+        let mut archive : ArchiveDao = ArchiveDao::new(machine.clone())?;
+        assert!(archive.verify(&machine.secret).is_ok());
+
+        archive.machine.scoreboard.blank_scores += 10;
+        assert!(archive.verify(&machine.secret).is_err());
+
+        let mut forger : VotingMachine = machine.clone();
+        forger.secret = ElectionSecret([7; 32]);
+        forger.get_scoreboard().blank_scores.0 += 10;
+        let forged : ArchiveDao = ArchiveDao::new(forger)?;
+        assert!(forged.verify(&machine.secret).is_err());
+        Ok(())
+    }
+}
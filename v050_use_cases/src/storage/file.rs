@@ -1,25 +1,178 @@
+use std::io::Write;
 use std::path::Path;
 use std::sync::{Arc, RwLock};
-use crate::domain::{VotingMachine, Scoreboard, Candidate, Score, Voter, AttendanceSheet};
-use crate::storage::Storage;
-use anyhow::{Result, Ok};
+use crate::domain::{VotingMachine, Scoreboard, Candidate, CandidateProfile, CodeHash, ElectionSecret, InvalidReason, Proxy, ProxyRegister, RevotePolicy, Schedule, Score, Voter, VoterRoll, VoterToken, AttendanceSheet, Ballot, BallotBox, EncryptedBallot, TrackingCode, VoteOutcome};
+use crate::elgamal::{Ciphertext, PublicKey, ThresholdKey};
+use crate::trustees::PartialDecryption;
+use crate::storage::{Storage, StorageError, StorageResult, Revision, VersionedMachine};
 use serde::{Serialize, Deserialize};
-use tokio::fs::File;
+use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncWriteExt, AsyncReadExt};
 use std::collections::BTreeMap as Map;
 use std::collections::BTreeSet as Set;
 
+#[derive(Serialize, Deserialize)]
+pub struct CandidateDao {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub party: Option<String>,
+    pub ballot_order: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub withdrawn: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ScoreboardDao {
     pub scores: Map<String, usize>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub candidates: Map<String, CandidateDao>,
     pub blank_scores: usize,
     pub invalid_score: usize,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub invalid_reasons: Map<String, usize>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub write_in: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub write_in_scores: Map<String, usize>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub sealed_scores: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub election_key: Option<PublicKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold_key: Option<ThresholdKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_proxies: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleDao>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScheduleDao {
+    pub opens_at: u64,
+    pub closes_at: u64,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+impl From<CandidateProfile> for CandidateDao {
+    fn from(profile: CandidateProfile) -> Self {
+        CandidateDao {
+            name: profile.name,
+            party: profile.party,
+            ballot_order: profile.ballot_order,
+            description: profile.description,
+            withdrawn: profile.withdrawn,
+        }
+    }
+}
+
+impl From<CandidateDao> for CandidateProfile {
+    fn from(candidate_dao: CandidateDao) -> Self {
+        CandidateProfile {
+            name: candidate_dao.name,
+            party: candidate_dao.party,
+            ballot_order: candidate_dao.ballot_order,
+            description: candidate_dao.description,
+            withdrawn: candidate_dao.withdrawn,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct VotingMachineDao {
     pub voters: Set<String>,
     pub scoreboard: ScoreboardDao,
+    #[serde(default)]
+    pub revision: u64,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll: Option<Map<String, String>>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub ballot_box: Map<String, BallotDao>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub partial_decryptions: Map<u64, PartialDecryption>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub ballot_tokens: Map<String, String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub proxies: Map<String, ProxyDao>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub proxy_holders: Map<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decryption: Option<PartialDecryption>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProxyDao {
+    pub holder: String,
+    pub valid_from: u64,
+    pub valid_until: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum BallotDao {
+    Candidate(String),
+    WriteIn(String),
+    Blank,
+    Invalid,
+    Rejected(String),
+    Sealed(Map<String, Ciphertext>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum JournalRecordDao {
+    Attendance {
+        voter: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        holder: Option<String>,
+    },
+    Ballot {
+        ballot: BallotDao,
+        tracking_code: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+}
+
+impl From<Ballot> for BallotDao {
+    fn from(ballot: Ballot) -> Self {
+        match ballot {
+            Ballot::Candidate(candidate) => BallotDao::Candidate(candidate.0),
+            Ballot::WriteIn(name) => BallotDao::WriteIn(name),
+            Ballot::Blank => BallotDao::Blank,
+            Ballot::Invalid(reason) => BallotDao::Rejected(reason.code().to_string()),
+            Ballot::Sealed(sealed) => BallotDao::Sealed(sealed.0),
+        }
+    }
+}
+
+impl TryFrom<BallotDao> for Ballot {
+    type Error = StorageError;
+
+    fn try_from(ballot_dao: BallotDao) -> StorageResult<Self> {
+        Ok(match ballot_dao {
+            BallotDao::Candidate(candidate) => Ballot::Candidate(Candidate(candidate)),
+            BallotDao::WriteIn(name) => Ballot::WriteIn(name),
+            BallotDao::Blank => Ballot::Blank,
+            BallotDao::Invalid => Ballot::Invalid(InvalidReason::UnknownCandidate),
+            BallotDao::Rejected(code) => Ballot::Invalid(InvalidReason::from_code(&code)
+                .ok_or_else(|| StorageError::Corrupted(format!("unknown invalid vote reason {}", code)))?),
+            BallotDao::Sealed(sealed) => Ballot::Sealed(EncryptedBallot(sealed)),
+        })
+    }
 }
 
 impl From<Scoreboard> for ScoreboardDao {
@@ -29,109 +182,436 @@ impl From<Scoreboard> for ScoreboardDao {
             .map(|(candidate, score)| (candidate.0, score.0))
             .collect();
 
+        let candidates: Map<String, CandidateDao> = scoreboard.profiles
+            .into_iter()
+            .map(|(candidate, profile)| (candidate.0, CandidateDao::from(profile)))
+            .collect();
+
         ScoreboardDao { 
             scores, 
+            candidates,
             blank_scores: scoreboard.blank_scores.0, 
             invalid_score: scoreboard.invalid_scores.0,
+            invalid_reasons: scoreboard.invalid_reasons.into_iter().map(|(reason, score)| (reason.code().to_string(), score.0)).collect(),
+            write_in: scoreboard.write_in,
+            write_in_scores: scoreboard.write_in_scores.into_iter().map(|(name, score)| (name, score.0)).collect(),
+            sealed_scores: scoreboard.sealed_scores.0,
+            election_key: scoreboard.election_key,
+            threshold_key: scoreboard.threshold_key,
+            revote: (scoreboard.revote != RevotePolicy::Reject).then(|| scoreboard.revote.code().to_string()),
+            max_proxies: (scoreboard.max_proxies != 1).then_some(scoreboard.max_proxies),
+            schedule: scoreboard.schedule.map(|schedule| ScheduleDao { opens_at: schedule.opens_at, closes_at: schedule.closes_at }),
         }
     }
 }
 
-impl From<VotingMachineDao> for VotingMachine {
-    fn from(voting_machine_dao: VotingMachineDao) -> Self {
+impl TryFrom<VotingMachineDao> for VotingMachine {
+    type Error = StorageError;
+
+    fn try_from(voting_machine_dao: VotingMachineDao) -> StorageResult<Self> {
 
         let voters: Set<Voter> = voting_machine_dao.voters
             .iter()
-            .map(|(voter)| (Voter(voter.clone())))
+            .map(|voter| Voter(voter.clone()))
+            .collect();
+        let proxy_holders: Map<Voter, Voter> = voting_machine_dao.proxy_holders
+            .iter()
+            .map(|(voter, holder)| (Voter(voter.clone()), Voter(holder.clone())))
             .collect();
 
-        VotingMachine {
-            voters: AttendanceSheet(voters),
-            scoreboard: Scoreboard::from(voting_machine_dao.scoreboard),
-        }
+        let mut machine : VotingMachine = VotingMachine::recover_from(AttendanceSheet { attendees: voters, proxy_holders }, Scoreboard::try_from(voting_machine_dao.scoreboard)?, voting_machine_dao.closed);
+        machine.roll = voting_machine_dao.roll.map(|roll| VoterRoll(roll
+            .into_iter()
+            .map(|(voter, code_hash)| (Voter(voter), CodeHash(code_hash)))
+            .collect()));
+        machine.ballot_box = BallotBox(voting_machine_dao.ballot_box
+            .into_iter()
+            .map(|(tracking_code, ballot)| Ok((TrackingCode(tracking_code), Ballot::try_from(ballot)?)))
+            .collect::<StorageResult<_>>()?);
+        machine.partial_decryptions = voting_machine_dao.partial_decryptions;
+        machine.ballot_tokens = voting_machine_dao.ballot_tokens
+            .into_iter()
+            .map(|(token, tracking_code)| (VoterToken(token), TrackingCode(tracking_code)))
+            .collect();
+        machine.proxies = ProxyRegister(voting_machine_dao.proxies
+            .into_iter()
+            .map(|(principal, proxy)| (Voter(principal), Proxy { holder: Voter(proxy.holder), valid_from: proxy.valid_from, valid_until: proxy.valid_until }))
+            .collect());
+        machine.admin = voting_machine_dao.admin.map(CodeHash);
+        machine.decryption = voting_machine_dao.decryption;
+        Ok(machine)
     }
 }
 
 impl From<VotingMachine> for VotingMachineDao {
     fn from(voting_machine: VotingMachine) -> Self {
 
-        let voters: Set<String> = voting_machine.voters.0
+        let voters: Set<String> = voting_machine.voters.attendees
             .iter()
-            .map(|voter| (voter.0.clone()))
+            .map(|voter| voter.0.clone())
+            .collect();
+        let proxy_holders: Map<String, String> = voting_machine.voters.proxy_holders
+            .iter()
+            .map(|(voter, holder)| (voter.0.clone(), holder.0.clone()))
             .collect();
 
         VotingMachineDao {
             voters, 
             scoreboard: ScoreboardDao::from(voting_machine.scoreboard), 
+            revision: 0,
+            closed: voting_machine.closed,
+            roll: voting_machine.roll.map(|roll| roll.0
+                .into_iter()
+                .map(|(voter, code_hash)| (voter.0, code_hash.0))
+                .collect()),
+            ballot_box: voting_machine.ballot_box.0
+                .into_iter()
+                .map(|(tracking_code, ballot)| (tracking_code.0, BallotDao::from(ballot)))
+                .collect(),
+            partial_decryptions: voting_machine.partial_decryptions,
+            ballot_tokens: voting_machine.ballot_tokens
+                .into_iter()
+                .map(|(token, tracking_code)| (token.0, tracking_code.0))
+                .collect(),
+            proxies: voting_machine.proxies.0
+                .into_iter()
+                .map(|(principal, proxy)| (principal.0, ProxyDao { holder: proxy.holder.0, valid_from: proxy.valid_from, valid_until: proxy.valid_until }))
+                .collect(),
+            proxy_holders,
+            admin: voting_machine.admin.map(|credential| credential.0),
+            decryption: voting_machine.decryption,
+        }
+    }
+}
+
+impl JournalRecordDao {
+    pub fn attendance(voter: &Voter, holder: Option<&Voter>) -> Self {
+        JournalRecordDao::Attendance { voter: voter.0.clone(), holder: holder.map(|holder| holder.0.clone()) }
+    }
+
+    pub fn ballot(ballot: &Ballot, token: Option<&VoterToken>, tracking_code: &TrackingCode) -> Self {
+        JournalRecordDao::Ballot { ballot: BallotDao::from(ballot.clone()), tracking_code: tracking_code.0.clone(), token: token.map(|token| token.0.clone()) }
+    }
+
+    pub fn replay(self, machine: &mut VotingMachine) -> StorageResult<()> {
+        match self {
+            JournalRecordDao::Attendance { voter, holder } => machine.attend(&Voter(voter), holder.map(Voter)),
+            JournalRecordDao::Ballot { ballot, tracking_code, token } => machine.file_ballot(Ballot::try_from(ballot)?, token.map(VoterToken), TrackingCode(tracking_code)),
         }
+        Ok(())
     }
 }
 
 pub struct FileStore {
-    filepath: Arc<RwLock<String>>
+    filepath: String,
+    journal_path: String,
+    ballots_path: String,
+    state: Arc<RwLock<VersionedMachine>>,
 }
 
-#[async_trait::async_trait]
-impl Storage for FileStore {
-    async fn get_voting_machine(&self) -> Result<VotingMachine> {
+impl FileStore {
+    fn read_state(&self) -> StorageResult<std::sync::RwLockReadGuard<'_, VersionedMachine>> {
+        self.state.read().map_err(|_| StorageError::LockPoisoned)
+    }
 
-        let filepath = self.filepath.read().unwrap().clone();
-        let mut my_file = File::open(filepath).await?;
-        
-        let mut my_slice = vec![];
-        my_file.read_to_end(&mut my_slice).await?;
+    fn write_state(&self) -> StorageResult<std::sync::RwLockWriteGuard<'_, VersionedMachine>> {
+        self.state.write().map_err(|_| StorageError::LockPoisoned)
+    }
 
-        let voting_machine_dao: VotingMachineDao = serde_json::from_slice::<VotingMachineDao>(&my_slice)?;
+    async fn write_snapshot(&self, versioned: &VersionedMachine) -> StorageResult<()> {
+        let mut machine_dao = VotingMachineDao::from(versioned.machine.clone());
+        machine_dao.revision = versioned.revision.0;
 
-        Ok(VotingMachine::from(voting_machine_dao))
-    }
+        write_secret(&versioned.machine.secret, &secret_path(&self.filepath)).await?;
 
-    async fn put_voting_machine(&mut self, machine: VotingMachine) -> Result<()> 
-    {
-        let filepath = self.filepath.read().unwrap().clone();
-        let mut my_file = File::create(filepath).await?;
-        let machine_dao = VotingMachineDao::from(machine);
+        let temporary_path : String = format!("{}.tmp", self.filepath);
+        let mut my_file = File::create(&temporary_path).await?;
         let serialized_machine = serde_json::to_string(&machine_dao)?;
         my_file.write_all(serialized_machine.as_bytes()).await?;
+        my_file.sync_all().await?;
+        tokio::fs::rename(&temporary_path, &self.filepath).await?;
+
+        for journal_path in [&self.journal_path, &self.ballots_path] {
+            if Path::new(journal_path).exists() {
+                tokio::fs::remove_file(journal_path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn write_attendance(&self, voter: &Voter, holder: Option<&Voter>) -> StorageResult<()> {
+        let mut kept_lines : Vec<String> = Vec::new();
+        let mut attendance : Map<String, String> = Map::new();
+        if Path::new(&self.journal_path).exists() {
+            for line in tokio::fs::read_to_string(&self.journal_path).await?.lines().filter(|line| !line.is_empty()) {
+                match serde_json::from_str::<JournalRecordDao>(line)? {
+                    JournalRecordDao::Attendance { voter, .. } => {
+                        attendance.insert(voter, line.to_string());
+                    }
+                    JournalRecordDao::Ballot { .. } => kept_lines.push(line.to_string()),
+                }
+            }
+        }
+        attendance.insert(voter.0.clone(), serde_json::to_string(&JournalRecordDao::attendance(voter, holder))?);
+
+        let mut serialized_records : String = String::new();
+        for line in kept_lines.into_iter().chain(attendance.into_values()) {
+            serialized_records.push_str(&line);
+            serialized_records.push('\n');
+        }
+        let temporary_path : String = format!("{}.tmp", self.journal_path);
+        let mut journal = File::create(&temporary_path).await?;
+        journal.write_all(serialized_records.as_bytes()).await?;
+        journal.sync_all().await?;
+        tokio::fs::rename(&temporary_path, &self.journal_path).await?;
+        Ok(())
+    }
+
+    async fn append_ballot(&self, record: &JournalRecordDao) -> StorageResult<()> {
+        let mut ballots = OpenOptions::new().create(true).append(true).open(&self.ballots_path).await?;
+        let mut serialized_record : String = serde_json::to_string(record)?;
+        serialized_record.push('\n');
+        ballots.write_all(serialized_record.as_bytes()).await?;
+        ballots.sync_data().await?;
+        Ok(())
+    }
+
+    async fn journal_vote(&mut self, voter: &Voter, holder: Option<Voter>, ballot: Ballot, tracking_code: TrackingCode) -> StorageResult<()> {
+        if self.read_state()?.machine.closed {
+            return Err(StorageError::Conflict(String::from("the election was closed before the ballot was recorded")));
+        }
+        if self.has_voted(voter).await? && self.superseded_ballot(voter).await?.is_none() {
+            return Err(StorageError::Conflict(format!("{} has already been recorded", voter.0)));
+        }
+        if self.read_state()?.machine.ballot_box.contains(&tracking_code) {
+            return Err(StorageError::Conflict(format!("tracking code {} is already used", tracking_code.0)));
+        }
+
+        let token : Option<VoterToken> = self.read_state()?.machine.ballot_token(voter);
+        self.write_attendance(voter, holder.as_ref()).await?;
+        self.append_ballot(&JournalRecordDao::ballot(&ballot, token.as_ref(), &tracking_code)).await?;
+
+        let mut state = self.write_state()?;
+        state.machine.attend(voter, holder);
+        state.machine.file_ballot(ballot, token, tracking_code);
+        state.revision.0 += 1;
+        Ok(())
+    }
+
+    async fn replace_machine(&mut self, machine: VotingMachine, revision: Revision) -> StorageResult<()> {
+        let versioned : VersionedMachine = VersionedMachine { machine, revision };
+        self.write_snapshot(&versioned).await?;
+        *self.write_state()? = versioned;
         Ok(())
     }
 }
 
-impl From<ScoreboardDao> for Scoreboard {
-    fn from(scoreboard_dao: ScoreboardDao) -> Self {
+#[async_trait::async_trait]
+impl Storage for FileStore {
+    async fn get_voting_machine(&self) -> StorageResult<VotingMachine> {
+        Ok(self.read_state()?.machine.clone())
+    }
+
+    async fn get_versioned_voting_machine(&self) -> StorageResult<VersionedMachine> {
+        Ok(self.read_state()?.clone())
+    }
+
+    async fn put_voting_machine(&mut self, machine: VotingMachine) -> StorageResult<()> 
+    {
+        let revision : Revision = Revision(self.read_state()?.revision.0 + 1);
+        self.replace_machine(machine, revision).await
+    }
+
+    async fn compare_and_put_voting_machine(&mut self, machine: VotingMachine, expected: Revision) -> StorageResult<Revision> 
+    {
+        let current_revision : Revision = self.read_state()?.revision;
+        if current_revision != expected {
+            return Err(StorageError::Conflict(format!("expected revision {} but found {}", expected.0, current_revision.0)));
+        }
+
+        let revision : Revision = Revision(current_revision.0 + 1);
+        self.replace_machine(machine, revision).await?;
+        Ok(revision)
+    }
+
+    async fn has_voted(&self, voter: &Voter) -> StorageResult<bool> {
+        Ok(self.read_state()?.machine.voters.attendees.contains(voter))
+    }
+
+    async fn superseded_ballot(&self, voter: &Voter) -> StorageResult<Option<TrackingCode>> {
+        Ok(self.read_state()?.machine.superseded_ballot(voter).cloned())
+    }
+
+    async fn get_scoreboard(&self) -> StorageResult<Scoreboard> {
+        Ok(self.read_state()?.machine.scoreboard.clone())
+    }
+
+    async fn record_vote(&mut self, outcome: VoteOutcome, holder: Option<Voter>, tracking_code: TrackingCode) -> StorageResult<()> {
+        let Some(ballot) = Ballot::of(&outcome) else {
+            return Ok(());
+        };
+        self.journal_vote(outcome.voter(), holder, ballot, tracking_code).await
+    }
+
+    async fn record_sealed_vote(&mut self, voter: Voter, holder: Option<Voter>, ballot: EncryptedBallot, tracking_code: TrackingCode) -> StorageResult<()> {
+        self.journal_vote(&voter, holder, Ballot::Sealed(ballot), tracking_code).await
+    }
+}
+
+impl TryFrom<ScoreboardDao> for Scoreboard {
+    type Error = StorageError;
+
+    fn try_from(scoreboard_dao: ScoreboardDao) -> StorageResult<Self> {
         let scores: Map<Candidate, Score> = scoreboard_dao.scores
             .into_iter()
             .map(|(candidate, score)| (Candidate(candidate), Score(score)))
             .collect();
 
-        Scoreboard { 
+        let invalid_reasons: Map<InvalidReason, Score> = scoreboard_dao.invalid_reasons
+            .into_iter()
+            .map(|(code, score)| InvalidReason::from_code(&code)
+                .map(|reason| (reason, Score(score)))
+                .ok_or_else(|| StorageError::Corrupted(format!("unknown invalid vote reason {}", code))))
+            .collect::<StorageResult<_>>()?;
+
+        let mut profiles: Map<Candidate, CandidateProfile> = Scoreboard::new(scores.keys().cloned().collect()).profiles;
+        profiles.extend(scoreboard_dao.candidates
+            .into_iter()
+            .map(|(candidate, candidate_dao)| (Candidate(candidate), CandidateProfile::from(candidate_dao))));
+
+        Ok(Scoreboard { 
             scores,
+            profiles,
             blank_scores: Score(scoreboard_dao.blank_scores), 
-            invalid_scores: Score(scoreboard_dao.invalid_score)
+            invalid_scores: Score(scoreboard_dao.invalid_score),
+            invalid_reasons,
+            write_in: scoreboard_dao.write_in,
+            write_in_scores: scoreboard_dao.write_in_scores.into_iter().map(|(name, score)| (name, Score(score))).collect(),
+            sealed_scores: Score(scoreboard_dao.sealed_scores),
+            election_key: scoreboard_dao.election_key,
+            threshold_key: scoreboard_dao.threshold_key,
+            revote: scoreboard_dao.revote.map(|policy| policy.parse().map_err(StorageError::Corrupted)).transpose()?.unwrap_or_default(),
+            max_proxies: scoreboard_dao.max_proxies.unwrap_or(1),
+            schedule: scoreboard_dao.schedule.map(|schedule| Schedule { opens_at: schedule.opens_at, closes_at: schedule.closes_at }),
+        })
+    }
+}
+
+pub fn write_private(filepath: impl AsRef<Path>, contents: &[u8]) -> std::io::Result<()> {
+    let mut options : std::fs::OpenOptions = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file : std::fs::File = options.open(filepath)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+pub fn secret_path(filepath: &str) -> String {
+    format!("{}.secret", filepath)
+}
+
+pub async fn read_secret(machine: &mut VotingMachine, secret_path: &str) -> StorageResult<()> {
+    if !Path::new(secret_path).exists() {
+        if machine.roll.is_some() || !machine.ballot_tokens.is_empty() || machine.admin.is_some() {
+            return Err(StorageError::Missing(secret_path.to_string()));
         }
+        return Ok(());
     }
+    machine.secret = load_secret(secret_path).await?;
+    Ok(())
+}
+
+pub async fn load_secret(secret_path: &str) -> StorageResult<ElectionSecret> {
+    let encoded : String = match tokio::fs::read_to_string(secret_path).await {
+        Ok(encoded) => encoded,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Err(StorageError::Missing(secret_path.to_string())),
+        Err(error) => return Err(StorageError::Io(error)),
+    };
+    let secret : [u8; 32] = hex::decode(encoded.trim()).ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| StorageError::Corrupted(format!("unreadable election secret {}", secret_path)))?;
+    Ok(ElectionSecret(secret))
+}
+
+pub async fn write_secret(secret: &ElectionSecret, secret_path: &str) -> StorageResult<()> {
+    let encoded : String = hex::encode(secret.0);
+    if tokio::fs::read_to_string(secret_path).await.is_ok_and(|stored| stored.trim() == encoded) {
+        return Ok(());
+    }
+    let temporary_path : String = format!("{}.tmp", secret_path);
+    if Path::new(&temporary_path).exists() {
+        tokio::fs::remove_file(&temporary_path).await?;
+    }
+    write_private(&temporary_path, encoded.as_bytes())?;
+    tokio::fs::rename(&temporary_path, secret_path).await?;
+    Ok(())
+}
+
+pub async fn open_file(filepath: &str) -> StorageResult<File> {
+    File::open(filepath).await.map_err(|error| match error.kind() {
+        std::io::ErrorKind::NotFound => StorageError::Missing(filepath.to_string()),
+        _ => StorageError::Io(error),
+    })
+}
+
+async fn load(filepath: &str, journal_paths: [&str; 2]) -> StorageResult<VersionedMachine> {
+    let mut file : File = open_file(filepath).await?;
+    let mut content: Vec<u8> = vec![];
+    file.read_to_end(&mut content).await?;
+    let machine_dao : VotingMachineDao = serde_json::from_slice::<VotingMachineDao>(&content)?;
+
+    let mut revision : Revision = Revision(machine_dao.revision);
+    let mut machine : VotingMachine = machine_dao.try_into()?;
+    read_secret(&mut machine, &secret_path(filepath)).await?;
+
+    for journal_path in journal_paths.into_iter().filter(|journal_path| Path::new(journal_path).exists()) {
+        let journal : String = tokio::fs::read_to_string(journal_path).await?;
+        for line in journal.lines().filter(|line| !line.is_empty()) {
+            let record : JournalRecordDao = serde_json::from_str::<JournalRecordDao>(line)?;
+            if let JournalRecordDao::Ballot { .. } = record {
+                revision.0 += 1;
+            }
+            record.replay(&mut machine)?;
+        }
+    }
+
+    Ok(VersionedMachine { machine, revision })
 }
 
 impl FileStore 
 {
-    pub async fn new(machine: &VotingMachine, filepath: &str) -> anyhow::Result<Self> {
+    pub async fn open(filepath: &str) -> StorageResult<Self> {
+        let journal_path : String = format!("{}.journal", filepath);
+        let ballots_path : String = format!("{}.ballots", filepath);
+        let versioned : VersionedMachine = load(filepath, [&journal_path, &ballots_path]).await?;
+
+        Ok(FileStore { 
+            filepath: filepath.to_string(), 
+            journal_path, 
+            ballots_path,
+            state: Arc::new(RwLock::new(versioned)),
+        })
+    }
+
+    pub async fn new(machine: &VotingMachine, filepath: &str) -> StorageResult<Self> {
         
-        let file_path : &Path = Path::new(filepath);
-    
-        let machine : VotingMachine = if file_path.exists() 
+        let journal_path : String = format!("{}.journal", filepath);
+        let ballots_path : String = format!("{}.ballots", filepath);
+
+        let versioned : VersionedMachine = if Path::new(filepath).exists() 
         {
-            let mut file : File = File::open(file_path).await?;
-            let mut content: Vec<u8> = vec![];
-            file.read_to_end(&mut content).await?;
-            let content : VotingMachineDao = serde_json::from_slice::<VotingMachineDao>(&content)?;
-            content.into()
+            load(filepath, [&journal_path, &ballots_path]).await?
         } 
-        else { machine.clone() };
-    
-        if !file_path.exists() { let _ = File::create(file_path).await; }
+        else { VersionedMachine { machine: machine.clone(), revision: Revision::default() } };
 
-        let mut file_store : FileStore = FileStore { filepath: Arc::new(RwLock::new(filepath.to_string())), };
-        let _ = file_store.put_voting_machine(machine).await;
+        let file_store : FileStore = FileStore { 
+            filepath: filepath.to_string(), 
+            journal_path, 
+            ballots_path,
+            state: Arc::new(RwLock::new(versioned.clone())),
+        };
+        file_store.write_snapshot(&versioned).await?;
     
         Ok(file_store)
     }
@@ -142,24 +622,25 @@ mod tests
 {
     use std::fs;
 
-    use crate::storage::Storage;
-    use crate::domain::{VotingMachine, Candidate};
-    use crate::storage::file::FileStore;
+    use crate::storage::{Storage, StorageError};
+    use crate::domain::{VotingMachine, Ballot, BallotPaper, Candidate, InvalidReason, TrackingCode, Voter, VoteOutcome};
+    use crate::storage::file::{FileStore, secret_path};
     use std::sync::{Arc, RwLock};
 
     fn setup_voting_machine() -> VotingMachine
     {
-        let mut candidates : Vec<Candidate> = Vec::new();
-        candidates.push(Candidate("E.Macron".to_string()));
-        candidates.push(Candidate("M.Lepen".to_string()));
-        candidates.push(Candidate("JL.Mélanchon".to_string()));
-        return VotingMachine::new(candidates);
+        let candidates : Vec<Candidate> = vec![
+            Candidate("E.Macron".to_string()),
+            Candidate("M.Lepen".to_string()),
+            Candidate("JL.Mélanchon".to_string()),
+        ];
+        VotingMachine::new(candidates)
     }
 
     #[tokio::test]
     async fn test_get_and_put_voting_machine() -> anyhow::Result<()> 
     {
-        let mut machine : VotingMachine = setup_voting_machine();
+        let machine : VotingMachine = setup_voting_machine();
         let filepath : &str = "test.txt";
         let memory : Arc<RwLock<FileStore>> = Arc::new(RwLock::new(FileStore::new(&machine, filepath).await?));
 
@@ -170,6 +651,7 @@ mod tests
         };
 
         fs::remove_file(filepath)?;
+        let _ = fs::remove_file(secret_path(filepath));
 
         assert_eq!(stored_machine, machine);
         Ok(())
@@ -178,7 +660,7 @@ mod tests
     #[tokio::test]
     async fn store_value_is_conserved() -> anyhow::Result<()> 
     {
-        let mut machine : VotingMachine = setup_voting_machine();
+        let machine : VotingMachine = setup_voting_machine();
         let filepath : &str = "test.txt";
 
         let first_memory : Arc<RwLock<FileStore>> = Arc::new(RwLock::new(FileStore::new(&machine, filepath).await?));
@@ -192,15 +674,180 @@ mod tests
         let second_memory : Arc<RwLock<FileStore>> = Arc::new(RwLock::new(FileStore::new(&machine, filepath).await?));
 
         let second_stored_machine = {
-            let memory_guard = first_memory.read(); // Acquire lock on RwLock
+            let memory_guard = second_memory.read(); // Acquire lock on RwLock
             let memory = memory_guard.as_ref().expect("Failed to get memory");
             memory.get_voting_machine().await?
         };
 
         fs::remove_file(filepath)?;
+        let _ = fs::remove_file(secret_path(filepath));
 
         assert_eq!(first_stored_machine, second_stored_machine);
         Ok(())
     }
-}
 
+    #[tokio::test]
+    async fn corrupted_file_is_reported() -> anyhow::Result<()> 
+    {
+        let machine : VotingMachine = setup_voting_machine();
+        let filepath : &str = "test_corrupted.txt";
+        fs::write(filepath, "{ not a voting machine")?;
+
+        let file_store = FileStore::new(&machine, filepath).await;
+
+        fs::remove_file(filepath)?;
+        let _ = fs::remove_file(secret_path(filepath));
+
+        assert!(matches!(file_store, Err(StorageError::Corrupted(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_invalid_reason_is_reported() -> anyhow::Result<()> 
+    {
+        let machine : VotingMachine = setup_voting_machine();
+        let filepath : &str = "test_unknown_reason.txt";
+        let file_store : FileStore = FileStore::new(&machine, filepath).await?;
+        drop(file_store);
+        let content : String = fs::read_to_string(filepath)?.replacen("\"invalid_score\":0", "\"invalid_score\":1,\"invalid_reasons\":{\"too_many_choices\":1}", 1);
+        fs::write(filepath, content)?;
+
+        let reloaded = FileStore::open(filepath).await;
+
+        fs::remove_file(filepath)?;
+        let _ = fs::remove_file(secret_path(filepath));
+
+        assert!(matches!(reloaded, Err(StorageError::Corrupted(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_ballot_reason_and_revote_policy_are_reported() -> anyhow::Result<()> 
+    {
+        let machine : VotingMachine = setup_voting_machine();
+        let filepath : &str = "test_unknown_codes.txt";
+        let file_store : FileStore = FileStore::new(&machine, filepath).await?;
+        drop(file_store);
+        let content : String = fs::read_to_string(filepath)?;
+
+        fs::write(filepath, content.replacen("\"closed\":false", "\"closed\":false,\"ballot_box\":{\"AAAA-AAAA-AAAA\":{\"Rejected\":\"too_many_choices\"}}", 1))?;
+        let unknown_reason = FileStore::open(filepath).await;
+        fs::write(filepath, content.replacen("\"invalid_score\":0", "\"invalid_score\":0,\"revote\":\"replace\"", 1))?;
+        let unknown_policy = FileStore::open(filepath).await;
+
+        fs::remove_file(filepath)?;
+        let _ = fs::remove_file(secret_path(filepath));
+
+        assert!(matches!(unknown_reason, Err(StorageError::Corrupted(_))));
+        assert!(matches!(unknown_policy, Err(StorageError::Corrupted(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn issued_codes_survive_reopening_with_their_secret() -> anyhow::Result<()> 
+    {
+        let mut machine : VotingMachine = setup_voting_machine();
+        let filepath : &str = "test_secret.txt";
+        machine.issue_code(Voter("Jean".to_string()), "ABCD-2345");
+        let ballot_paper : BallotPaper = BallotPaper { voter: Voter("Jean".to_string()), candidate: None, code: Some("ABCD-2345".to_string()), holder: None };
+
+        drop(FileStore::new(&machine, filepath).await?);
+        let reopened : FileStore = FileStore::open(filepath).await?;
+        let accepted = reopened.get_voting_machine().await?.authenticate(&ballot_paper, 0);
+        let stored : String = fs::read_to_string(filepath)?;
+        #[cfg(unix)]
+        let mode : u32 = std::os::unix::fs::PermissionsExt::mode(&fs::metadata(secret_path(filepath))?.permissions());
+        fs::remove_file(secret_path(filepath))?;
+        let orphaned = FileStore::open(filepath).await;
+
+        fs::remove_file(filepath)?;
+
+        assert_eq!(accepted, None);
+        assert!(!stored.contains(&hex::encode(machine.secret.0)));
+        #[cfg(unix)]
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(matches!(orphaned, Err(StorageError::Missing(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn legacy_file_gets_default_candidate_profiles() -> anyhow::Result<()> 
+    {
+        let filepath : &str = "test_legacy_profiles.txt";
+        fs::write(filepath, r#"{"voters":["Jean"],"scoreboard":{"scores":{"E.Macron":1,"M.Lepen":0},"blank_scores":0,"invalid_score":0}}"#)?;
+
+        let file_store = FileStore::open(filepath).await;
+
+        fs::remove_file(filepath)?;
+        let _ = fs::remove_file(secret_path(filepath));
+
+        let scoreboard = file_store?.get_scoreboard().await?;
+        let candidate : Candidate = Candidate("E.Macron".to_string());
+        assert_eq!(scoreboard.display_name(&candidate), "E.Macron");
+        assert_eq!(scoreboard.scores[&candidate].0, 1);
+        assert_eq!(scoreboard.profiles.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recorded_votes_are_replayed_from_journal() -> anyhow::Result<()> 
+    {
+        let machine : VotingMachine = setup_voting_machine();
+        let filepath : &str = "test_journal.txt";
+        let voter : Voter = Voter("Jean".to_string());
+
+        let mut first_store : FileStore = FileStore::new(&machine, filepath).await?;
+        first_store.record_vote(VoteOutcome::AcceptedVote(voter.clone(), Candidate("E.Macron".to_string())), None, TrackingCode("AAAA-AAAA-AAAA".to_string())).await?;
+        first_store.record_vote(VoteOutcome::BlankVote(Voter("Paul".to_string())), None, TrackingCode("BBBB-BBBB-BBBB".to_string())).await?;
+        let duplicate = first_store.record_vote(VoteOutcome::InvalidVote(voter.clone(), InvalidReason::UnknownCandidate), None, TrackingCode("CCCC-CCCC-CCCC".to_string())).await;
+        let journal : String = fs::read_to_string(format!("{}.journal", filepath))?;
+        let ballots : String = fs::read_to_string(format!("{}.ballots", filepath))?;
+
+        let second_store : FileStore = FileStore::new(&machine, filepath).await?;
+        let reloaded_machine : VotingMachine = second_store.get_voting_machine().await?;
+
+        fs::remove_file(filepath)?;
+        let _ = fs::remove_file(secret_path(filepath));
+
+        assert_eq!(journal.lines().filter(|line| line.contains("\"voter\"")).count(), 2);
+        assert!(journal.lines().all(|line| !line.contains("\"ballot\"")));
+        assert_eq!(ballots.lines().count(), 2);
+        assert!(ballots.lines().all(|line| !line.contains("\"voter\"")));
+        assert!(matches!(duplicate, Err(StorageError::Conflict(_))));
+        assert!(second_store.has_voted(&voter).await?);
+        assert_eq!(reloaded_machine, first_store.get_voting_machine().await?);
+        assert_eq!(second_store.get_scoreboard().await?.blank_scores.0, 1);
+        assert_eq!(reloaded_machine.ballot_box.lookup("BBBB-BBBB-BBBB"), Some(&Ballot::Blank));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn journaled_ballots_cannot_be_matched_to_voters_by_position() -> anyhow::Result<()> 
+    {
+        let machine : VotingMachine = setup_voting_machine();
+        let filepath : &str = "test_journal_unlinkable.txt";
+        let casting_order : [(&str, &str, &str); 3] = [("Paul", "E.Macron", "AAAA-AAAA-AAAA"), ("Anne", "", "BBBB-BBBB-BBBB"), ("Marc", "M.Lepen", "CCCC-CCCC-CCCC")];
+
+        let mut file_store : FileStore = FileStore::new(&machine, filepath).await?;
+        for (voter, candidate, tracking_code) in casting_order
+        {
+            let outcome : VoteOutcome = if candidate.is_empty() { VoteOutcome::BlankVote(Voter(voter.to_string())) } else { VoteOutcome::AcceptedVote(Voter(voter.to_string()), Candidate(candidate.to_string())) };
+            file_store.record_vote(outcome, None, TrackingCode(tracking_code.to_string())).await?;
+        }
+        let journal : String = fs::read_to_string(format!("{}.journal", filepath))?;
+        let ballots : String = fs::read_to_string(format!("{}.ballots", filepath))?;
+        let reopened : VotingMachine = FileStore::open(filepath).await?.get_voting_machine().await?;
+
+        fs::remove_file(filepath)?;
+        let _ = fs::remove_file(format!("{}.journal", filepath));
+        let _ = fs::remove_file(format!("{}.ballots", filepath));
+        let _ = fs::remove_file(secret_path(filepath));
+
+        let attendance : Vec<&str> = journal.lines().filter_map(|line| ["Anne", "Marc", "Paul"].into_iter().find(|voter| line.contains(voter))).collect();
+        let tracking_codes : Vec<&str> = ballots.lines().filter_map(|line| ["AAAA-AAAA-AAAA", "BBBB-BBBB-BBBB", "CCCC-CCCC-CCCC"].into_iter().find(|tracking_code| line.contains(tracking_code))).collect();
+        assert_eq!(attendance, vec!["Anne", "Marc", "Paul"]);
+        assert_eq!(tracking_codes, vec!["AAAA-AAAA-AAAA", "BBBB-BBBB-BBBB", "CCCC-CCCC-CCCC"]);
+        assert_eq!(reopened, file_store.get_voting_machine().await?);
+        Ok(())
+    }
+}
@@ -1,27 +1,97 @@
-use crate::domain::{VotingMachine, Candidate};
-use crate::storage::Storage;
-use anyhow::{Result, anyhow};
+use crate::domain::{EncryptedBallot, Scoreboard, TrackingCode, Voter, VoteOutcome, VotingMachine};
+use crate::storage::{Storage, StorageError, StorageResult, Revision, VersionedMachine};
 use std::sync::{RwLock, Arc};
 pub struct MemoryStore {
-    pub machine: Arc<RwLock<VotingMachine>>,
+    pub machine: Arc<RwLock<VersionedMachine>>,
 }
 
 impl MemoryStore {
     pub fn new(new_machine: VotingMachine) -> Self {
-        MemoryStore { machine: Arc::new(RwLock::new(new_machine)) }
+        MemoryStore { machine: Arc::new(RwLock::new(VersionedMachine { machine: new_machine, revision: Revision::default() })) }
     }
 }
 
 #[async_trait::async_trait]
 impl Storage for MemoryStore {
-    async fn get_voting_machine(&self) -> Result<VotingMachine> {
-        let machine = self.machine.read().map_err(|_| anyhow!("Failed to acquire read lock"))?;
-        Ok(machine.clone())
+    async fn get_voting_machine(&self) -> StorageResult<VotingMachine> {
+        let versioned = self.machine.read().map_err(|_| StorageError::LockPoisoned)?;
+        Ok(versioned.machine.clone())
     }
 
-    async fn put_voting_machine(&mut self, machine: VotingMachine) -> Result<()> {
-        let mut write_guard = self.machine.write().map_err(|_| anyhow!("Failed to acquire write lock"))?;
-        *write_guard = machine;
+    async fn get_versioned_voting_machine(&self) -> StorageResult<VersionedMachine> {
+        let versioned = self.machine.read().map_err(|_| StorageError::LockPoisoned)?;
+        Ok(versioned.clone())
+    }
+
+    async fn put_voting_machine(&mut self, machine: VotingMachine) -> StorageResult<()> {
+        let mut write_guard = self.machine.write().map_err(|_| StorageError::LockPoisoned)?;
+        let revision : Revision = Revision(write_guard.revision.0 + 1);
+        *write_guard = VersionedMachine { machine, revision };
+        Ok(())
+    }
+
+    async fn compare_and_put_voting_machine(&mut self, machine: VotingMachine, expected: Revision) -> StorageResult<Revision> {
+        let mut write_guard = self.machine.write().map_err(|_| StorageError::LockPoisoned)?;
+        if write_guard.revision != expected {
+            return Err(StorageError::Conflict(format!("expected revision {} but found {}", expected.0, write_guard.revision.0)));
+        }
+
+        let revision : Revision = Revision(expected.0 + 1);
+        *write_guard = VersionedMachine { machine, revision };
+        Ok(revision)
+    }
+
+    async fn has_voted(&self, voter: &Voter) -> StorageResult<bool> {
+        let versioned = self.machine.read().map_err(|_| StorageError::LockPoisoned)?;
+        Ok(versioned.machine.voters.attendees.contains(voter))
+    }
+
+    async fn superseded_ballot(&self, voter: &Voter) -> StorageResult<Option<TrackingCode>> {
+        let versioned = self.machine.read().map_err(|_| StorageError::LockPoisoned)?;
+        Ok(versioned.machine.superseded_ballot(voter).cloned())
+    }
+
+    async fn get_scoreboard(&self) -> StorageResult<Scoreboard> {
+        let versioned = self.machine.read().map_err(|_| StorageError::LockPoisoned)?;
+        Ok(versioned.machine.scoreboard.clone())
+    }
+
+    async fn record_vote(&mut self, outcome: VoteOutcome, holder: Option<Voter>, tracking_code: TrackingCode) -> StorageResult<()> {
+        let mut write_guard = self.machine.write().map_err(|_| StorageError::LockPoisoned)?;
+        if !outcome.is_recorded() {
+            return Ok(());
+        }
+        if write_guard.machine.closed {
+            return Err(StorageError::Conflict(String::from("the election was closed before the ballot was recorded")));
+        }
+        if write_guard.machine.voters.attendees.contains(outcome.voter()) && write_guard.machine.superseded_ballot(outcome.voter()).is_none() {
+            return Err(StorageError::Conflict(format!("{} has already been recorded", outcome.voter().0)));
+        }
+        if write_guard.machine.ballot_box.contains(&tracking_code) {
+            return Err(StorageError::Conflict(format!("tracking code {} is already used", tracking_code.0)));
+        }
+
+        write_guard.machine.deposit(&outcome, tracking_code);
+        write_guard.machine.countersign(outcome.voter(), holder);
+        write_guard.revision.0 += 1;
+        Ok(())
+    }
+
+    async fn record_sealed_vote(&mut self, voter: Voter, holder: Option<Voter>, ballot: EncryptedBallot, tracking_code: TrackingCode) -> StorageResult<()> {
+        let mut write_guard = self.machine.write().map_err(|_| StorageError::LockPoisoned)?;
+        if write_guard.machine.closed {
+            return Err(StorageError::Conflict(String::from("the election was closed before the ballot was recorded")));
+        }
+        if write_guard.machine.voters.attendees.contains(&voter) && write_guard.machine.superseded_ballot(&voter).is_none() {
+            return Err(StorageError::Conflict(format!("{} has already been recorded", voter.0)));
+        }
+        if write_guard.machine.ballot_box.contains(&tracking_code) {
+            return Err(StorageError::Conflict(format!("tracking code {} is already used", tracking_code.0)));
+        }
+
+        write_guard.machine.deposit_sealed(&voter, ballot, tracking_code);
+        write_guard.machine.countersign(&voter, holder);
+        write_guard.revision.0 += 1;
         Ok(())
     }
 }
@@ -29,17 +99,20 @@ impl Storage for MemoryStore {
 #[cfg(test)]
 mod tests 
 {
-    use crate::storage::Storage;
+    use crate::storage::{Storage, StorageError};
+
+    use crate::domain::{Candidate, TrackingCode, Voter, VoteOutcome};
 
-    use super::{VotingMachine, Candidate, MemoryStore};
+    use super::{VotingMachine, MemoryStore};
 
     fn setup_voting_machine() -> VotingMachine
     {
-        let mut candidates : Vec<Candidate> = Vec::new();
-        candidates.push(Candidate("E.Macron".to_string()));
-        candidates.push(Candidate("M.Lepen".to_string()));
-        candidates.push(Candidate("JL.Mélanchon".to_string()));
-        return VotingMachine::new(candidates);
+        let candidates : Vec<Candidate> = vec![
+            Candidate("E.Macron".to_string()),
+            Candidate("M.Lepen".to_string()),
+            Candidate("JL.Mélanchon".to_string()),
+        ];
+        VotingMachine::new(candidates)
     }
 
     #[tokio::test]
@@ -57,4 +130,31 @@ mod tests
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn stale_revision_is_rejected() -> anyhow::Result<()> 
+    {
+        let mut memory = MemoryStore::new(setup_voting_machine());
+
+        let versioned = memory.get_versioned_voting_machine().await?;
+        memory.compare_and_put_voting_machine(versioned.machine.clone(), versioned.revision).await?;
+        let outcome = memory.compare_and_put_voting_machine(versioned.machine, versioned.revision).await;
+
+        assert!(matches!(outcome, Err(StorageError::Conflict(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ballot_is_refused_once_the_election_is_closed() -> anyhow::Result<()> 
+    {
+        let mut closed_machine = setup_voting_machine();
+        closed_machine.close();
+        let mut memory = MemoryStore::new(closed_machine);
+
+        let outcome = memory.record_vote(VoteOutcome::BlankVote(Voter("Jean".to_string())), None, TrackingCode("ABCD-EFGH-JKLM".to_string())).await;
+
+        assert!(matches!(outcome, Err(StorageError::Conflict(_))));
+        assert!(!memory.has_voted(&Voter("Jean".to_string())).await?);
+        Ok(())
+    }
+}
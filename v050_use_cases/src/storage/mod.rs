@@ -1,12 +1,52 @@
 pub mod memory;
 pub mod file;
+pub mod archive;
 
 use async_trait::async_trait;
+use thiserror::Error;
 
-use crate::domain::VotingMachine;
+use crate::domain::{EncryptedBallot, Scoreboard, TrackingCode, Voter, VoteOutcome, VotingMachine};
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("storage file {0} is missing")]
+    Missing(String),
+    #[error("stored data is corrupted: {0}")]
+    Corrupted(String),
+    #[error("storage lock is poisoned")]
+    LockPoisoned,
+    #[error("storage conflict: {0}")]
+    Conflict(String),
+    #[error("storage i/o error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(error: serde_json::Error) -> Self {
+        StorageError::Corrupted(error.to_string())
+    }
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Revision(pub u64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionedMachine {
+    pub machine: VotingMachine,
+    pub revision: Revision,
+}
 
 #[async_trait]
-pub trait Storage {
-    async fn get_voting_machine(&self) -> anyhow::Result<VotingMachine>;
-    async fn put_voting_machine(&mut self, machine: VotingMachine) -> anyhow::Result<()>;
-}
\ No newline at end of file
+pub trait Storage: Send + Sync {
+    async fn get_voting_machine(&self) -> StorageResult<VotingMachine>;
+    async fn get_versioned_voting_machine(&self) -> StorageResult<VersionedMachine>;
+    async fn put_voting_machine(&mut self, machine: VotingMachine) -> StorageResult<()>;
+    async fn compare_and_put_voting_machine(&mut self, machine: VotingMachine, expected: Revision) -> StorageResult<Revision>;
+    async fn has_voted(&self, voter: &Voter) -> StorageResult<bool>;
+    async fn superseded_ballot(&self, voter: &Voter) -> StorageResult<Option<TrackingCode>>;
+    async fn get_scoreboard(&self) -> StorageResult<Scoreboard>;
+    async fn record_vote(&mut self, outcome: VoteOutcome, holder: Option<Voter>, tracking_code: TrackingCode) -> StorageResult<()>;
+    async fn record_sealed_vote(&mut self, voter: Voter, holder: Option<Voter>, ballot: EncryptedBallot, tracking_code: TrackingCode) -> StorageResult<()>;
+}
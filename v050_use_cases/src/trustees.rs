@@ -0,0 +1,91 @@
+use std::collections::BTreeMap as Map;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{bulletin::{PublishedBulletin, VerificationFailure}, domain::EncryptedBallot, elgamal::{DecryptionFactor, Group, KeyShare}};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialDecryption {
+    pub trustee: u64,
+    pub factors: Map<String, DecryptionFactor>,
+}
+
+impl PartialDecryption {
+    pub fn compute(share: &KeyShare, aggregate: &EncryptedBallot, rng: &mut impl Rng) -> Self {
+        let factors : Map<String, DecryptionFactor> = aggregate.0.iter()
+            .map(|(slot, ciphertext)| (slot.clone(), share.decryption_factor(ciphertext, rng)))
+            .collect();
+        PartialDecryption { trustee: share.trustee, factors }
+    }
+
+    pub fn decrypts(&self, aggregate: &EncryptedBallot) -> bool {
+        self.factors.len() == aggregate.0.len()
+            && aggregate.0.iter().all(|(slot, ciphertext)| self.factors.get(slot).is_some_and(|factor| factor.a == ciphertext.a))
+    }
+}
+
+pub fn published_aggregate(bulletin: &PublishedBulletin, group: &Group) -> Result<EncryptedBallot, VerificationFailure> {
+    bulletin.verify()?;
+    bulletin.sealed_aggregate(group)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use crate::bulletin::{PublishedBulletin, VerificationFailure};
+    use crate::domain::{Candidate, Revelation, Submission, TrackingCode, Voter, VoteOutcome, VotingMachine};
+    use crate::elgamal::{Group, KeyShare, SecretKey, ThresholdKey};
+    use super::{PartialDecryption, published_aggregate};
+
+    #[test]
+    fn trustees_decrypt_the_published_ballots_once_the_threshold_is_reached()
+    {
+        let mut rng : StdRng = StdRng::seed_from_u64(50);
+        let key : SecretKey = SecretKey::generate(Group::election(), &mut rng);
+        let (threshold_key, shares) : (ThresholdKey, Vec<KeyShare>) = key.split(2, 3, &mut rng);
+        let mut voting_machine : VotingMachine = VotingMachine::new(vec![Candidate("E.Macron".to_string()), Candidate("M.Lepen".to_string())]);
+        voting_machine.scoreboard.election_key = Some(key.public_key());
+        voting_machine.scoreboard.threshold_key = Some(threshold_key);
+
+        for (index, (voter, candidate)) in [("Jean", "E.Macron"), ("Paul", "M.Lepen"), ("Marie", "E.Macron")].iter().enumerate()
+        {
+            let outcome : VoteOutcome = VoteOutcome::AcceptedVote(Voter(voter.to_string()), Candidate(candidate.to_string()));
+            if let Some(sealed) = voting_machine.scoreboard.seal(&outcome, &mut rng)
+            {
+                voting_machine.deposit_sealed(outcome.voter(), sealed, TrackingCode(format!("CODE-000{}", index)));
+            }
+        }
+        voting_machine.close();
+
+        let bulletin : PublishedBulletin = PublishedBulletin::of(&voting_machine);
+        let partials : Vec<PartialDecryption> = shares.iter()
+            .map(|share| published_aggregate(&bulletin, &share.group).map(|aggregate| PartialDecryption::compute(share, &aggregate, &mut rng)))
+            .collect::<Result<_, _>>()
+            .expect("bulletin is consistent");
+        let mut forged : PartialDecryption = partials[1].clone();
+        forged.trustee = 3;
+
+        assert_eq!(voting_machine.submit_partial(partials[2].clone()), Submission::Accepted { submitted: 1, threshold: 2 });
+        assert_eq!(voting_machine.combine(), Revelation::MissingShares { submitted: 1, threshold: 2 });
+        assert_eq!(voting_machine.submit_partial(forged), Submission::InvalidProof(3));
+        assert_eq!(voting_machine.submit_partial(partials[0].clone()), Submission::Accepted { submitted: 2, threshold: 2 });
+        assert_eq!(voting_machine.combine(), Revelation::Revealed(3));
+        assert_eq!(voting_machine.scoreboard.scores[&Candidate("E.Macron".to_string())].0, 2);
+        assert_eq!(voting_machine.scoreboard.scores[&Candidate("M.Lepen".to_string())].0, 1);
+
+        let revealed : PublishedBulletin = PublishedBulletin::of(&voting_machine);
+        assert_eq!(revealed.decryption.as_ref().map(|decryption| decryption.partials.len()), Some(2));
+        assert_eq!(revealed.verify(), Ok(()));
+
+        let mut short : PublishedBulletin = revealed.clone();
+        short.decryption.as_mut().expect("decryption is published").partials.pop();
+        assert_eq!(short.verify(), Err(VerificationFailure::InvalidDecryption("1 of 2 trustees".to_string())));
+
+        let mut impersonated : PublishedBulletin = revealed.clone();
+        impersonated.decryption.as_mut().expect("decryption is published").partials[1].trustee = 2;
+        assert_eq!(impersonated.verify(), Err(VerificationFailure::InvalidDecryption("trustee 2".to_string())));
+    }
+}
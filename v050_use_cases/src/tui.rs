@@ -0,0 +1,397 @@
+use std::{io, sync::Arc, time::Duration};
+
+use crossterm::{event::{self, Event, KeyCode, KeyEventKind}, execute, terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode}};
+use ratatui::{Frame, Terminal, backend::CrosstermBackend, layout::{Constraint, Direction, Layout}, style::{Modifier, Style}, widgets::{Block, Borders, List, ListItem, ListState, Paragraph}};
+use tokio::sync::RwLock;
+
+use crate::{audit::{AuditEntry, AuditLog}, configuration::OutputFormat, domain::{Candidate, Receipt, Scoreboard, Voter, VotingMachine}, messages::{Language, Message}, results::Results, storage::{Storage, StorageResult}, use_cases::{VoteForm, get_scoreboard, get_voting_machine, vote}};
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Step {
+    VoterInput,
+    PrincipalInput,
+    CodeInput,
+    CandidateSelection,
+    Confirmation,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TuiAction {
+    Nothing,
+    Cast(String, String, Option<String>, Option<String>),
+    Quit,
+}
+
+pub struct TuiState {
+    pub step: Step,
+    pub voter: String,
+    pub principal: String,
+    pub code: String,
+    pub codes_required: bool,
+    pub candidates: Vec<Candidate>,
+    pub selected: usize,
+    pub status: String,
+    pub scoreboard: Scoreboard,
+    pub language: Language,
+}
+
+impl TuiState {
+    pub fn new(scoreboard: Scoreboard, language: Language) -> Self {
+        let candidates : Vec<Candidate> = scoreboard.ballot().into_iter().cloned().collect();
+        TuiState {
+            step: Step::VoterInput,
+            voter: String::new(),
+            principal: String::new(),
+            code: String::new(),
+            codes_required: false,
+            candidates,
+            selected: 0,
+            status: String::new(),
+            scoreboard,
+            language,
+        }
+    }
+
+    fn choice_count(&self) -> usize {
+        self.candidates.len() + 1
+    }
+
+    fn selected_candidate(&self) -> String {
+        self.candidates.get(self.selected).map(|candidate| candidate.0.clone()).unwrap_or_default()
+    }
+
+    fn label_of(&self, candidate: &Candidate) -> String {
+        let name : &str = self.scoreboard.display_name(candidate);
+        match self.scoreboard.profiles.get(candidate).and_then(|profile| profile.party.as_deref()) {
+            Some(party) => format!("{} ({})", name, party),
+            None => name.to_string(),
+        }
+    }
+
+    pub fn selected_label(&self) -> String {
+        match self.candidates.get(self.selected) {
+            Some(candidate) => self.label_of(candidate),
+            None => Message::BlankChoice.text(self.language),
+        }
+    }
+
+    fn voter_label(&self) -> String {
+        match self.principal.trim() {
+            "" => self.voter.trim().to_string(),
+            principal => format!("{} ({})", principal, Message::ProxyMarker(self.voter.trim()).text(self.language)),
+        }
+    }
+
+    fn identity_step(&self) -> Step {
+        if self.principal.trim().is_empty() { Step::VoterInput } else { Step::PrincipalInput }
+    }
+
+    fn identified(&mut self) {
+        if self.codes_required {
+            self.step = Step::CodeInput;
+        } else {
+            self.step = Step::CandidateSelection;
+            self.selected = 0;
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> TuiAction {
+        match self.step {
+            Step::VoterInput => match key {
+                KeyCode::Esc => return TuiAction::Quit,
+                KeyCode::Char(character) => self.voter.push(character),
+                KeyCode::Backspace => { self.voter.pop(); }
+                KeyCode::Tab if !self.voter.trim().is_empty() => self.step = Step::PrincipalInput,
+                KeyCode::Enter if !self.voter.trim().is_empty() => {
+                    self.principal.clear();
+                    self.identified();
+                }
+                _ => {}
+            },
+            Step::PrincipalInput => match key {
+                KeyCode::Esc => {
+                    self.principal.clear();
+                    self.step = Step::VoterInput;
+                }
+                KeyCode::Char(character) => self.principal.push(character),
+                KeyCode::Backspace => { self.principal.pop(); }
+                KeyCode::Enter if !self.principal.trim().is_empty() => self.identified(),
+                _ => {}
+            },
+            Step::CodeInput => match key {
+                KeyCode::Esc => self.step = self.identity_step(),
+                KeyCode::Char(character) => self.code.push(character),
+                KeyCode::Backspace => { self.code.pop(); }
+                KeyCode::Enter if !self.code.trim().is_empty() => {
+                    self.step = Step::CandidateSelection;
+                    self.selected = 0;
+                }
+                _ => {}
+            },
+            Step::CandidateSelection => match key {
+                KeyCode::Esc if self.codes_required => self.step = Step::CodeInput,
+                KeyCode::Esc => self.step = self.identity_step(),
+                KeyCode::Up => self.selected = (self.selected + self.choice_count() - 1) % self.choice_count(),
+                KeyCode::Down => self.selected = (self.selected + 1) % self.choice_count(),
+                KeyCode::Enter => self.step = Step::Confirmation,
+                _ => {}
+            },
+            Step::Confirmation => match key {
+                KeyCode::Enter => return self.cast(),
+                KeyCode::Char(character) if character.to_ascii_lowercase() == self.language.confirmation_key() => return self.cast(),
+                KeyCode::Esc | KeyCode::Char(_) => self.step = Step::CandidateSelection,
+                _ => {}
+            },
+        }
+        TuiAction::Nothing
+    }
+
+    fn cast(&mut self) -> TuiAction {
+        let code : Option<String> = Some(self.code.trim().to_string()).filter(|code| !code.is_empty());
+        let principal : Option<String> = Some(self.principal.trim().to_string()).filter(|principal| !principal.is_empty());
+        let action : TuiAction = TuiAction::Cast(self.voter.trim().to_string(), self.selected_candidate(), code, principal);
+        self.step = Step::VoterInput;
+        self.voter.clear();
+        self.principal.clear();
+        self.code.clear();
+        self.selected = 0;
+        action
+    }
+}
+
+fn draw(frame: &mut Frame, state: &TuiState) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(frame.size());
+    let by_proxy : bool = state.step == Step::PrincipalInput || !state.principal.is_empty();
+    let principal_height : u16 = if by_proxy { 3 } else { 0 };
+    let code_height : u16 = if state.codes_required { 3 } else { 0 };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(principal_height), Constraint::Length(code_height), Constraint::Min(3), Constraint::Length(3)])
+        .split(columns[0]);
+
+    let voter = Paragraph::new(state.voter.as_str())
+        .block(Block::default().borders(Borders::ALL).title(Message::VoterField.text(state.language)));
+    frame.render_widget(voter, rows[0]);
+
+    if by_proxy {
+        let principal = Paragraph::new(state.principal.as_str())
+            .block(Block::default().borders(Borders::ALL).title(Message::PrincipalField.text(state.language)));
+        frame.render_widget(principal, rows[1]);
+    }
+
+    if state.codes_required {
+        let code = Paragraph::new("*".repeat(state.code.chars().count()))
+            .block(Block::default().borders(Borders::ALL).title(Message::VotingCodeField.text(state.language)));
+        frame.render_widget(code, rows[2]);
+    }
+
+    let mut choices : Vec<ListItem> = state.candidates.iter().map(|candidate| ListItem::new(state.label_of(candidate))).collect();
+    choices.push(ListItem::new(Message::BlankChoice.text(state.language)));
+    let mut list_state : ListState = ListState::default();
+    if matches!(state.step, Step::CandidateSelection | Step::Confirmation) {
+        list_state.select(Some(state.selected));
+    }
+    let list = List::new(choices)
+        .block(Block::default().borders(Borders::ALL).title(Message::CandidatesTitle.text(state.language)))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, rows[3], &mut list_state);
+
+    let status : String = match state.step {
+        Step::VoterInput if state.status.is_empty() => Message::VoterFieldHelp.text(state.language),
+        Step::VoterInput => state.status.clone(),
+        Step::PrincipalInput => Message::PrincipalFieldHelp.text(state.language),
+        Step::CodeInput => Message::VotingCodeFieldHelp.text(state.language),
+        Step::CandidateSelection => Message::CandidatesHelp.text(state.language),
+        Step::Confirmation => Message::ConfirmVote(&state.voter_label(), &state.selected_label()).text(state.language),
+    };
+    frame.render_widget(Paragraph::new(status).block(Block::default().borders(Borders::ALL)), rows[4]);
+
+    let results = Paragraph::new(Results::from(&state.scoreboard).render(OutputFormat::Table, state.language))
+        .block(Block::default().borders(Borders::ALL).title(Message::ResultsTitle.text(state.language)));
+    frame.render_widget(results, columns[1]);
+}
+
+pub async fn run(store: Arc<RwLock<dyn Storage>>, audit: AuditLog, language: Language) -> anyhow::Result<()> {
+    let machine : VotingMachine = get_voting_machine(store.clone()).await?;
+    let mut state : TuiState = TuiState::new(machine.scoreboard, language);
+    state.codes_required = machine.roll.is_some();
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let result : anyhow::Result<()> = event_loop(&mut terminal, &mut state, store, &audit).await;
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+async fn event_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, state: &mut TuiState, store: Arc<RwLock<dyn Storage>>, audit: &AuditLog) -> anyhow::Result<()> {
+    loop {
+        if let Ok(scoreboard) = get_scoreboard(store.clone()).await {
+            state.scoreboard = scoreboard;
+        }
+        terminal.draw(|frame| draw(frame, state))?;
+
+        if !event::poll(REFRESH_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match state.handle_key(key.code) {
+            TuiAction::Nothing => {}
+            TuiAction::Quit => return Ok(()),
+            TuiAction::Cast(voter, candidate, code, on_behalf_of) => {
+                let result : StorageResult<Receipt> = vote(store.clone(), VoteForm { voter: voter.clone(), candidate, code, on_behalf_of }).await;
+                state.status = match (audit.record(&AuditEntry::vote("tui", &Voter(voter), &result)), result) {
+                    (Err(error), _) => Message::AuditFailed(&error).text(state.language),
+                    (Ok(()), Ok(Receipt { outcome, tracking_code: Some(tracking_code), .. })) => {
+                        format!("{} {}", Message::Vote(&outcome).text(state.language), Message::TrackingCode(&tracking_code.0).text(state.language))
+                    }
+                    (Ok(()), Ok(receipt)) => Message::Vote(&receipt.outcome).text(state.language),
+                    (Ok(()), Err(error)) => Message::Storage(&error).text(state.language),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests 
+{
+    use crossterm::event::KeyCode;
+
+    use crate::domain::{Candidate, Scoreboard};
+    use crate::messages::Language;
+    use super::{Step, TuiAction, TuiState};
+
+    fn setup_state() -> TuiState
+    {
+        let scoreboard : Scoreboard = Scoreboard::new(vec![
+            Candidate("E.Macron".to_string()),
+            Candidate("M.Lepen".to_string()),
+        ]);
+        TuiState::new(scoreboard, Language::Fr)
+    }
+
+    fn type_voter(state: &mut TuiState, voter: &str)
+    {
+        for character in voter.chars() {
+            state.handle_key(KeyCode::Char(character));
+        }
+        state.handle_key(KeyCode::Enter);
+    }
+
+    fn type_code(state: &mut TuiState, code: &str)
+    {
+        assert_eq!(state.step, Step::CodeInput);
+        for character in code.chars() {
+            state.handle_key(KeyCode::Char(character));
+        }
+        assert_eq!(state.code, code);
+        state.handle_key(KeyCode::Enter);
+    }
+
+    #[test]
+    fn vote_is_cast_after_confirmation()
+    {
+        let mut state : TuiState = setup_state();
+
+        type_voter(&mut state, "Jean");
+        state.handle_key(KeyCode::Down);
+        state.handle_key(KeyCode::Enter);
+        assert_eq!(state.step, Step::Confirmation);
+
+        let action : TuiAction = state.handle_key(KeyCode::Char('o'));
+
+        assert_eq!(action, TuiAction::Cast("Jean".to_string(), "M.Lepen".to_string(), None, None));
+        assert_eq!(state.step, Step::VoterInput);
+        assert!(state.voter.is_empty());
+    }
+
+    #[test]
+    fn refusing_confirmation_returns_to_selection()
+    {
+        let mut state : TuiState = setup_state();
+
+        type_voter(&mut state, "Jean");
+        state.handle_key(KeyCode::Up);
+        state.handle_key(KeyCode::Enter);
+        let action : TuiAction = state.handle_key(KeyCode::Char('n'));
+
+        assert_eq!(action, TuiAction::Nothing);
+        assert_eq!(state.step, Step::CandidateSelection);
+        assert_eq!(state.selected_label(), "Vote blanc");
+    }
+
+    #[test]
+    fn empty_voter_is_not_accepted()
+    {
+        let mut state : TuiState = setup_state();
+
+        state.handle_key(KeyCode::Enter);
+
+        assert_eq!(state.step, Step::VoterInput);
+        assert_eq!(state.handle_key(KeyCode::Esc), TuiAction::Quit);
+    }
+
+    #[test]
+    fn voting_code_is_asked_when_required()
+    {
+        let mut state : TuiState = setup_state();
+        state.codes_required = true;
+
+        type_voter(&mut state, "Jean");
+        assert_eq!(state.step, Step::CodeInput);
+        state.handle_key(KeyCode::Enter);
+        assert_eq!(state.step, Step::CodeInput);
+
+        type_code(&mut state, "ABCD");
+        assert_eq!(state.step, Step::CandidateSelection);
+        state.handle_key(KeyCode::Enter);
+        let action : TuiAction = state.handle_key(KeyCode::Enter);
+
+        assert_eq!(action, TuiAction::Cast("Jean".to_string(), "E.Macron".to_string(), Some("ABCD".to_string()), None));
+        assert!(state.code.is_empty());
+    }
+
+    #[test]
+    fn proxy_holder_names_the_principal()
+    {
+        let mut state : TuiState = setup_state();
+        state.codes_required = true;
+
+        for character in "Marie".chars() {
+            state.handle_key(KeyCode::Char(character));
+        }
+        state.handle_key(KeyCode::Tab);
+        assert_eq!(state.step, Step::PrincipalInput);
+        type_voter(&mut state, "Jean");
+        type_code(&mut state, "ABCD");
+        state.handle_key(KeyCode::Esc);
+        state.handle_key(KeyCode::Esc);
+        assert_eq!(state.step, Step::PrincipalInput);
+        state.handle_key(KeyCode::Enter);
+        state.handle_key(KeyCode::Enter);
+        state.handle_key(KeyCode::Enter);
+        assert_eq!(state.voter_label(), "Jean (par procuration : Marie)");
+
+        let action : TuiAction = state.handle_key(KeyCode::Enter);
+
+        assert_eq!(action, TuiAction::Cast("Marie".to_string(), "E.Macron".to_string(), Some("ABCD".to_string()), Some("Jean".to_string())));
+        assert!(state.principal.is_empty());
+    }
+}
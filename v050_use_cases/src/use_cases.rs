@@ -1,40 +1,642 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
 
+use rand::Rng;
 use serde::Deserialize;
+use tokio::sync::{RwLock, broadcast};
 
-use crate::{domain::{BallotPaper, Candidate, Voter, VotingMachine, VoteOutcome}, storage::Storage};
+use crate::{domain::{BallotBox, BallotPaper, Candidate, Delegation, EncryptedBallot, Issuance, Opening, Proxy, Receipt, Revelation, Scoreboard, Submission, TrackingCode, Voter, VotingMachine, VoteOutcome, Withdrawal, WithdrawalPolicy}, elgamal::SecretKey, trustees::PartialDecryption, storage::{Storage, StorageError, StorageResult, VersionedMachine, archive::{export_archive, import_archive}}};
 
 #[derive(Deserialize)]
 pub struct VoteForm 
 {
     pub voter: String,
     pub candidate: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub on_behalf_of: Option<String>,
 }
 
 impl From<VoteForm> for BallotPaper 
 {
     fn from(form: VoteForm) -> Self 
     {
-        let voter: Voter = Voter(form.voter);
-        let candidate : Option<Candidate>;
-        if form.candidate.is_empty() {
-            candidate = None;
+        let (voter, holder) : (Voter, Option<Voter>) = match form.on_behalf_of {
+            Some(principal) => (Voter(principal), Some(Voter(form.voter))),
+            None => (Voter(form.voter), None),
+        };
+        let candidate : Option<Candidate> = if form.candidate.is_empty() {
+            None
         } else {
-            candidate = Some(Candidate(form.candidate));
-        }
+            Some(Candidate(form.candidate))
+        };
 
         BallotPaper
         {
             voter,
             candidate,
+            code: form.code,
+            holder,
+        }
+    }
+}
+
+pub const MAX_VOTE_ATTEMPTS: usize = 10;
+const RETRY_BACKOFF_MS: u64 = 5;
+
+pub async fn retry_on_conflict<T, F, Fut>(max_attempts: usize, mut operation: F) -> StorageResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = StorageResult<T>>,
+{
+    let mut attempt : usize = 1;
+    loop {
+        match operation().await {
+            Err(StorageError::Conflict(_)) if attempt < max_attempts => 
+            {
+                let backoff : u64 = rand::thread_rng().gen_range(1..=RETRY_BACKOFF_MS * attempt as u64);
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub async fn vote(store: Arc<RwLock<dyn Storage>>, vote_form: VoteForm) -> StorageResult<Receipt> {
+    let ballot_paper : BallotPaper = BallotPaper::from(vote_form);
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || try_vote(store.clone(), ballot_paper.clone())).await
+}
+
+async fn try_vote(store: Arc<RwLock<dyn Storage>>, ballot_paper: BallotPaper) -> StorageResult<Receipt> {
+    let machine : VotingMachine = store.read().await.get_voting_machine().await?;
+    let now : u64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let holder : Option<Voter> = ballot_paper.holder.clone();
+    let outcome : VoteOutcome = machine.vote(ballot_paper, now);
+    if !outcome.is_recorded() {
+        return Ok(Receipt::refused(outcome));
+    }
+
+    let superseded : Option<TrackingCode> = machine.superseded_ballot(outcome.voter()).cloned();
+    let tracking_code : TrackingCode = TrackingCode(generate_code());
+    let sealed : Option<EncryptedBallot> = machine.scoreboard.seal(&outcome, &mut rand::thread_rng());
+    match sealed {
+        Some(sealed) => store.write().await.record_sealed_vote(outcome.voter().clone(), holder, sealed, tracking_code.clone()).await?,
+        None => store.write().await.record_vote(outcome.clone(), holder, tracking_code.clone()).await?,
+    }
+    Ok(Receipt { outcome, tracking_code: Some(tracking_code), superseded })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoteEvent {
+    pub scoreboard: Scoreboard,
+    pub turnout: usize,
+}
+
+impl From<Scoreboard> for VoteEvent {
+    fn from(scoreboard: Scoreboard) -> Self {
+        let turnout : usize = scoreboard.total_votes();
+        VoteEvent { scoreboard, turnout }
+    }
+}
+
+pub type VoteEvents = broadcast::Sender<VoteEvent>;
+
+pub async fn vote_and_publish(store: Arc<RwLock<dyn Storage>>, events: &VoteEvents, vote_form: VoteForm) -> StorageResult<Receipt> {
+    let receipt : Receipt = vote(store.clone(), vote_form).await?;
+
+    if receipt.outcome.is_recorded() {
+        let scoreboard : Scoreboard = get_scoreboard(store).await?;
+        let _ = events.send(VoteEvent::from(scoreboard));
+    }
+
+    Ok(receipt)
+}
+
+pub async fn get_voting_machine(store: Arc<RwLock<dyn Storage>>) -> StorageResult<VotingMachine> {
+    store.read().await.get_voting_machine().await
+}
+
+pub async fn get_ballot_box(store: Arc<RwLock<dyn Storage>>) -> StorageResult<BallotBox> {
+    Ok(store.read().await.get_voting_machine().await?.ballot_box)
+}
+
+pub async fn get_scoreboard(store: Arc<RwLock<dyn Storage>>) -> StorageResult<Scoreboard> {
+    store.read().await.get_scoreboard().await
+}
+
+pub async fn initialize(store: Arc<RwLock<dyn Storage>>, scoreboard: Scoreboard, admin_token: &str, force: bool) -> StorageResult<()> {
+    let mut machine : VotingMachine = VotingMachine::with_scoreboard(scoreboard);
+    machine.protect(admin_token);
+    overwrite(store, machine, force).await
+}
+
+pub async fn close(store: Arc<RwLock<dyn Storage>>) -> StorageResult<VotingMachine> {
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        machine.close();
+        store.write().await.compare_and_put_voting_machine(machine.clone(), revision).await?;
+        Ok(machine)
+    }).await
+}
+
+pub async fn reveal(store: Arc<RwLock<dyn Storage>>, key: &SecretKey) -> StorageResult<Revelation> {
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        let revelation : Revelation = machine.reveal(key, &mut rand::thread_rng());
+        if let Revelation::Revealed(_) = revelation {
+            store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        }
+        Ok(revelation)
+    }).await
+}
+
+pub async fn submit_partial(store: Arc<RwLock<dyn Storage>>, partial: PartialDecryption) -> StorageResult<Submission> {
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        let submission : Submission = machine.submit_partial(partial.clone());
+        if let Submission::Accepted { .. } = submission {
+            store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        }
+        Ok(submission)
+    }).await
+}
+
+pub async fn combine(store: Arc<RwLock<dyn Storage>>) -> StorageResult<Revelation> {
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        let revelation : Revelation = machine.combine();
+        if let Revelation::Revealed(_) = revelation {
+            store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        }
+        Ok(revelation)
+    }).await
+}
+
+pub async fn open(store: Arc<RwLock<dyn Storage>>) -> StorageResult<Opening> {
+    let now : u64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        let opening : Opening = machine.open(now);
+        if opening == Opening::Opened {
+            store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        }
+        Ok(opening)
+    }).await
+}
+
+pub async fn rename_candidate(store: Arc<RwLock<dyn Storage>>, candidate: Candidate, name: &str) -> StorageResult<bool> {
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        if !machine.get_scoreboard().rename(&candidate, name) {
+            return Ok(false);
+        }
+        store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        Ok(true)
+    }).await
+}
+
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_GROUPS: usize = 3;
+const CODE_GROUP_LENGTH: usize = 4;
+
+pub fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    let groups : Vec<String> = (0..CODE_GROUPS)
+        .map(|_| (0..CODE_GROUP_LENGTH).map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char).collect())
+        .collect();
+    groups.join("-")
+}
+
+pub async fn register_proxy(store: Arc<RwLock<dyn Storage>>, principal: Voter, proxy: Proxy) -> StorageResult<Delegation> {
+    let now : u64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        let delegation : Delegation = machine.register_proxy(principal.clone(), proxy.clone(), now);
+        if let Delegation::Registered(_, _) = delegation {
+            store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        }
+        Ok(delegation)
+    }).await
+}
+
+pub async fn issue_codes(store: Arc<RwLock<dyn Storage>>, voters: Vec<Voter>, force: bool) -> StorageResult<Issuance> {
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        let holders : Vec<Voter> = voters.iter().filter(|voter| !force && machine.holds_code(voter)).cloned().collect();
+        if !holders.is_empty() {
+            return Ok(Issuance::AlreadyIssued(holders));
+        }
+        let codes : Vec<(Voter, String)> = voters
+            .iter()
+            .filter(|voter| !machine.voters.attendees.contains(voter))
+            .map(|voter| (voter.clone(), generate_code()))
+            .collect();
+        for (voter, code) in &codes {
+            machine.issue_code(voter.clone(), code);
         }
+        store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        Ok(Issuance::Issued(codes))
+    }).await
+}
+
+pub async fn withdraw_candidate(store: Arc<RwLock<dyn Storage>>, candidate: Candidate, policy: WithdrawalPolicy) -> StorageResult<Withdrawal> {
+    retry_on_conflict(MAX_VOTE_ATTEMPTS, || async {
+        let VersionedMachine { mut machine, revision } = store.read().await.get_versioned_voting_machine().await?;
+        let withdrawal : Withdrawal = machine.withdraw(&candidate, policy);
+        if let Withdrawal::Withdrawn(_) = withdrawal {
+            store.write().await.compare_and_put_voting_machine(machine, revision).await?;
+        }
+        Ok(withdrawal)
+    }).await
+}
+
+pub async fn backup(store: Arc<RwLock<dyn Storage>>, filepath: &str) -> StorageResult<()> {
+    let machine : VotingMachine = store.read().await.get_voting_machine().await?;
+    export_archive(machine, filepath).await
+}
+
+pub async fn restore(store: Arc<RwLock<dyn Storage>>, filepath: &str, force: bool) -> StorageResult<()> {
+    let restored_machine : VotingMachine = import_archive(filepath).await?;
+    overwrite(store, restored_machine, force).await
+}
+
+pub async fn migrate(source: Arc<RwLock<dyn Storage>>, target: Arc<RwLock<dyn Storage>>, force: bool) -> StorageResult<VotingMachine> {
+    let machine : VotingMachine = source.read().await.get_voting_machine().await?;
+    overwrite(target.clone(), machine, force).await?;
+
+    let source_machine : VotingMachine = source.read().await.get_voting_machine().await?;
+    let target_machine : VotingMachine = target.read().await.get_voting_machine().await?;
+    if source_machine != target_machine {
+        return Err(StorageError::Conflict("the target storage does not hold the same election after migration".to_string()));
     }
+
+    Ok(target_machine)
 }
 
-pub async fn vote(store: Arc<dyn Storage>, vote_form: VoteForm) -> anyhow::Result<VoteOutcome> {
-    Ok(store.get_voting_machine().await?.vote(BallotPaper::from(vote_form)))
+async fn overwrite(store: Arc<RwLock<dyn Storage>>, machine: VotingMachine, force: bool) -> StorageResult<()> {
+    let mut store = store.write().await;
+    let current : VersionedMachine = store.get_versioned_voting_machine().await?;
+    if !force && current.machine.has_votes() {
+        return Err(StorageError::Conflict("an election is already in progress in this storage, use --force to overwrite it".to_string()));
+    }
+
+    store.compare_and_put_voting_machine(machine, current.revision).await?;
+    Ok(())
 }
 
-pub async fn get_voting_machine(store: Arc<dyn Storage>) -> anyhow::Result<VotingMachine> {
-    Ok(store.get_voting_machine().await?)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests 
+{
+    use std::{fs, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+    use rand::{SeedableRng, rngs::StdRng};
+    use tokio::sync::RwLock;
+
+    use crate::elgamal::{Group, SecretKey};
+    use crate::domain::{VotingMachine, Ballot, BallotBox, Candidate, InvalidReason, Issuance, Opening, Receipt, Revelation, RevotePolicy, Schedule, Scoreboard, TrackingCode, VoteOutcome, Voter, Withdrawal, WithdrawalPolicy};
+    use crate::storage::{Storage, memory::MemoryStore, file::{FileStore, secret_path}};
+    use crate::storage::StorageError;
+    use super::{VoteForm, VoteEvent, close, open, reveal, vote, vote_and_publish, get_ballot_box, get_voting_machine, get_scoreboard, backup, restore, migrate, rename_candidate, withdraw_candidate, issue_codes, retry_on_conflict};
+
+    fn setup_store() -> Arc<RwLock<dyn Storage>>
+    {
+        let candidates : Vec<Candidate> = vec![
+            Candidate("E.Macron".to_string()),
+            Candidate("M.Lepen".to_string()),
+            Candidate("JL.Mélanchon".to_string()),
+        ];
+        Arc::new(RwLock::new(MemoryStore::new(VotingMachine::new(candidates))))
+    }
+
+    fn vote_form(voter: &str, candidate: &str) -> VoteForm
+    {
+        VoteForm { voter: voter.to_string(), candidate: candidate.to_string(), code: None, on_behalf_of: None }
+    }
+
+    #[tokio::test]
+    async fn vote_is_stored() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+
+        let outcome : VoteOutcome = vote(store.clone(), vote_form("Jean", "E.Macron")).await?.outcome;
+        let mut machine : VotingMachine = get_voting_machine(store.clone()).await?;
+
+        assert_eq!(outcome, VoteOutcome::AcceptedVote(Voter("Jean".to_string()), Candidate("E.Macron".to_string())));
+        assert!(machine.get_voters().attendees.contains(&Voter("Jean".to_string())));
+        assert_eq!(machine.get_scoreboard().scores[&Candidate("E.Macron".to_string())].0, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn receipt_tracks_the_ballot_without_the_voter() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+
+        let receipt : Receipt = vote(store.clone(), vote_form("Jean", "M.Lepen")).await?;
+        let refused : Receipt = vote(store.clone(), vote_form("Jean", "E.Macron")).await?;
+        let ballot_box : BallotBox = get_ballot_box(store.clone()).await?;
+        let tracking_code : TrackingCode = receipt.tracking_code.unwrap_or_else(|| TrackingCode(String::new()));
+
+        assert_eq!(ballot_box.lookup(&tracking_code.0.to_lowercase()), Some(&Ballot::Candidate(Candidate("M.Lepen".to_string()))));
+        assert_eq!(refused.tracking_code, None);
+        assert_eq!(ballot_box.0.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_refuses_to_overwrite_election() -> anyhow::Result<()> 
+    {
+        let source = setup_store();
+        let target = setup_store();
+        let filepath : String = std::env::temp_dir().join("use_cases_restore.json").display().to_string();
+
+        vote(source.clone(), vote_form("Jean", "E.Macron")).await?;
+        vote(target.clone(), vote_form("Paul", "M.Lepen")).await?;
+        backup(source.clone(), &filepath).await?;
+
+        let refused = restore(target.clone(), &filepath, false).await;
+        let forced = restore(target.clone(), &filepath, true).await;
+
+        fs::remove_file(&filepath)?;
+        let _ = fs::remove_file(secret_path(&filepath));
+
+        assert!(refused.is_err());
+        assert!(forced.is_ok());
+        assert_eq!(get_voting_machine(target).await?, get_voting_machine(source).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_copies_election_to_file() -> anyhow::Result<()> 
+    {
+        let source = setup_store();
+        let filepath : String = std::env::temp_dir().join("use_cases_migrate.txt").display().to_string();
+        let empty_machine : VotingMachine = get_voting_machine(source.clone()).await?;
+
+        vote(source.clone(), vote_form("Jean", "E.Macron")).await?;
+        vote(source.clone(), vote_form("Paul", "")).await?;
+
+        let target : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(FileStore::new(&empty_machine, &filepath).await?));
+        let migrated_machine = migrate(source.clone(), target.clone(), false).await;
+        let refused = migrate(setup_store(), target.clone(), false).await;
+
+        fs::remove_file(&filepath)?;
+        let _ = fs::remove_file(secret_path(&filepath));
+
+        assert_eq!(migrated_machine?, get_voting_machine(source).await?);
+        assert!(refused.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn conflicting_operation_is_retried() -> anyhow::Result<()> 
+    {
+        let mut calls : usize = 0;
+
+        let outcome = retry_on_conflict(3, || {
+            calls += 1;
+            let current_call : usize = calls;
+            async move {
+                if current_call < 3 { Err(StorageError::Conflict("stale".to_string())) } else { Ok(current_call) }
+            }
+        }).await;
+
+        assert_eq!(outcome?, 3);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_votes_are_not_lost() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+
+        let mut handles = Vec::new();
+        for index in 0..8 {
+            let store = store.clone();
+            handles.push(tokio::spawn(async move { vote(store, vote_form(&format!("Votant{}", index), "M.Lepen")).await }));
+        }
+        for handle in handles {
+            handle.await??;
+        }
+
+        let mut machine : VotingMachine = get_voting_machine(store).await?;
+        assert_eq!(machine.get_scoreboard().scores[&Candidate("M.Lepen".to_string())].0, 8);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recorded_votes_are_published() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+        let (events, mut receiver) = tokio::sync::broadcast::channel::<VoteEvent>(16);
+
+        vote_and_publish(store.clone(), &events, vote_form("Jean", "E.Macron")).await?;
+        vote_and_publish(store.clone(), &events, vote_form("Jean", "M.Lepen")).await?;
+        vote_and_publish(store.clone(), &events, vote_form("Paul", "")).await?;
+
+        let first_event : VoteEvent = receiver.recv().await?;
+        let second_event : VoteEvent = receiver.recv().await?;
+
+        assert_eq!(first_event.turnout, 1);
+        assert_eq!(second_event.turnout, 2);
+        assert_eq!(second_event.scoreboard.blank_scores.0, 1);
+        assert!(receiver.try_recv().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn closed_election_refuses_votes() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+
+        vote(store.clone(), vote_form("Jean", "E.Macron")).await?;
+        close(store.clone()).await?;
+        let outcome : VoteOutcome = vote(store.clone(), vote_form("Paul", "E.Macron")).await?.outcome;
+
+        assert_eq!(outcome, VoteOutcome::ElectionClosed(Voter("Paul".to_string())));
+        assert_eq!(get_scoreboard(store).await?.total_votes(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn schedule_bounds_the_voting_period() -> anyhow::Result<()> 
+    {
+        let now : u64 = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut scoreboard : Scoreboard = Scoreboard::new(vec![Candidate("E.Macron".to_string())]);
+        scoreboard.schedule = Some(Schedule { opens_at: now + 3600, closes_at: now + 7200 });
+        let upcoming : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(MemoryStore::new(VotingMachine::with_scoreboard(scoreboard.clone()))));
+        scoreboard.schedule = Some(Schedule { opens_at: now - 7200, closes_at: now - 3600 });
+        let past : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(MemoryStore::new(VotingMachine::with_scoreboard(scoreboard))));
+
+        let early : VoteOutcome = vote(upcoming.clone(), vote_form("Jean", "E.Macron")).await?.outcome;
+        let late : VoteOutcome = vote(past.clone(), vote_form("Jean", "E.Macron")).await?.outcome;
+
+        assert_eq!(early, VoteOutcome::NotYetOpen(Voter("Jean".to_string())));
+        assert_eq!(late, VoteOutcome::ElectionClosed(Voter("Jean".to_string())));
+        assert_eq!(get_scoreboard(upcoming).await?.total_votes() + get_scoreboard(past).await?.total_votes(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn opening_lets_voters_in_before_the_schedule() -> anyhow::Result<()> 
+    {
+        let now : u64 = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut scoreboard : Scoreboard = Scoreboard::new(vec![Candidate("E.Macron".to_string())]);
+        scoreboard.schedule = Some(Schedule { opens_at: now + 3600, closes_at: now + 7200 });
+        let store : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(MemoryStore::new(VotingMachine::with_scoreboard(scoreboard))));
+
+        assert_eq!(open(store.clone()).await?, Opening::Opened);
+        assert_eq!(open(store.clone()).await?, Opening::AlreadyOpen);
+        let outcome : VoteOutcome = vote(store.clone(), vote_form("Jean", "E.Macron")).await?.outcome;
+
+        assert_eq!(outcome, VoteOutcome::AcceptedVote(Voter("Jean".to_string()), Candidate("E.Macron".to_string())));
+        close(store.clone()).await?;
+        assert_eq!(open(store).await?, Opening::Closed);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renamed_candidate_keeps_its_votes() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+        let candidate : Candidate = Candidate("E.Macron".to_string());
+
+        vote(store.clone(), vote_form("Jean", "E.Macron")).await?;
+        assert!(rename_candidate(store.clone(), candidate.clone(), "Emmanuel Macron").await?);
+        assert!(!rename_candidate(store.clone(), Candidate("J.Chirac".to_string()), "Jacques Chirac").await?);
+        vote(store.clone(), vote_form("Paul", "E.Macron")).await?;
+
+        let scoreboard : Scoreboard = get_scoreboard(store).await?;
+        assert_eq!(scoreboard.display_name(&candidate), "Emmanuel Macron");
+        assert_eq!(scoreboard.scores[&candidate].0, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn withdrawal_is_persisted_and_refuses_new_votes() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+        let candidate : Candidate = Candidate("E.Macron".to_string());
+
+        vote(store.clone(), vote_form("Jean", "E.Macron")).await?;
+        let withdrawal : Withdrawal = withdraw_candidate(store.clone(), candidate.clone(), WithdrawalPolicy::Keep).await?;
+        let outcome : VoteOutcome = vote(store.clone(), vote_form("Paul", "E.Macron")).await?.outcome;
+
+        let scoreboard : Scoreboard = get_scoreboard(store).await?;
+        assert_eq!(withdrawal, Withdrawal::Withdrawn(candidate.clone()));
+        assert_eq!(outcome, VoteOutcome::InvalidVote(Voter("Paul".to_string()), InvalidReason::WithdrawnCandidate));
+        assert!(scoreboard.is_withdrawn(&candidate));
+        assert_eq!(scoreboard.scores[&candidate].0, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn issued_codes_are_required_to_vote() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+        let jean : Voter = Voter("Jean".to_string());
+
+        let Issuance::Issued(codes) = issue_codes(store.clone(), vec![jean.clone(), Voter("Paul".to_string())], false).await? else {
+            panic!("codes should be issued to new voters");
+        };
+        let jean_code : String = codes.iter().find(|(voter, _)| *voter == jean).map(|(_, code)| code.clone()).unwrap_or_default();
+
+        let missing : VoteOutcome = vote(store.clone(), vote_form("Jean", "E.Macron")).await?.outcome;
+        let accepted : VoteOutcome = vote(store.clone(), VoteForm { code: Some(jean_code.clone()), ..vote_form("Jean", "E.Macron") }).await?.outcome;
+        let reused : VoteOutcome = vote(store.clone(), VoteForm { code: Some(jean_code), ..vote_form("Paul", "E.Macron") }).await?.outcome;
+        let machine : VotingMachine = get_voting_machine(store).await?;
+
+        assert_eq!(codes.len(), 2);
+        assert_eq!(missing, VoteOutcome::MissingCode(jean.clone()));
+        assert_eq!(accepted, VoteOutcome::AcceptedVote(jean, Candidate("E.Macron".to_string())));
+        assert_eq!(reused, VoteOutcome::WrongCode(Voter("Paul".to_string())));
+        assert!(machine.roll.is_some_and(|roll| roll.0.values().all(|code_hash| code_hash.0.len() == 64)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn issued_codes_are_only_replaced_when_forced() -> anyhow::Result<()> 
+    {
+        let store = setup_store();
+        let jean : Voter = Voter("Jean".to_string());
+
+        let first : Issuance = issue_codes(store.clone(), vec![jean.clone()], false).await?;
+        let refused : Issuance = issue_codes(store.clone(), vec![jean.clone(), Voter("Paul".to_string())], false).await?;
+        let forced : Issuance = issue_codes(store.clone(), vec![jean.clone()], true).await?;
+
+        let (Issuance::Issued(first), Issuance::Issued(forced)) = (first, forced) else {
+            panic!("codes should be issued");
+        };
+        let stale : VoteOutcome = vote(store.clone(), VoteForm { code: Some(first[0].1.clone()), ..vote_form("Jean", "E.Macron") }).await?.outcome;
+
+        assert_eq!(refused, Issuance::AlreadyIssued(vec![jean.clone()]));
+        assert!(!get_voting_machine(store.clone()).await?.holds_code(&Voter("Paul".to_string())));
+        assert_eq!(forced.len(), 1);
+        assert_eq!(stale, VoteOutcome::WrongCode(jean));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encrypted_ballots_are_only_counted_when_revealed() -> anyhow::Result<()> 
+    {
+        let filepath : String = std::env::temp_dir().join("use_cases_encrypted.txt").display().to_string();
+        let _ = fs::remove_file(format!("{}.journal", filepath));
+        let _ = fs::remove_file(format!("{}.ballots", filepath));
+        let mut rng : StdRng = StdRng::seed_from_u64(47);
+        let key : SecretKey = SecretKey::generate(Group::election(), &mut rng);
+        let mut scoreboard : Scoreboard = Scoreboard::new(vec![Candidate("E.Macron".to_string()), Candidate("M.Lepen".to_string())]);
+        scoreboard.election_key = Some(key.public_key());
+
+        let store : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(FileStore::new(&VotingMachine::with_scoreboard(scoreboard), &filepath).await?));
+        vote(store.clone(), vote_form("Jean", "E.Macron")).await?;
+        vote(store.clone(), vote_form("Paul", "E.Macron")).await?;
+        vote(store.clone(), vote_form("Marie", "")).await?;
+
+        let reopened : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(FileStore::open(&filepath).await?));
+        let sealed : VotingMachine = get_voting_machine(reopened.clone()).await?;
+        let still_open : Revelation = reveal(reopened.clone(), &key).await?;
+        close(reopened.clone()).await?;
+        let wrong_key : Revelation = reveal(reopened.clone(), &SecretKey::generate(Group::election(), &mut rng)).await?;
+        let revealed : Revelation = reveal(reopened.clone(), &key).await?;
+        let scoreboard : Scoreboard = get_scoreboard(reopened).await?;
+        fs::remove_file(&filepath)?;
+        let _ = fs::remove_file(secret_path(&filepath));
+
+        assert_eq!((sealed.scoreboard.expressed_votes(), sealed.scoreboard.sealed_scores.0, sealed.scoreboard.total_votes()), (0, 3, 3));
+        assert!(sealed.ballot_box.0.values().all(|ballot| matches!(ballot, Ballot::Sealed(_))));
+        assert_eq!((still_open, wrong_key, revealed), (Revelation::StillOpen, Revelation::WrongKey, Revelation::Revealed(3)));
+        assert_eq!(scoreboard.scores[&Candidate("E.Macron".to_string())].0, 2);
+        assert_eq!((scoreboard.blank_scores.0, scoreboard.sealed_scores.0, scoreboard.total_votes()), (1, 0, 3));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn revoting_supersedes_the_journaled_ballot() -> anyhow::Result<()> 
+    {
+        let filepath : String = std::env::temp_dir().join("use_cases_revote.txt").display().to_string();
+        let _ = fs::remove_file(format!("{}.journal", filepath));
+        let _ = fs::remove_file(format!("{}.ballots", filepath));
+        let mut scoreboard : Scoreboard = Scoreboard::new(vec![Candidate("E.Macron".to_string()), Candidate("M.Lepen".to_string())]);
+        scoreboard.revote = RevotePolicy::Supersede;
+
+        let store : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(FileStore::new(&VotingMachine::with_scoreboard(scoreboard), &filepath).await?));
+        let first : Receipt = vote(store.clone(), vote_form("Jean", "E.Macron")).await?;
+        let second : Receipt = vote(store.clone(), vote_form("Jean", "M.Lepen")).await?;
+        vote(store.clone(), vote_form("Paul", "E.Macron")).await?;
+
+        let reopened : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(FileStore::open(&filepath).await?));
+        let machine : VotingMachine = get_voting_machine(reopened).await?;
+        fs::remove_file(&filepath)?;
+        let _ = fs::remove_file(secret_path(&filepath));
+
+        assert_eq!(second.outcome, VoteOutcome::AcceptedVote(Voter("Jean".to_string()), Candidate("M.Lepen".to_string())));
+        assert_eq!(second.superseded, first.tracking_code);
+        assert_eq!(machine.ballot_box.0.len(), 2);
+        assert!(!machine.ballot_box.contains(&first.tracking_code.unwrap_or(TrackingCode(String::new()))));
+        assert_eq!(machine.scoreboard.scores[&Candidate("E.Macron".to_string())].0, 1);
+        assert_eq!(machine.scoreboard.scores[&Candidate("M.Lepen".to_string())].0, 1);
+        assert_eq!((machine.voters.attendees.len(), machine.scoreboard.total_votes()), (2, 2));
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::BTreeMap as Map, sync::Arc, time::Duration};
+
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, sync::RwLock};
+use v050_use_cases::{admin::AdminCredential, audit::AuditLog, bulletin::{InclusionProof, PublishedBulletin}, domain::{Candidate, VotingMachine}, server::{AppState, serve}, storage::{Storage, memory::MemoryStore}};
+
+async fn start_server() -> anyhow::Result<String>
+{
+    start_server_with(|state| state.with_admin(Some(AdminCredential::new("s3cret")), AuditLog::disabled())).await
+}
+
+async fn start_server_with(configure: impl FnOnce(AppState) -> AppState) -> anyhow::Result<String>
+{
+    let candidates : Vec<Candidate> = vec![
+        Candidate("E.Macron".to_string()),
+        Candidate("M.Lepen".to_string()),
+        Candidate("JL.Mélanchon".to_string()),
+    ];
+    let mut machine : VotingMachine = VotingMachine::new(candidates);
+    machine.protect("s3cret");
+    let store : Arc<RwLock<dyn Storage>> = Arc::new(RwLock::new(MemoryStore::new(machine)));
+
+    let listener : TcpListener = TcpListener::bind("127.0.0.1:0").await?;
+    let address : String = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(serve(listener, configure(AppState::new(store))));
+    Ok(address)
+}
+
+#[tokio::test]
+async fn vote_is_counted_in_scores() -> anyhow::Result<()> 
+{
+    let address : String = start_server().await?;
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Jean", "candidate": "E.Macron" }))
+        .send().await?;
+    assert_eq!(response.status(), 200);
+    let body : Value = response.json().await?;
+    assert_eq!((&body["voter"], &body["outcome"]), (&json!("Jean"), &json!("accepted")));
+    assert_eq!(body["tracking_code"].as_str().map(str::len), Some(14));
+
+    client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Paul", "candidate": "" }))
+        .send().await?;
+
+    let scores : Value = client.get(format!("{}/scores", address)).bearer_auth("s3cret").send().await?.json().await?;
+    let expected_scores : Map<&str, usize> = Map::from([("E.Macron", 1), ("M.Lepen", 0), ("JL.Mélanchon", 0)]);
+    assert_eq!(scores["scores"], json!(expected_scores));
+    assert_eq!(scores["blank_scores"], 1);
+
+    let voters : Vec<String> = client.get(format!("{}/voters", address)).bearer_auth("s3cret").send().await?.json().await?;
+    assert_eq!(voters, vec!["Jean".to_string(), "Paul".to_string()]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn published_bulletin_proves_each_ballot() -> anyhow::Result<()> 
+{
+    let address : String = start_server().await?;
+    let client = reqwest::Client::new();
+
+    let mut tracking_codes : Vec<String> = Vec::new();
+    for (voter, candidate) in [("Jean", "E.Macron"), ("Paul", "M.Lepen"), ("Marie", "E.Macron")]
+    {
+        let body : Value = client.post(format!("{}/vote", address))
+            .json(&json!({ "voter": voter, "candidate": candidate }))
+            .send().await?
+            .json().await?;
+        tracking_codes.push(body["tracking_code"].as_str().unwrap_or_default().to_string());
+    }
+
+    let unpublished = client.get(format!("{}/bulletin", address)).send().await?;
+    assert_eq!(unpublished.status(), 409);
+    let early_proof = client.get(format!("{}/bulletin/{}", address, tracking_codes[0])).send().await?;
+    assert_eq!(early_proof.status(), 409);
+
+    client.post(format!("{}/admin/close", address)).bearer_auth("s3cret").send().await?;
+
+    let bulletin : PublishedBulletin = client.get(format!("{}/bulletin", address)).send().await?.json().await?;
+    assert_eq!(bulletin.ballots.len(), 3);
+    assert_eq!(bulletin.verify(), Ok(()));
+
+    for tracking_code in &tracking_codes
+    {
+        let proof : InclusionProof = client.get(format!("{}/bulletin/{}", address, tracking_code.to_lowercase())).send().await?.json().await?;
+        assert_eq!(&proof.entry.tracking_code, tracking_code);
+        assert_eq!(bulletin.verify_proof(&proof), Ok(()));
+    }
+
+    let missing = client.get(format!("{}/bulletin/AAAA-BBBB-CCCC", address)).send().await?;
+    assert_eq!(missing.status(), 404);
+    Ok(())
+}
+
+#[tokio::test]
+async fn second_vote_is_rejected() -> anyhow::Result<()> 
+{
+    let address : String = start_server().await?;
+    let client = reqwest::Client::new();
+
+    client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Jean", "candidate": "M.Lepen" }))
+        .send().await?;
+
+    let response = client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Jean", "candidate": "E.Macron" }))
+        .send().await?;
+    assert_eq!(response.status(), 409);
+    assert_eq!(response.json::<Value>().await?["outcome"], "already_voted");
+
+    let scores : Value = client.get(format!("{}/scores", address)).bearer_auth("s3cret").send().await?.json().await?;
+    assert_eq!(scores["scores"]["M.Lepen"], 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn malformed_vote_is_refused() -> anyhow::Result<()> 
+{
+    let address : String = start_server().await?;
+
+    let response = reqwest::Client::new().post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Jean" }))
+        .send().await?;
+
+    assert!(response.status().is_client_error());
+    Ok(())
+}
+
+#[tokio::test]
+async fn results_are_streamed_after_each_vote() -> anyhow::Result<()> 
+{
+    let address : String = start_server().await?;
+    let client = reqwest::Client::new();
+
+    let hidden = client.get(format!("{}/results/stream", address)).send().await?;
+    assert_eq!(hidden.status(), 401);
+
+    let mut stream = client.get(format!("{}/results/stream", address)).bearer_auth("s3cret").send().await?;
+    client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Jean", "candidate": "JL.Mélanchon" }))
+        .send().await?;
+
+    let mut received : String = String::new();
+    while !received.contains("\"turnout\":1") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.chunk()).await??;
+        match chunk {
+            Some(bytes) => received.push_str(&String::from_utf8_lossy(&bytes)),
+            None => break,
+        }
+    }
+
+    assert!(received.contains("\"turnout\":0"));
+    assert!(received.contains("\"JL.Mélanchon\":1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_routes_require_the_admin_token() -> anyhow::Result<()> 
+{
+    let audit_path = std::env::temp_dir().join(format!("v050_http_admin_audit_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&audit_path);
+    let audit : AuditLog = AuditLog::new(audit_path.to_str().unwrap_or_default());
+    let address : String = start_server_with(|state| state.with_admin(Some(AdminCredential::new("s3cret")), audit)).await?;
+    let client = reqwest::Client::new();
+
+    let refused = client.post(format!("{}/admin/voters", address))
+        .json(&json!({ "voter": "Jean" }))
+        .send().await?;
+    assert_eq!(refused.status(), 401);
+
+    let wrong = client.post(format!("{}/admin/close", address))
+        .bearer_auth("guess")
+        .send().await?;
+    assert_eq!(wrong.status(), 401);
+
+    let hidden = client.get(format!("{}/scores", address)).send().await?;
+    assert_eq!(hidden.status(), 401);
+
+    let registered : Value = client.post(format!("{}/admin/voters", address))
+        .bearer_auth("s3cret")
+        .json(&json!({ "voter": "Jean" }))
+        .send().await?
+        .json().await?;
+    assert_eq!(registered["voter"], "Jean");
+
+    let vote = client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Jean", "candidate": "E.Macron", "code": registered["code"] }))
+        .send().await?;
+    assert_eq!(vote.status(), 200);
+
+    let closed = client.post(format!("{}/admin/close", address))
+        .bearer_auth("s3cret")
+        .send().await?;
+    assert_eq!(closed.status(), 200);
+
+    let reopened = client.post(format!("{}/admin/open", address))
+        .bearer_auth("s3cret")
+        .send().await?;
+    assert_eq!(reopened.status(), 409);
+
+    let voters = client.get(format!("{}/voters", address)).bearer_auth("s3cret").send().await?;
+    assert_eq!(voters.status(), 200);
+
+    let audit_lines : Vec<Value> = std::fs::read_to_string(&audit_path)?
+        .lines()
+        .map(serde_json::from_str::<Value>)
+        .collect::<Result<_, _>>()?;
+    let broken_link : Option<usize> = AuditLog::new(audit_path.to_str().unwrap_or_default()).broken_link()?;
+    std::fs::remove_file(&audit_path)?;
+    assert_eq!(broken_link, None);
+    let outcomes : Vec<(&str, &str)> = audit_lines.iter()
+        .map(|entry| (entry["action"].as_str().unwrap_or_default(), entry["outcome"].as_str().unwrap_or_default()))
+        .collect();
+    assert_eq!(outcomes, vec![("add_voter", "denied"), ("close", "denied"), ("scores", "denied"), ("add_voter", "succeeded"), ("vote", "succeeded"), ("close", "succeeded"), ("open", "failed"), ("voters", "succeeded")]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn proxy_holder_votes_on_behalf_of_the_principal() -> anyhow::Result<()> 
+{
+    let audit_path = std::env::temp_dir().join(format!("v050_http_proxy_audit_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&audit_path);
+    let audit : AuditLog = AuditLog::new(audit_path.to_str().unwrap_or_default());
+    let address : String = start_server_with(|state| state.with_admin(Some(AdminCredential::new("s3cret")), audit)).await?;
+    let client = reqwest::Client::new();
+
+    let registered : Value = client.post(format!("{}/admin/proxies", address))
+        .bearer_auth("s3cret")
+        .json(&json!({ "principal": "Jean", "holder": "Marie", "valid_until": u64::MAX }))
+        .send().await?
+        .json().await?;
+    assert_eq!(registered["outcome"], "registered");
+
+    let exceeded : Value = client.post(format!("{}/admin/proxies", address))
+        .bearer_auth("s3cret")
+        .json(&json!({ "principal": "Paul", "holder": "Marie", "valid_until": u64::MAX }))
+        .send().await?
+        .json().await?;
+    assert_eq!(exceeded["outcome"], "too_many_proxies");
+
+    let usurped = client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Paul", "candidate": "M.Lepen", "on_behalf_of": "Jean" }))
+        .send().await?;
+    assert_eq!(usurped.status(), 403);
+    assert_eq!(usurped.json::<Value>().await?["outcome"], "invalid_proxy");
+
+    let delegated = client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Marie", "candidate": "E.Macron", "on_behalf_of": "Jean" }))
+        .send().await?;
+    assert_eq!(delegated.status(), 200);
+    assert_eq!(delegated.json::<Value>().await?["voter"], "Jean");
+
+    let in_person = client.post(format!("{}/vote", address))
+        .json(&json!({ "voter": "Jean", "candidate": "M.Lepen" }))
+        .send().await?;
+    assert_eq!(in_person.status(), 409);
+
+    let voters : Vec<String> = client.get(format!("{}/voters", address)).bearer_auth("s3cret").send().await?.json().await?;
+    let scores : Value = client.get(format!("{}/scores", address)).bearer_auth("s3cret").send().await?.json().await?;
+    std::fs::remove_file(&audit_path)?;
+    assert_eq!(voters, vec!["Jean"]);
+    assert_eq!(scores["scores"]["E.Macron"], 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_routes_are_disabled_without_a_token() -> anyhow::Result<()> 
+{
+    let address : String = start_server_with(|state| state).await?;
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{}/admin/export", address))
+        .bearer_auth("anything")
+        .send().await?;
+    assert_eq!(response.status(), 403);
+
+    let voters = client.get(format!("{}/voters", address)).send().await?;
+    assert_eq!(voters.status(), 403);
+    Ok(())
+}
+
+#[tokio::test]
+async fn restarting_with_another_token_does_not_grant_admin() -> anyhow::Result<()> 
+{
+    let address : String = start_server_with(|state| state.with_admin(Some(AdminCredential::new("intruder")), AuditLog::disabled())).await?;
+    let client = reqwest::Client::new();
+
+    let close = client.post(format!("{}/admin/close", address))
+        .bearer_auth("intruder")
+        .send().await?;
+    assert_eq!(close.status(), 401);
+
+    let scores = client.get(format!("{}/scores", address)).bearer_auth("intruder").send().await?;
+    assert_eq!(scores.status(), 401);
+    Ok(())
+}